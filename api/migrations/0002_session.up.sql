@@ -0,0 +1,19 @@
+CREATE TABLE session (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id INTEGER NOT NULL REFERENCES "user" (id) ON DELETE CASCADE,
+    device_name TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    last_used_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    revoked_at TIMESTAMPTZ
+);
+
+CREATE INDEX session_user_id_idx ON session (user_id);
+
+CREATE TABLE refresh_token (
+    id SERIAL PRIMARY KEY,
+    session_id UUID NOT NULL REFERENCES session (id) ON DELETE CASCADE,
+    token_hash TEXT UNIQUE NOT NULL,
+    issued_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    expires_at TIMESTAMPTZ NOT NULL,
+    used_at TIMESTAMPTZ
+);
@@ -0,0 +1,10 @@
+CREATE TABLE device_credential (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    pot_id INTEGER NOT NULL REFERENCES pot (id) ON DELETE CASCADE,
+    secret_hash TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    last_used_at TIMESTAMPTZ,
+    revoked_at TIMESTAMPTZ
+);
+
+CREATE INDEX device_credential_pot_id_idx ON device_credential (pot_id);
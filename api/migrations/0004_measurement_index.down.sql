@@ -0,0 +1 @@
+DROP INDEX measurement_pot_timestamp_idx;
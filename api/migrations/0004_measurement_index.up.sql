@@ -0,0 +1 @@
+CREATE INDEX measurement_pot_timestamp_idx ON measurement (pot_id, timestamp DESC, id DESC);
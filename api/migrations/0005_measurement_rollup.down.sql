@@ -0,0 +1,5 @@
+DROP TRIGGER measurement_rollup_queue_insert ON measurement;
+DROP FUNCTION queue_measurement_rollup();
+DROP TABLE measurement_rollup_queue;
+DROP TABLE measurement_rollup_state;
+DROP TABLE measurement_rollup;
@@ -0,0 +1,45 @@
+-- Per-pot aggregates of the measurement table, maintained incrementally by a
+-- background task. New measurements are queued by a trigger and removed from
+-- the queue once they have been rolled up, so rows committed out of id order
+-- are not skipped.
+CREATE TABLE measurement_rollup (
+    pot_id INTEGER NOT NULL REFERENCES pot (id) ON DELETE CASCADE,
+    bucket TEXT NOT NULL CHECK (bucket IN ('hour', 'day')),
+    bucket_start TIMESTAMP NOT NULL,
+    count INTEGER NOT NULL,
+    soil_moisture_min REAL NOT NULL,
+    soil_moisture_max REAL NOT NULL,
+    soil_moisture_avg REAL NOT NULL,
+    temperature_min REAL NOT NULL,
+    temperature_max REAL NOT NULL,
+    temperature_avg REAL NOT NULL,
+    light_level_min REAL NOT NULL,
+    light_level_max REAL NOT NULL,
+    light_level_avg REAL NOT NULL,
+    humidity_min REAL NOT NULL,
+    humidity_max REAL NOT NULL,
+    humidity_avg REAL NOT NULL,
+    PRIMARY KEY (pot_id, bucket, bucket_start)
+);
+
+CREATE TABLE measurement_rollup_state (
+    id BOOLEAN PRIMARY KEY DEFAULT TRUE CHECK (id),
+    refreshed_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+INSERT INTO measurement_rollup_state DEFAULT VALUES;
+
+CREATE TABLE measurement_rollup_queue (
+    measurement_id INTEGER PRIMARY KEY REFERENCES measurement (id) ON DELETE CASCADE
+);
+
+CREATE FUNCTION queue_measurement_rollup() RETURNS trigger AS $$
+BEGIN
+    INSERT INTO measurement_rollup_queue (measurement_id) VALUES (NEW.id);
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER measurement_rollup_queue_insert
+    AFTER INSERT ON measurement
+    FOR EACH ROW EXECUTE FUNCTION queue_measurement_rollup();
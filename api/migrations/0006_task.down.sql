@@ -0,0 +1,15 @@
+DROP TABLE task_snooze;
+DROP TABLE task_completion;
+
+DROP INDEX task_plant_id_idx;
+DROP INDEX task_owner_due_idx;
+
+ALTER TABLE task
+    DROP COLUMN updated_at,
+    DROP COLUMN created_at,
+    DROP COLUMN done,
+    DROP COLUMN interval_days,
+    DROP COLUMN due_at,
+    DROP COLUMN task_type,
+    DROP COLUMN plant_id,
+    DROP COLUMN owner_id;
@@ -0,0 +1,40 @@
+-- This wipes every existing task. The original table only had a name and no
+-- owner, nothing wrote to it, and its rows cannot be given the required
+-- owner and due date.
+DELETE FROM task;
+
+ALTER TABLE task
+    ADD COLUMN owner_id INTEGER NOT NULL REFERENCES "user" (id) ON DELETE CASCADE,
+    ADD COLUMN plant_id INTEGER REFERENCES plant (id) ON DELETE CASCADE,
+    ADD COLUMN task_type TEXT NOT NULL CHECK (task_type IN ('watering', 'fertilization', 'repotting', 'custom')),
+    ADD COLUMN due_at TIMESTAMPTZ NOT NULL,
+    -- Recurring tasks move their due date forward on completion instead of
+    -- being marked as done. The upper bound keeps due dates far from the end
+    -- of the timestamp range and matches `services::task::MAX_INTERVAL_DAYS`.
+    ADD COLUMN interval_days INTEGER CHECK (interval_days BETWEEN 1 AND 3650),
+    ADD COLUMN done BOOLEAN NOT NULL DEFAULT FALSE,
+    ADD COLUMN created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    ADD COLUMN updated_at TIMESTAMPTZ NOT NULL DEFAULT now();
+
+CREATE INDEX task_owner_due_idx ON task (owner_id, due_at);
+CREATE INDEX task_plant_id_idx ON task (plant_id);
+
+CREATE TABLE task_completion (
+    id SERIAL PRIMARY KEY,
+    task_id INTEGER NOT NULL REFERENCES task (id) ON DELETE CASCADE,
+    -- The due date the completion satisfied.
+    due_at TIMESTAMPTZ NOT NULL,
+    completed_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX task_completion_task_id_idx ON task_completion (task_id, completed_at);
+
+CREATE TABLE task_snooze (
+    id SERIAL PRIMARY KEY,
+    task_id INTEGER NOT NULL REFERENCES task (id) ON DELETE CASCADE,
+    from_due_at TIMESTAMPTZ NOT NULL,
+    to_due_at TIMESTAMPTZ NOT NULL,
+    snoozed_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX task_snooze_task_id_idx ON task_snooze (task_id, snoozed_at);
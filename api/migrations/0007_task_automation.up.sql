@@ -0,0 +1,16 @@
+-- Log of the changes the measurement rules made to tasks. The previous state
+-- of the task is kept so the change can be reverted.
+CREATE TABLE task_automation (
+    id SERIAL PRIMARY KEY,
+    task_id INTEGER NOT NULL REFERENCES task (id) ON DELETE CASCADE,
+    action TEXT NOT NULL CHECK (action IN ('completed', 'due_early')),
+    measurement_ids INTEGER[] NOT NULL,
+    reason TEXT NOT NULL,
+    previous_due_at TIMESTAMPTZ NOT NULL,
+    previous_done BOOLEAN NOT NULL,
+    completion_id INTEGER REFERENCES task_completion (id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    reverted_at TIMESTAMPTZ
+);
+
+CREATE INDEX task_automation_task_id_idx ON task_automation (task_id, created_at);
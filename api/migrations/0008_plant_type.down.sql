@@ -0,0 +1,5 @@
+DROP INDEX plant_plant_type_id_idx;
+
+ALTER TABLE plant DROP COLUMN plant_type_id;
+
+DROP TABLE plant_type;
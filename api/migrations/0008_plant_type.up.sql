@@ -0,0 +1,21 @@
+-- Species with their care defaults. The rows are seeded from
+-- `data/plant_types.json` on startup, right after the migrations ran.
+CREATE TABLE plant_type (
+    id SERIAL PRIMARY KEY,
+    common_name TEXT NOT NULL,
+    scientific_name TEXT NOT NULL UNIQUE,
+    watering_interval_days INTEGER NOT NULL CHECK (watering_interval_days > 0),
+    fertilization_interval_days INTEGER NOT NULL CHECK (fertilization_interval_days > 0),
+    -- Soil moisture in percent, light in lux, temperature in degrees Celsius.
+    moisture_min REAL NOT NULL,
+    moisture_max REAL NOT NULL CHECK (moisture_max >= moisture_min),
+    light_min REAL NOT NULL,
+    light_max REAL NOT NULL CHECK (light_max >= light_min),
+    temperature_min REAL NOT NULL,
+    temperature_max REAL NOT NULL CHECK (temperature_max >= temperature_min)
+);
+
+ALTER TABLE plant
+    ADD COLUMN plant_type_id INTEGER REFERENCES plant_type (id) ON DELETE SET NULL;
+
+CREATE INDEX plant_plant_type_id_idx ON plant (plant_type_id);
@@ -0,0 +1,7 @@
+ALTER TABLE plant
+    DROP COLUMN archived,
+    DROP COLUMN acquired_on,
+    DROP COLUMN image_url,
+    DROP COLUMN notes,
+    DROP COLUMN location,
+    DROP COLUMN species;
@@ -0,0 +1,8 @@
+ALTER TABLE plant
+    -- Free-form species name, for plants without a catalog plant type.
+    ADD COLUMN species TEXT,
+    ADD COLUMN location TEXT,
+    ADD COLUMN notes TEXT,
+    ADD COLUMN image_url TEXT,
+    ADD COLUMN acquired_on DATE,
+    ADD COLUMN archived BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,16 @@
+-- Photos of a plant. The image and its thumbnail live in the storage backend
+-- under the given keys; the rows only hold their metadata.
+CREATE TABLE plant_photo (
+    id SERIAL PRIMARY KEY,
+    plant_id INTEGER NOT NULL REFERENCES plant (id) ON DELETE CASCADE,
+    image_key TEXT NOT NULL UNIQUE,
+    thumbnail_key TEXT NOT NULL UNIQUE,
+    content_type TEXT NOT NULL,
+    width INTEGER NOT NULL CHECK (width > 0),
+    height INTEGER NOT NULL CHECK (height > 0),
+    captured_at TIMESTAMPTZ NOT NULL,
+    capture_source TEXT NOT NULL CHECK (capture_source IN ('explicit', 'exif', 'upload')),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX plant_photo_plant_id_idx ON plant_photo (plant_id, captured_at, id);
@@ -0,0 +1,21 @@
+-- Time-lapse jobs. The worker claims pending jobs with SKIP LOCKED, so any
+-- number of replicas can run it; the rendered file lives in the storage
+-- backend under output_key.
+CREATE TABLE timelapse (
+    id SERIAL PRIMARY KEY,
+    plant_id INTEGER NOT NULL REFERENCES plant (id) ON DELETE CASCADE,
+    range_start TIMESTAMPTZ NOT NULL,
+    range_end TIMESTAMPTZ NOT NULL CHECK (range_end >= range_start),
+    frame_delay_ms INTEGER NOT NULL CHECK (frame_delay_ms > 0),
+    status TEXT NOT NULL DEFAULT 'pending'
+        CHECK (status IN ('pending', 'running', 'completed', 'failed')),
+    frame_count INTEGER,
+    output_key TEXT,
+    error TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    started_at TIMESTAMPTZ,
+    finished_at TIMESTAMPTZ
+);
+
+CREATE INDEX timelapse_plant_id_idx ON timelapse (plant_id, created_at);
+CREATE INDEX timelapse_pending_idx ON timelapse (id) WHERE status = 'pending';
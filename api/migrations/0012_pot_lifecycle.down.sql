@@ -0,0 +1,11 @@
+DROP INDEX pot_serial_number_key;
+
+ALTER TABLE pot
+    DROP COLUMN archived_at,
+    DROP COLUMN updated_at,
+    DROP COLUMN created_at,
+    DROP COLUMN status,
+    DROP COLUMN firmware_version,
+    DROP COLUMN model,
+    DROP COLUMN name,
+    DROP COLUMN serial_number;
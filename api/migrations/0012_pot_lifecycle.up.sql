@@ -0,0 +1,15 @@
+ALTER TABLE pot
+    ADD COLUMN serial_number TEXT,
+    ADD COLUMN name TEXT,
+    ADD COLUMN model TEXT,
+    ADD COLUMN firmware_version TEXT,
+    ADD COLUMN status TEXT NOT NULL DEFAULT 'active'
+        CHECK (status IN ('active', 'inactive', 'maintenance')),
+    ADD COLUMN created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    ADD COLUMN updated_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    -- Set when the pot is unregistered but its measurements are kept.
+    ADD COLUMN archived_at TIMESTAMPTZ;
+
+-- A device can only be registered once at a time, archiving a pot releases
+-- its serial number.
+CREATE UNIQUE INDEX pot_serial_number_key ON pot (serial_number) WHERE archived_at IS NULL;
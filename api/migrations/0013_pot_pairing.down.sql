@@ -0,0 +1,3 @@
+DROP TABLE pairing_request;
+DROP TABLE pairing_attempt;
+DROP TABLE pot_pairing;
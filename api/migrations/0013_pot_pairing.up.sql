@@ -0,0 +1,39 @@
+-- A device waiting to be claimed by a user. The device keeps the token to
+-- collect its credential once the pot is claimed.
+CREATE TABLE pot_pairing (
+    id SERIAL PRIMARY KEY,
+    serial_number TEXT NOT NULL,
+    model TEXT,
+    firmware_version TEXT,
+    code_hash TEXT NOT NULL,
+    token_hash TEXT NOT NULL UNIQUE,
+    expires_at TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    claimed_at TIMESTAMPTZ,
+    pot_id INTEGER REFERENCES pot (id) ON DELETE CASCADE,
+    -- Set once the device has collected its credential.
+    delivered_at TIMESTAMPTZ
+);
+
+CREATE UNIQUE INDEX pot_pairing_code_key ON pot_pairing (code_hash) WHERE claimed_at IS NULL;
+CREATE INDEX pot_pairing_serial_number_idx ON pot_pairing (serial_number) WHERE claimed_at IS NULL;
+
+-- Failed claims, to slow down guessing of codes.
+CREATE TABLE pairing_attempt (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES "user" (id) ON DELETE CASCADE,
+    attempted_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX pairing_attempt_user_id_idx ON pairing_attempt (user_id, attempted_at);
+
+-- Codes asked for, to limit how many anyone can request without an account.
+CREATE TABLE pairing_request (
+    id SERIAL PRIMARY KEY,
+    serial_number TEXT NOT NULL,
+    client_addr TEXT NOT NULL,
+    requested_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX pairing_request_serial_number_idx ON pairing_request (serial_number, requested_at);
+CREATE INDEX pairing_request_client_addr_idx ON pairing_request (client_addr, requested_at);
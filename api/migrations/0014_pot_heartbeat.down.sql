@@ -0,0 +1,5 @@
+ALTER TABLE pot
+    DROP COLUMN offline_since,
+    DROP COLUMN report_interval_seconds,
+    DROP COLUMN battery_level,
+    DROP COLUMN last_seen_at;
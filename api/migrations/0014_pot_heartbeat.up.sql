@@ -0,0 +1,22 @@
+ALTER TABLE pot
+    ADD COLUMN last_seen_at TIMESTAMPTZ,
+    ADD COLUMN battery_level INTEGER,
+    -- How often the device is expected to report.
+    ADD COLUMN report_interval_seconds INTEGER NOT NULL DEFAULT 900
+        CHECK (report_interval_seconds > 0),
+    -- Set when the pot was reported offline, cleared once it reports again.
+    ADD COLUMN offline_since TIMESTAMPTZ;
+
+UPDATE pot SET
+    last_seen_at = latest.timestamp AT TIME ZONE 'UTC',
+    battery_level = latest.battery_level
+FROM (
+    SELECT DISTINCT ON (pot_id) pot_id, timestamp, battery_level
+    FROM measurement
+    ORDER BY pot_id, timestamp DESC, id DESC
+) latest
+WHERE latest.pot_id = pot.id;
+
+-- Pots that went silent before this migration are not alerted about.
+UPDATE pot SET offline_since = now()
+WHERE last_seen_at < now() - make_interval(secs => report_interval_seconds * 3);
@@ -0,0 +1,15 @@
+-- Settings pushed to the device, one row per version. The latest version is
+-- the current configuration, pots without any use the defaults.
+CREATE TABLE pot_config (
+    pot_id INTEGER NOT NULL REFERENCES pot (id) ON DELETE CASCADE,
+    version INTEGER NOT NULL CHECK (version > 0),
+    sampling_interval_seconds INTEGER NOT NULL CHECK (sampling_interval_seconds > 0),
+    upload_interval_seconds INTEGER NOT NULL CHECK (upload_interval_seconds > 0),
+    -- Raw soil moisture sensor readings in dry soil and in water.
+    moisture_dry INTEGER,
+    moisture_wet INTEGER,
+    changed_by INTEGER REFERENCES "user" (id) ON DELETE SET NULL,
+    changed_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (pot_id, version),
+    CHECK ((moisture_dry IS NULL) = (moisture_wet IS NULL))
+);
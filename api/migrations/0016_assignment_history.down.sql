@@ -0,0 +1,9 @@
+DROP INDEX plant_pot_assignment_pot_id_idx;
+DROP INDEX plant_pot_assignment_plant_id_idx;
+
+DELETE FROM plant_pot_assignment WHERE unlinked_at IS NOT NULL;
+
+ALTER TABLE plant_pot_assignment
+    DROP CONSTRAINT plant_pot_assignment_period_check,
+    DROP COLUMN unlinked_at,
+    DROP COLUMN linked_at;
@@ -0,0 +1,17 @@
+-- Assignments are periods now, unlinking closes them instead of deleting
+-- them so measurements stay attributed to the plant that was in the pot.
+ALTER TABLE plant_pot_assignment
+    ADD COLUMN linked_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    ADD COLUMN unlinked_at TIMESTAMPTZ,
+    ADD CONSTRAINT plant_pot_assignment_period_check
+        CHECK (unlinked_at IS NULL OR unlinked_at >= linked_at);
+
+-- Existing links cover everything their pot recorded so far.
+UPDATE plant_pot_assignment a
+SET linked_at = LEAST(
+    now(),
+    (SELECT MIN(m.timestamp) AT TIME ZONE 'UTC' FROM measurement m WHERE m.pot_id = a.pot_id)
+);
+
+CREATE INDEX plant_pot_assignment_plant_id_idx ON plant_pot_assignment (plant_id, linked_at);
+CREATE INDEX plant_pot_assignment_pot_id_idx ON plant_pot_assignment (pot_id, linked_at);
@@ -0,0 +1,6 @@
+ALTER TABLE plant_pot_assignment
+    DROP CONSTRAINT plant_pot_assignment_plant_period_excl,
+    DROP CONSTRAINT plant_pot_assignment_pot_period_excl;
+
+DROP INDEX plant_pot_assignment_active_plant_key;
+DROP INDEX plant_pot_assignment_active_pot_key;
@@ -0,0 +1,26 @@
+-- Links used to be checked in separate queries only. Drop the duplicates
+-- concurrent requests may have left behind before enforcing it here.
+DELETE FROM plant_pot_assignment a
+WHERE a.unlinked_at IS NULL
+AND EXISTS (
+    SELECT 1 FROM plant_pot_assignment b
+    WHERE b.unlinked_at IS NULL
+    AND (b.pot_id = a.pot_id OR b.plant_id = a.plant_id)
+    AND b.id < a.id
+);
+
+-- A pot holds one plant at a time and a plant sits in one pot at a time.
+CREATE UNIQUE INDEX plant_pot_assignment_active_pot_key
+    ON plant_pot_assignment (pot_id) WHERE unlinked_at IS NULL;
+CREATE UNIQUE INDEX plant_pot_assignment_active_plant_key
+    ON plant_pot_assignment (plant_id) WHERE unlinked_at IS NULL;
+
+-- The same goes for the history, so every measurement belongs to at most
+-- one plant.
+CREATE EXTENSION IF NOT EXISTS btree_gist;
+
+ALTER TABLE plant_pot_assignment
+    ADD CONSTRAINT plant_pot_assignment_pot_period_excl
+        EXCLUDE USING gist (pot_id WITH =, tstzrange(linked_at, unlinked_at) WITH &&),
+    ADD CONSTRAINT plant_pot_assignment_plant_period_excl
+        EXCLUDE USING gist (plant_id WITH =, tstzrange(linked_at, unlinked_at) WITH &&);
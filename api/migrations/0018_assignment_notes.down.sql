@@ -0,0 +1 @@
+ALTER TABLE plant_pot_assignment DROP COLUMN notes;
@@ -0,0 +1,2 @@
+-- Free-form notes about why the plant was put in the pot.
+ALTER TABLE plant_pot_assignment ADD COLUMN notes TEXT;
@@ -0,0 +1,7 @@
+-- Readings that failed the plausibility checks on ingestion. They are kept
+-- for inspection but left out of every series, rollup and task rule.
+CREATE TABLE measurement_quarantine (
+    measurement_id INTEGER PRIMARY KEY REFERENCES measurement (id) ON DELETE CASCADE,
+    reasons TEXT[] NOT NULL CHECK (cardinality(reasons) > 0),
+    quarantined_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
@@ -1,52 +1,177 @@
-use axum::{Json, extract::State, http::StatusCode};
-use log::debug;
+use std::sync::Arc;
+
+use axum::{extract::State, http::StatusCode};
 use serde::{Deserialize, Serialize};
-use sqlx::PgPool;
+use sqlx::{PgPool, types::Uuid};
 
-use crate::services::{self, auth::AuthError};
+use crate::{
+    controllers::{
+        error::ApiError,
+        extract::{Json, Path},
+        middleware::RequireAuth,
+    },
+    entities::Session,
+    services::{self, auth::AuthTokens, jwt::JwtKeys},
+};
 
 #[derive(Serialize)]
 pub struct JwtResponse {
-    token: String,
+    #[serde(rename = "accessToken")]
+    access_token: String,
+    #[serde(rename = "accessTokenExpiresAt")]
+    access_token_expires_at: String,
+    #[serde(rename = "refreshToken")]
+    refresh_token: String,
+    #[serde(rename = "refreshTokenExpiresAt")]
+    refresh_token_expires_at: String,
+}
+
+impl From<AuthTokens> for JwtResponse {
+    fn from(tokens: AuthTokens) -> Self {
+        JwtResponse {
+            access_token: tokens.access_token.token,
+            access_token_expires_at: tokens.access_token.expires_at.to_rfc3339(),
+            refresh_token: tokens.refresh_token.token,
+            refresh_token_expires_at: tokens.refresh_token.expires_at.to_rfc3339(),
+        }
+    }
 }
 
 #[derive(Deserialize)]
 pub struct RegisterPayload {
     email: String,
     password: String,
+    #[serde(rename = "deviceName")]
+    device_name: Option<String>,
 }
 
 pub async fn register(
     State(pool): State<PgPool>,
+    State(keys): State<Arc<JwtKeys>>,
     Json(payload): Json<RegisterPayload>,
-) -> Result<Json<JwtResponse>, StatusCode> {
-    let token = services::auth::register(&pool, payload.email.as_str(), payload.password.as_str())
-        .await
-        .map_err(|e| match e {
-            AuthError::UserAlreadyExists => return StatusCode::CONFLICT,
-            AuthError::InvalidCredentials => unreachable!(),
-            AuthError::InternalError(e) => {
-                debug!("Registration error: {}", e);
-                StatusCode::INTERNAL_SERVER_ERROR
-            }
-        })?;
+) -> Result<Json<JwtResponse>, ApiError> {
+    let tokens = services::auth::register(
+        &pool,
+        &keys,
+        payload.email.as_str(),
+        payload.password.as_str(),
+        payload.device_name.as_deref(),
+    )
+    .await?;
 
-    Ok(Json(JwtResponse { token }))
+    Ok(Json(JwtResponse::from(tokens)))
 }
 
 #[derive(Deserialize)]
 pub struct LoginPayload {
     email: String,
     password: String,
+    #[serde(rename = "deviceName")]
+    device_name: Option<String>,
 }
 
 pub async fn login(
     State(pool): State<PgPool>,
+    State(keys): State<Arc<JwtKeys>>,
     Json(payload): Json<LoginPayload>,
-) -> Result<Json<JwtResponse>, StatusCode> {
-    let token = services::auth::login(&pool, payload.email.as_str(), payload.password.as_str())
-        .await
-        .map_err(|_| StatusCode::UNAUTHORIZED)?;
+) -> Result<Json<JwtResponse>, ApiError> {
+    let tokens = services::auth::login(
+        &pool,
+        &keys,
+        payload.email.as_str(),
+        payload.password.as_str(),
+        payload.device_name.as_deref(),
+    )
+    .await?;
+
+    Ok(Json(JwtResponse::from(tokens)))
+}
+
+#[derive(Deserialize)]
+pub struct RefreshPayload {
+    #[serde(rename = "refreshToken")]
+    refresh_token: String,
+}
+
+pub async fn refresh(
+    State(pool): State<PgPool>,
+    State(keys): State<Arc<JwtKeys>>,
+    Json(payload): Json<RefreshPayload>,
+) -> Result<Json<JwtResponse>, ApiError> {
+    let tokens = services::auth::refresh(&pool, &keys, payload.refresh_token.as_str()).await?;
+
+    Ok(Json(JwtResponse::from(tokens)))
+}
+
+pub async fn logout(
+    State(pool): State<PgPool>,
+    RequireAuth(claims): RequireAuth,
+) -> Result<StatusCode, ApiError> {
+    let user = services::user::get_user_by_email(&pool, claims.sub.as_str())
+        .await?
+        .ok_or_else(ApiError::unauthorized)?;
+
+    services::session::revoke_session(&pool, user.id, claims.sid).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Serialize)]
+pub struct SessionResponse {
+    id: Uuid,
+    #[serde(rename = "deviceName")]
+    device_name: Option<String>,
+    #[serde(rename = "createdAt")]
+    created_at: String,
+    #[serde(rename = "lastUsedAt")]
+    last_used_at: String,
+    current: bool,
+}
+
+impl SessionResponse {
+    fn from(session: Session, current_session_id: Uuid) -> Self {
+        SessionResponse {
+            id: session.id,
+            device_name: session.device_name,
+            created_at: session.created_at.to_rfc3339(),
+            last_used_at: session.last_used_at.to_rfc3339(),
+            current: session.id == current_session_id,
+        }
+    }
+}
+
+pub async fn get_sessions(
+    State(pool): State<PgPool>,
+    RequireAuth(claims): RequireAuth,
+) -> Result<Json<Vec<SessionResponse>>, ApiError> {
+    let user = services::user::get_user_by_email(&pool, claims.sub.as_str())
+        .await?
+        .ok_or_else(ApiError::unauthorized)?;
+
+    let sessions = services::session::get_sessions(&pool, user.id).await?;
+
+    Ok(Json(
+        sessions
+            .into_iter()
+            .map(|session| SessionResponse::from(session, claims.sid))
+            .collect(),
+    ))
+}
+
+pub async fn revoke_session(
+    State(pool): State<PgPool>,
+    RequireAuth(claims): RequireAuth,
+    Path(session_id): Path<Uuid>,
+) -> Result<StatusCode, ApiError> {
+    let user = services::user::get_user_by_email(&pool, claims.sub.as_str())
+        .await?
+        .ok_or_else(ApiError::unauthorized)?;
+
+    let revoked = services::session::revoke_session(&pool, user.id, session_id).await?;
+
+    if !revoked {
+        return Err(ApiError::not_found());
+    }
 
-    Ok(Json(JwtResponse { token }))
+    Ok(StatusCode::NO_CONTENT)
 }
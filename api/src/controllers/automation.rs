@@ -0,0 +1,84 @@
+use axum::extract::State;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+
+use crate::{
+    controllers::{
+        error::ApiError,
+        extract::{Json, Path, Query},
+        middleware::RequireAuth,
+    },
+    entities::TaskAutomation,
+    services,
+};
+
+#[derive(Serialize)]
+pub struct AutomationResponse {
+    id: i32,
+    #[serde(rename = "taskId")]
+    task_id: i32,
+    action: &'static str,
+    #[serde(rename = "measurementIds")]
+    measurement_ids: Vec<i32>,
+    reason: String,
+    #[serde(rename = "previousDueAt")]
+    previous_due_at: String,
+    #[serde(rename = "createdAt")]
+    created_at: String,
+    #[serde(rename = "revertedAt")]
+    reverted_at: Option<String>,
+}
+
+impl From<TaskAutomation> for AutomationResponse {
+    fn from(automation: TaskAutomation) -> Self {
+        AutomationResponse {
+            id: automation.id,
+            task_id: automation.task_id,
+            action: automation.action.as_str(),
+            measurement_ids: automation.measurement_ids,
+            reason: automation.reason,
+            previous_due_at: automation.previous_due_at.to_rfc3339(),
+            created_at: automation.created_at.to_rfc3339(),
+            reverted_at: automation.reverted_at.map(|at| at.to_rfc3339()),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct AutomationQuery {
+    #[serde(rename = "taskId")]
+    task_id: Option<i32>,
+}
+
+pub async fn get_automations(
+    State(pool): State<PgPool>,
+    RequireAuth(claims): RequireAuth,
+    Query(query): Query<AutomationQuery>,
+) -> Result<Json<Vec<AutomationResponse>>, ApiError> {
+    let user = services::user::get_user_by_email(&pool, claims.sub.as_str())
+        .await?
+        .ok_or_else(ApiError::unauthorized)?;
+
+    let automations = services::automation::get_automations(&pool, user.id, query.task_id).await?;
+
+    Ok(Json(
+        automations
+            .into_iter()
+            .map(AutomationResponse::from)
+            .collect(),
+    ))
+}
+
+pub async fn revert_automation(
+    State(pool): State<PgPool>,
+    RequireAuth(claims): RequireAuth,
+    Path(automation_id): Path<i32>,
+) -> Result<Json<AutomationResponse>, ApiError> {
+    let user = services::user::get_user_by_email(&pool, claims.sub.as_str())
+        .await?
+        .ok_or_else(ApiError::unauthorized)?;
+
+    let automation = services::automation::revert_automation(&pool, user.id, automation_id).await?;
+
+    Ok(Json(AutomationResponse::from(automation)))
+}
@@ -0,0 +1,276 @@
+use axum::{
+    Json,
+    extract::{
+        multipart::{MultipartError, MultipartRejection},
+        rejection::{JsonRejection, PathRejection, QueryRejection},
+    },
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use log::error;
+use serde_json::{Value, json};
+
+use crate::services::{auth::AuthError, error::ServiceError};
+
+/// The error returned by every handler, rendered as
+/// `{"error": {"code", "message", "statusCode", "details"}}`.
+///
+/// `code` is stable and meant for clients to branch on; `message` is for
+/// humans and may change.
+#[derive(Debug)]
+pub struct ApiError {
+    status: StatusCode,
+    code: &'static str,
+    message: String,
+    details: Option<Value>,
+}
+
+impl ApiError {
+    pub fn new(status: StatusCode, code: &'static str, message: impl Into<String>) -> Self {
+        ApiError {
+            status,
+            code,
+            message: message.into(),
+            details: None,
+        }
+    }
+
+    pub fn with_details(mut self, details: Value) -> Self {
+        self.details = Some(details);
+        self
+    }
+
+    pub fn unauthorized() -> Self {
+        Self::new(
+            StatusCode::UNAUTHORIZED,
+            "UNAUTHORIZED",
+            "Authentication is required",
+        )
+    }
+
+    pub fn forbidden(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::FORBIDDEN, "FORBIDDEN", message)
+    }
+
+    pub fn not_found() -> Self {
+        Self::new(
+            StatusCode::NOT_FOUND,
+            "RESOURCE_NOT_FOUND",
+            "The requested resource was not found",
+        )
+    }
+
+    pub fn validation(field: &str, issue: impl Into<String>) -> Self {
+        Self::new(
+            StatusCode::BAD_REQUEST,
+            "VALIDATION_ERROR",
+            "Invalid input data",
+        )
+        .with_details(json!({ "fields": [{ "field": field, "issue": issue.into() }] }))
+    }
+
+    pub fn internal() -> Self {
+        Self::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "INTERNAL_ERROR",
+            "An unexpected error occurred",
+        )
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let mut error = json!({
+            "code": self.code,
+            "message": self.message,
+            "statusCode": self.status.as_u16(),
+        });
+        if let Some(details) = self.details {
+            error["details"] = details;
+        }
+
+        (self.status, Json(json!({ "error": error }))).into_response()
+    }
+}
+
+impl From<ServiceError> for ApiError {
+    fn from(e: ServiceError) -> Self {
+        match e {
+            ServiceError::NotFound(resource) => ApiError::new(
+                StatusCode::NOT_FOUND,
+                "RESOURCE_NOT_FOUND",
+                format!("{} not found", resource),
+            ),
+            ServiceError::Conflict { code, message } => {
+                ApiError::new(StatusCode::CONFLICT, code, message)
+            }
+            ServiceError::Validation(errors) => {
+                let fields: Vec<Value> = errors
+                    .into_iter()
+                    .map(|e| json!({ "field": e.field, "issue": e.issue }))
+                    .collect();
+                ApiError::new(
+                    StatusCode::BAD_REQUEST,
+                    "VALIDATION_ERROR",
+                    "Invalid input data",
+                )
+                .with_details(json!({ "fields": fields }))
+            }
+            ServiceError::Unavailable(message) => ApiError::new(
+                StatusCode::SERVICE_UNAVAILABLE,
+                "SERVICE_UNAVAILABLE",
+                message,
+            ),
+            ServiceError::RateLimited(message) => {
+                ApiError::new(StatusCode::TOO_MANY_REQUESTS, "TOO_MANY_ATTEMPTS", message)
+            }
+            ServiceError::Internal(e) => {
+                error!("Internal error: {}", e);
+                ApiError::internal()
+            }
+        }
+    }
+}
+
+impl From<AuthError> for ApiError {
+    fn from(e: AuthError) -> Self {
+        match e {
+            AuthError::UserAlreadyExists => ApiError::new(
+                StatusCode::CONFLICT,
+                "USER_ALREADY_EXISTS",
+                "A user with this email already exists",
+            ),
+            AuthError::InvalidCredentials => ApiError::new(
+                StatusCode::UNAUTHORIZED,
+                "INVALID_CREDENTIALS",
+                "Invalid email or password",
+            ),
+            AuthError::InvalidToken => ApiError::new(
+                StatusCode::UNAUTHORIZED,
+                "INVALID_REFRESH_TOKEN",
+                "The refresh token is invalid or has expired",
+            ),
+            AuthError::TokenReused => ApiError::new(
+                StatusCode::UNAUTHORIZED,
+                "REFRESH_TOKEN_REUSED",
+                "The refresh token has already been used, the session was revoked",
+            ),
+            AuthError::InternalError(e) => {
+                error!("Internal error: {}", e);
+                ApiError::internal()
+            }
+        }
+    }
+}
+
+impl From<JsonRejection> for ApiError {
+    fn from(rejection: JsonRejection) -> Self {
+        ApiError::new(
+            rejection.status(),
+            "VALIDATION_ERROR",
+            "Invalid request body",
+        )
+        .with_details(json!({ "reason": rejection.body_text() }))
+    }
+}
+
+impl From<PathRejection> for ApiError {
+    fn from(rejection: PathRejection) -> Self {
+        ApiError::new(
+            rejection.status(),
+            "VALIDATION_ERROR",
+            "Invalid path parameter",
+        )
+        .with_details(json!({ "reason": rejection.body_text() }))
+    }
+}
+
+impl From<QueryRejection> for ApiError {
+    fn from(rejection: QueryRejection) -> Self {
+        ApiError::new(
+            rejection.status(),
+            "VALIDATION_ERROR",
+            "Invalid query parameter",
+        )
+        .with_details(json!({ "reason": rejection.body_text() }))
+    }
+}
+
+impl From<MultipartRejection> for ApiError {
+    fn from(rejection: MultipartRejection) -> Self {
+        ApiError::new(
+            rejection.status(),
+            "VALIDATION_ERROR",
+            "Invalid multipart body",
+        )
+        .with_details(json!({ "reason": rejection.body_text() }))
+    }
+}
+
+impl From<MultipartError> for ApiError {
+    fn from(error: MultipartError) -> Self {
+        ApiError::new(error.status(), "VALIDATION_ERROR", "Invalid multipart body")
+            .with_details(json!({ "reason": error.body_text() }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::to_bytes;
+
+    use super::*;
+
+    async fn render(error: ApiError) -> (StatusCode, Value) {
+        let response = error.into_response();
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("body");
+        (status, serde_json::from_slice(&body).expect("json"))
+    }
+
+    #[tokio::test]
+    async fn renders_envelope() {
+        let (status, body) = render(ApiError::from(ServiceError::NotFound("Plant"))).await;
+
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(
+            body,
+            json!({
+                "error": {
+                    "code": "RESOURCE_NOT_FOUND",
+                    "message": "Plant not found",
+                    "statusCode": 404,
+                }
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn renders_validation_details() {
+        let (status, body) = render(ApiError::from(ServiceError::validation(
+            "name",
+            "must not be empty",
+        )))
+        .await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"]["code"], "VALIDATION_ERROR");
+        assert_eq!(
+            body["error"]["details"],
+            json!({ "fields": [{ "field": "name", "issue": "must not be empty" }] })
+        );
+    }
+
+    #[tokio::test]
+    async fn renders_conflict_code() {
+        let (status, body) = render(ApiError::from(ServiceError::conflict(
+            "POT_ALREADY_LINKED",
+            "The pot is already linked to a plant",
+        )))
+        .await;
+
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(body["error"]["code"], "POT_ALREADY_LINKED");
+    }
+}
@@ -0,0 +1,80 @@
+use std::sync::Arc;
+
+use axum::extract::State;
+use serde::Serialize;
+use sqlx::PgPool;
+
+use crate::{
+    controllers::{
+        error::ApiError, extract::Json, extract::Multipart, plant_type::PlantTypeResponse,
+    },
+    services::{
+        self,
+        identify::{Identification, IdentifiedSpecies, PlantIdentifier},
+    },
+};
+
+#[derive(Serialize)]
+pub struct CandidateResponse {
+    #[serde(rename = "scientificName")]
+    scientific_name: String,
+    #[serde(rename = "commonName")]
+    common_name: Option<String>,
+    /// Between 0 and 1.
+    confidence: f32,
+    /// The matching catalog species, `null` if it is not in the catalog.
+    #[serde(rename = "plantType")]
+    plant_type: Option<PlantTypeResponse>,
+}
+
+impl From<IdentifiedSpecies> for CandidateResponse {
+    fn from(species: IdentifiedSpecies) -> Self {
+        CandidateResponse {
+            scientific_name: species.scientific_name,
+            common_name: species.common_name,
+            confidence: species.confidence,
+            plant_type: species.plant_type.map(PlantTypeResponse::from),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct IdentificationResponse {
+    /// Best first.
+    candidates: Vec<CandidateResponse>,
+    /// No catalog species is a likely match.
+    unknown: bool,
+}
+
+impl From<Identification> for IdentificationResponse {
+    fn from(identification: Identification) -> Self {
+        IdentificationResponse {
+            candidates: identification
+                .candidates
+                .into_iter()
+                .map(CandidateResponse::from)
+                .collect(),
+            unknown: identification.unknown,
+        }
+    }
+}
+
+/// Identifies the species in the `photo` part of a multipart body.
+pub async fn identify_plant(
+    State(pool): State<PgPool>,
+    State(identifier): State<Arc<dyn PlantIdentifier>>,
+    Multipart(mut multipart): Multipart,
+) -> Result<Json<IdentificationResponse>, ApiError> {
+    let mut data = None;
+    while let Some(field) = multipart.next_field().await? {
+        if field.name() == Some("photo") {
+            data = Some(field.bytes().await?);
+        }
+    }
+    let data = data.ok_or_else(|| ApiError::validation("photo", "is required"))?;
+
+    let identification =
+        services::identify::identify_plant(&pool, identifier.as_ref(), data).await?;
+
+    Ok(Json(IdentificationResponse::from(identification)))
+}
@@ -1,14 +1,18 @@
-use axum::{
-    extract::{Json, State},
-    http::StatusCode,
-};
-use log::debug;
-use serde::Deserialize;
+use axum::{extract::State, http::StatusCode};
+use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
 
 use crate::{
-    controllers::middleware::RequireAuth,
+    controllers::{
+        error::ApiError,
+        extract::{Json, Path},
+        middleware::RequireAuth,
+        plant::PlantSummaryResponse,
+        pot::PotSummaryResponse,
+    },
+    entities::PlantPotAssignment,
     services::{self},
+    state::AppState,
 };
 
 #[derive(Deserialize)]
@@ -19,42 +23,263 @@ pub struct LinkPlantToPotRequest {
     pot_id: i32,
 }
 
-#[axum::debug_handler]
+#[derive(Serialize)]
+pub struct AssignmentResponse {
+    id: i32,
+    #[serde(rename = "plantId")]
+    plant_id: i32,
+    #[serde(rename = "potId")]
+    pot_id: i32,
+    #[serde(rename = "linkedAt")]
+    linked_at: String,
+    /// Unset while the plant is still in the pot.
+    #[serde(rename = "unlinkedAt")]
+    unlinked_at: Option<String>,
+    notes: Option<String>,
+}
+
+impl From<PlantPotAssignment> for AssignmentResponse {
+    fn from(assignment: PlantPotAssignment) -> Self {
+        AssignmentResponse {
+            id: assignment.id,
+            plant_id: assignment.plant_id,
+            pot_id: assignment.pot_id,
+            linked_at: assignment.linked_at.to_rfc3339(),
+            unlinked_at: assignment.unlinked_at.map(|at| at.to_rfc3339()),
+            notes: assignment.notes,
+        }
+    }
+}
+
+#[axum::debug_handler(state = AppState)]
 pub async fn link_plant_to_pot(
     State(pool): State<PgPool>,
     RequireAuth(claims): RequireAuth,
     Json(payload): Json<LinkPlantToPotRequest>,
-) -> Result<StatusCode, StatusCode> {
-    let user = services::user::get_user_by_email(&pool, claims.sub.as_str())
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
-        .ok_or(StatusCode::UNAUTHORIZED)?;
-
-    services::link::link_plant_to_pot(&pool, user.id, payload.plant_id, payload.pot_id)
-        .await
-        .map_err(|e| {
-            debug!("Error linking plant to pot: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
+) -> Result<StatusCode, ApiError> {
+    let user = services::user::get_user_by_email(&pool, claims.sub.as_str())
+        .await?
+        .ok_or_else(ApiError::unauthorized)?;
+
+    services::link::link_plant_to_pot(&pool, user.id, payload.plant_id, payload.pot_id).await?;
     Ok(StatusCode::NO_CONTENT)
 }
 
-#[axum::debug_handler]
+#[axum::debug_handler(state = AppState)]
 pub async fn unlink_plant_from_pot(
     State(pool): State<PgPool>,
     RequireAuth(claims): RequireAuth,
     Json(payload): Json<LinkPlantToPotRequest>,
-) -> Result<StatusCode, StatusCode> {
-    let user = services::user::get_user_by_email(&pool, claims.sub.as_str())
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
-        .ok_or(StatusCode::UNAUTHORIZED)?;
-
-    services::link::unlink_plant_from_pot(&pool, user.id, payload.plant_id, payload.pot_id)
-        .await
-        .map_err(|e| {
-            debug!("Error unlinking plant from pot: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
+) -> Result<StatusCode, ApiError> {
+    let user = services::user::get_user_by_email(&pool, claims.sub.as_str())
+        .await?
+        .ok_or_else(ApiError::unauthorized)?;
+
+    services::link::unlink_plant_from_pot(&pool, user.id, payload.plant_id, payload.pot_id).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Deserialize)]
+pub struct SwitchPlantRequest {
+    #[serde(rename = "plantId")]
+    plant_id: i32,
+    #[serde(rename = "potId")]
+    pot_id: i32,
+    /// Lets the plant in the target pot move to the pot the plant came from.
+    #[serde(default)]
+    swap: bool,
+}
+
+/// Moves a plant to another pot, or swaps two plants, in one step. Returns
+/// the links that were started.
+#[axum::debug_handler(state = AppState)]
+pub async fn switch_plant(
+    State(pool): State<PgPool>,
+    RequireAuth(claims): RequireAuth,
+    Json(payload): Json<SwitchPlantRequest>,
+) -> Result<Json<Vec<AssignmentResponse>>, ApiError> {
+    let user = services::user::get_user_by_email(&pool, claims.sub.as_str())
+        .await?
+        .ok_or_else(ApiError::unauthorized)?;
+
+    let assignments = services::link::switch_plant(
+        &pool,
+        user.id,
+        payload.plant_id,
+        payload.pot_id,
+        payload.swap,
+    )
+    .await?;
+
+    Ok(Json(
+        assignments
+            .into_iter()
+            .map(AssignmentResponse::from)
+            .collect(),
+    ))
+}
+
+/// The pots the plant was in, most recent first.
+pub async fn get_plant_history(
+    State(pool): State<PgPool>,
+    RequireAuth(claims): RequireAuth,
+    Path(plant_id): Path<i32>,
+) -> Result<Json<Vec<AssignmentResponse>>, ApiError> {
+    let user = services::user::get_user_by_email(&pool, claims.sub.as_str())
+        .await?
+        .ok_or_else(ApiError::unauthorized)?;
+
+    let history = services::link::get_plant_history(&pool, user.id, plant_id).await?;
+
+    Ok(Json(
+        history.into_iter().map(AssignmentResponse::from).collect(),
+    ))
+}
+
+/// The plants that were in the pot, most recent first.
+pub async fn get_pot_history(
+    State(pool): State<PgPool>,
+    RequireAuth(claims): RequireAuth,
+    Path(pot_id): Path<i32>,
+) -> Result<Json<Vec<AssignmentResponse>>, ApiError> {
+    let user = services::user::get_user_by_email(&pool, claims.sub.as_str())
+        .await?
+        .ok_or_else(ApiError::unauthorized)?;
+
+    let history = services::link::get_pot_history(&pool, user.id, pot_id).await?;
+
+    Ok(Json(
+        history.into_iter().map(AssignmentResponse::from).collect(),
+    ))
+}
+
+/// A link with the plant and the pot it joins.
+#[derive(Serialize)]
+pub struct LinkResponse {
+    #[serde(flatten)]
+    link: AssignmentResponse,
+    plant: PlantSummaryResponse,
+    pot: PotSummaryResponse,
+}
+
+async fn link_response(
+    pool: &PgPool,
+    user_id: i32,
+    link: PlantPotAssignment,
+) -> Result<LinkResponse, ApiError> {
+    let plant = services::plant::get_plant(pool, user_id, link.plant_id).await?;
+    let pot = services::pot::get_pot(pool, user_id, link.pot_id).await?;
+
+    Ok(LinkResponse {
+        link: AssignmentResponse::from(link),
+        plant: PlantSummaryResponse::from(plant),
+        pot: PotSummaryResponse::from(pot),
+    })
+}
+
+#[derive(Deserialize)]
+pub struct CreateLinkPayload {
+    #[serde(rename = "plantId")]
+    plant_id: i32,
+    #[serde(rename = "potId")]
+    pot_id: i32,
+    notes: Option<String>,
+}
+
+pub async fn create_link(
+    State(pool): State<PgPool>,
+    RequireAuth(claims): RequireAuth,
+    Json(payload): Json<CreateLinkPayload>,
+) -> Result<(StatusCode, Json<LinkResponse>), ApiError> {
+    let user = services::user::get_user_by_email(&pool, claims.sub.as_str())
+        .await?
+        .ok_or_else(ApiError::unauthorized)?;
+
+    let link = services::link::create_link(
+        &pool,
+        user.id,
+        payload.plant_id,
+        payload.pot_id,
+        payload.notes.as_deref(),
+    )
+    .await?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(link_response(&pool, user.id, link).await?),
+    ))
+}
+
+pub async fn get_link(
+    State(pool): State<PgPool>,
+    RequireAuth(claims): RequireAuth,
+    Path(link_id): Path<i32>,
+) -> Result<Json<LinkResponse>, ApiError> {
+    let user = services::user::get_user_by_email(&pool, claims.sub.as_str())
+        .await?
+        .ok_or_else(ApiError::unauthorized)?;
+
+    let link = services::link::get_link(&pool, user.id, link_id).await?;
+
+    Ok(Json(link_response(&pool, user.id, link).await?))
+}
+
+/// The link of the plant currently in the pot.
+pub async fn get_current_link(
+    State(pool): State<PgPool>,
+    RequireAuth(claims): RequireAuth,
+    Path(pot_id): Path<i32>,
+) -> Result<Json<LinkResponse>, ApiError> {
+    let user = services::user::get_user_by_email(&pool, claims.sub.as_str())
+        .await?
+        .ok_or_else(ApiError::unauthorized)?;
+
+    let link = services::link::get_current_link(&pool, user.id, pot_id).await?;
+
+    Ok(Json(link_response(&pool, user.id, link).await?))
+}
+
+#[derive(Deserialize)]
+pub struct UpdateLinkPayload {
+    /// Another plant ends the link and starts a new one for the pot.
+    #[serde(rename = "plantId")]
+    plant_id: i32,
+    notes: Option<String>,
+}
+
+pub async fn update_link(
+    State(pool): State<PgPool>,
+    RequireAuth(claims): RequireAuth,
+    Path(link_id): Path<i32>,
+    Json(payload): Json<UpdateLinkPayload>,
+) -> Result<Json<LinkResponse>, ApiError> {
+    let user = services::user::get_user_by_email(&pool, claims.sub.as_str())
+        .await?
+        .ok_or_else(ApiError::unauthorized)?;
+
+    let link = services::link::update_link(
+        &pool,
+        user.id,
+        link_id,
+        payload.plant_id,
+        payload.notes.as_deref(),
+    )
+    .await?;
+
+    Ok(Json(link_response(&pool, user.id, link).await?))
+}
+
+pub async fn delete_link(
+    State(pool): State<PgPool>,
+    RequireAuth(claims): RequireAuth,
+    Path(link_id): Path<i32>,
+) -> Result<StatusCode, ApiError> {
+    let user = services::user::get_user_by_email(&pool, claims.sub.as_str())
+        .await?
+        .ok_or_else(ApiError::unauthorized)?;
+
+    services::link::delete_link(&pool, user.id, link_id).await?;
+
     Ok(StatusCode::NO_CONTENT)
 }
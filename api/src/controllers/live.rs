@@ -0,0 +1,151 @@
+use std::{sync::Arc, time::Duration};
+
+use axum::{
+    extract::{
+        State,
+        ws::{CloseFrame, Message, WebSocket, WebSocketUpgrade, close_code},
+    },
+    response::Response,
+};
+use log::debug;
+use serde::Serialize;
+use sqlx::{PgPool, types::chrono::Utc};
+use tokio::{
+    sync::broadcast::{Receiver, error::RecvError},
+    time::timeout,
+};
+
+use crate::{
+    controllers::{error::ApiError, extract::Path, middleware::RequireSocketAuth},
+    services::{
+        self,
+        events::{EventBus, Notification},
+        jwt::Claims,
+    },
+};
+
+/// A client that cannot take a message within this time is disconnected.
+const SEND_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How often an open socket checks that its session was not revoked.
+const SESSION_CHECK_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Messages about the stream itself, sent alongside the events.
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+enum StreamMessage {
+    /// The client fell behind and `skipped` events were dropped; it should
+    /// refetch whatever it displays.
+    Lagged { skipped: u64 },
+}
+
+/// Streams the events of one pot.
+pub async fn pot_live(
+    ws: WebSocketUpgrade,
+    State(pool): State<PgPool>,
+    State(events): State<EventBus>,
+    RequireSocketAuth(claims): RequireSocketAuth,
+    Path(pot_id): Path<i32>,
+) -> Result<Response, ApiError> {
+    let user = services::user::get_user_by_email(&pool, claims.sub.as_str())
+        .await?
+        .ok_or_else(ApiError::unauthorized)?;
+
+    services::pot::get_pot(&pool, user.id, pot_id).await?;
+
+    let receiver = events.subscribe();
+    Ok(ws.on_upgrade(move |socket| {
+        stream(socket, receiver, pool, claims, move |notification| {
+            notification.owner_id == user.id && notification.event.pot_id() == pot_id
+        })
+    }))
+}
+
+/// Streams the events of all pots of the user over a single socket.
+pub async fn user_live(
+    ws: WebSocketUpgrade,
+    State(pool): State<PgPool>,
+    State(events): State<EventBus>,
+    RequireSocketAuth(claims): RequireSocketAuth,
+) -> Result<Response, ApiError> {
+    let user = services::user::get_user_by_email(&pool, claims.sub.as_str())
+        .await?
+        .ok_or_else(ApiError::unauthorized)?;
+
+    let receiver = events.subscribe();
+    Ok(ws.on_upgrade(move |socket| {
+        stream(socket, receiver, pool, claims, move |notification| {
+            notification.owner_id == user.id
+        })
+    }))
+}
+
+/// Forwards matching events until the client leaves, falls too far behind
+/// on the socket, or its access token expires or its session is revoked.
+async fn stream(
+    mut socket: WebSocket,
+    mut events: Receiver<Arc<Notification>>,
+    pool: PgPool,
+    claims: Claims,
+    matches: impl Fn(&Notification) -> bool,
+) {
+    let remaining = Duration::from_secs((claims.exp - Utc::now().timestamp()).max(0) as u64);
+    let expiry = tokio::time::sleep(remaining);
+    tokio::pin!(expiry);
+    let mut session_check = tokio::time::interval(SESSION_CHECK_INTERVAL);
+    // The session was checked on upgrade.
+    session_check.tick().await;
+
+    loop {
+        tokio::select! {
+            received = events.recv() => {
+                let message = match received {
+                    Ok(notification) if matches(&notification) => {
+                        serde_json::to_string(&notification.event)
+                    }
+                    Ok(_) => continue,
+                    Err(RecvError::Lagged(skipped)) => {
+                        debug!("Live client lagged behind by {} events", skipped);
+                        serde_json::to_string(&StreamMessage::Lagged { skipped })
+                    }
+                    Err(RecvError::Closed) => break,
+                };
+                let Ok(message) = message else { continue };
+
+                match timeout(SEND_TIMEOUT, socket.send(Message::Text(message.into()))).await {
+                    Ok(Ok(())) => {}
+                    Ok(Err(_)) => break,
+                    Err(_) => {
+                        debug!("Disconnecting live client that stopped reading");
+                        break;
+                    }
+                }
+            }
+            incoming = socket.recv() => match incoming {
+                // Pings are answered by axum; anything else from the client is ignored.
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+            _ = &mut expiry => {
+                close(&mut socket, "Access token expired").await;
+                break;
+            }
+            _ = session_check.tick() => {
+                // A failed check keeps the socket, the next one decides.
+                if let Ok(false) = services::session::is_active(&pool, claims.sid).await {
+                    close(&mut socket, "Session revoked").await;
+                    break;
+                }
+            }
+        }
+    }
+}
+
+async fn close(socket: &mut WebSocket, reason: &'static str) {
+    let _ = socket
+        .send(Message::Close(Some(CloseFrame {
+            code: close_code::POLICY,
+            reason: reason.into(),
+        })))
+        .await;
+}
@@ -1,17 +1,39 @@
-use axum::{
-    Json,
-    extract::{Path, State},
-    http::StatusCode,
-};
+use std::{sync::Arc, time::Duration};
+
+use axum::{extract::State, http::StatusCode};
 use log::debug;
 use serde::{Deserialize, Serialize};
-use sqlx::{PgPool, types::chrono::DateTime};
+use sqlx::{
+    PgPool,
+    types::chrono::{DateTime, NaiveDateTime, Utc},
+};
 
-use crate::{entities::Measurement, services};
+use crate::{
+    controllers::{
+        error::ApiError,
+        extract::{Json, Path, Query},
+        middleware::{RequireAuth, RequireDevice},
+        pagination::PaginatedResponse,
+    },
+    entities::{Measurement, MeasurementRollup, MetricSummary, QuarantinedMeasurement},
+    services::{
+        self,
+        downsample::{DEFAULT_MAX_POINTS, Metric},
+        jwt::Claims,
+        measurement::{MeasurementFilter, MeasurementPage, MeasurementSource},
+        plausibility::PlausibilityLimits,
+        rollup::Bucket,
+    },
+    state::AppState,
+};
 
 #[derive(Serialize)]
 pub struct MeasurementResponse {
+    id: i32,
+    #[serde(rename = "potId")]
     pot_id: i32,
+    #[serde(rename = "plantId")]
+    plant_id: Option<i32>,
     timestamp: String,
     #[serde(rename = "soilMoisture")]
     soil_moisture: f32,
@@ -26,8 +48,10 @@ pub struct MeasurementResponse {
 impl From<Measurement> for MeasurementResponse {
     fn from(measurement: Measurement) -> Self {
         MeasurementResponse {
+            id: measurement.id,
             pot_id: measurement.pot_id,
-            timestamp: measurement.timestamp.to_string(),
+            plant_id: measurement.plant_id,
+            timestamp: measurement.timestamp.and_utc().to_rfc3339(),
             soil_moisture: measurement.soil_moisture,
             temperature: measurement.temperature,
             light_level: measurement.light_level,
@@ -50,23 +74,33 @@ pub struct CreateMeasurementPayload {
     battery_level: i32,
 }
 
+#[derive(Serialize)]
+pub struct CreatedMeasurementResponse {
+    #[serde(flatten)]
+    measurement: MeasurementResponse,
+    /// The current configuration version of the pot. The device fetches the
+    /// configuration again when it differs from the one it runs with.
+    #[serde(rename = "configVersion")]
+    config_version: i32,
+    /// Set when the reading was stored but quarantined as implausible.
+    #[serde(rename = "quarantineReasons", skip_serializing_if = "Vec::is_empty")]
+    quarantine_reasons: Vec<String>,
+}
+
 pub async fn create_measurement(
     State(pool): State<PgPool>,
-    Path(pot_id): Path<String>,
+    State(limits): State<Arc<PlausibilityLimits>>,
+    RequireDevice(pot_id): RequireDevice,
     Json(payload): Json<CreateMeasurementPayload>,
-) -> Result<(), StatusCode> {
+) -> Result<(StatusCode, Json<CreatedMeasurementResponse>), ApiError> {
     let timestamp = DateTime::parse_from_rfc3339(&payload.timestamp).map_err(|_| {
         debug!("Failed to parse timestamp: {}", &payload.timestamp);
-        StatusCode::BAD_REQUEST
+        ApiError::validation("timestamp", "must be an RFC 3339 date-time")
     })?;
 
-    let pot_id = pot_id.parse::<i32>().map_err(|_| {
-        debug!("Failed to parse pot_id: {}", &pot_id);
-        StatusCode::BAD_REQUEST
-    })?;
-
-    let _measurement = services::measurement::create_measurement(
+    let (measurement, quarantine_reasons) = services::measurement::create_measurement(
         &pool,
+        &limits,
         pot_id,
         payload.soil_moisture,
         payload.temperature,
@@ -75,20 +109,333 @@ pub async fn create_measurement(
         payload.battery_level,
         timestamp.naive_utc(),
     )
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    .await?;
+    let config_version = services::pot_config::get_config_version(&pool, pot_id).await?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(CreatedMeasurementResponse {
+            measurement: MeasurementResponse::from(measurement),
+            config_version,
+            quarantine_reasons,
+        }),
+    ))
+}
+
+#[derive(Deserialize)]
+pub struct MeasurementQuery {
+    #[serde(rename = "potId")]
+    pot_id: Option<i32>,
+    #[serde(rename = "plantId")]
+    plant_id: Option<i32>,
+    #[serde(rename = "startDate")]
+    start_date: Option<String>,
+    #[serde(rename = "endDate")]
+    end_date: Option<String>,
+    limit: Option<i64>,
+    offset: Option<i64>,
+    cursor: Option<String>,
+}
 
-    Ok(())
+impl MeasurementQuery {
+    fn into_filter(self) -> Result<MeasurementFilter, ApiError> {
+        Ok(MeasurementFilter {
+            pot_id: self.pot_id,
+            plant_id: self.plant_id,
+            start: parse_date("startDate", self.start_date)?,
+            end: parse_date("endDate", self.end_date)?,
+            limit: self.limit,
+            offset: self.offset,
+            cursor: self.cursor,
+        })
+    }
 }
 
-#[axum::debug_handler]
+fn parse_date(field: &str, value: Option<String>) -> Result<Option<NaiveDateTime>, ApiError> {
+    value
+        .map(|value| {
+            DateTime::parse_from_rfc3339(&value)
+                .map(|date| date.naive_utc())
+                .map_err(|_| ApiError::validation(field, "must be an RFC 3339 date-time"))
+        })
+        .transpose()
+}
+
+#[axum::debug_handler(state = AppState)]
 pub async fn get_measurements(
     State(pool): State<PgPool>,
-    Path(pot_id): Path<String>,
-) -> Result<Json<Vec<MeasurementResponse>>, StatusCode> {
-    let measurements = services::measurement::get_measurements(&pool, pot_id)
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    RequireAuth(claims): RequireAuth,
+    Query(query): Query<MeasurementQuery>,
+) -> Result<Json<PaginatedResponse<MeasurementResponse>>, ApiError> {
+    let user = services::user::get_user_by_email(&pool, claims.sub.as_str())
+        .await?
+        .ok_or_else(ApiError::unauthorized)?;
+
+    let page =
+        services::measurement::get_measurements(&pool, user.id, &query.into_filter()?).await?;
+
+    Ok(Json(PaginatedResponse::from(page)))
+}
+
+#[axum::debug_handler(state = AppState)]
+pub async fn get_pot_measurements(
+    State(pool): State<PgPool>,
+    RequireAuth(claims): RequireAuth,
+    Path(pot_id): Path<i32>,
+    Query(query): Query<MeasurementQuery>,
+) -> Result<Json<PaginatedResponse<MeasurementResponse>>, ApiError> {
+    let user = services::user::get_user_by_email(&pool, claims.sub.as_str())
+        .await?
+        .ok_or_else(ApiError::unauthorized)?;
+
+    let filter = MeasurementFilter {
+        pot_id: Some(pot_id),
+        ..query.into_filter()?
+    };
+    let page = services::measurement::get_measurements(&pool, user.id, &filter).await?;
+
+    Ok(Json(PaginatedResponse::from(page)))
+}
+
+/// The plant's measurements across every pot it was in, each annotated
+/// with the pot it was recorded by.
+#[axum::debug_handler(state = AppState)]
+pub async fn get_plant_measurements(
+    State(pool): State<PgPool>,
+    RequireAuth(claims): RequireAuth,
+    Path(plant_id): Path<i32>,
+    Query(query): Query<MeasurementQuery>,
+) -> Result<Json<PaginatedResponse<MeasurementResponse>>, ApiError> {
+    let user = services::user::get_user_by_email(&pool, claims.sub.as_str())
+        .await?
+        .ok_or_else(ApiError::unauthorized)?;
+
+    let filter = MeasurementFilter {
+        plant_id: Some(plant_id),
+        ..query.into_filter()?
+    };
+    let page = services::measurement::get_measurements(&pool, user.id, &filter).await?;
+
+    Ok(Json(PaginatedResponse::from(page)))
+}
+
+#[derive(Serialize)]
+pub struct QuarantinedMeasurementResponse {
+    #[serde(flatten)]
+    measurement: MeasurementResponse,
+    reasons: Vec<String>,
+    #[serde(rename = "quarantinedAt")]
+    quarantined_at: String,
+}
+
+impl From<QuarantinedMeasurement> for QuarantinedMeasurementResponse {
+    fn from(quarantined: QuarantinedMeasurement) -> Self {
+        QuarantinedMeasurementResponse {
+            measurement: MeasurementResponse::from(quarantined.measurement),
+            reasons: quarantined.reasons,
+            quarantined_at: quarantined.quarantined_at.to_rfc3339(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct QuarantineQuery {
+    limit: Option<i64>,
+}
+
+/// Readings of the pot that were stored but left out as implausible.
+pub async fn get_pot_quarantine(
+    State(pool): State<PgPool>,
+    RequireAuth(claims): RequireAuth,
+    Path(pot_id): Path<i32>,
+    Query(query): Query<QuarantineQuery>,
+) -> Result<Json<Vec<QuarantinedMeasurementResponse>>, ApiError> {
+    let user = services::user::get_user_by_email(&pool, claims.sub.as_str())
+        .await?
+        .ok_or_else(ApiError::unauthorized)?;
+
+    let measurements =
+        services::measurement::get_quarantined(&pool, user.id, pot_id, query.limit).await?;
+
+    Ok(Json(
+        measurements
+            .into_iter()
+            .map(QuarantinedMeasurementResponse::from)
+            .collect(),
+    ))
+}
+
+impl From<MeasurementPage> for PaginatedResponse<MeasurementResponse> {
+    fn from(page: MeasurementPage) -> Self {
+        PaginatedResponse::new(
+            page.measurements
+                .into_iter()
+                .map(MeasurementResponse::from)
+                .collect(),
+            page.total,
+            page.limit,
+            page.offset,
+            page.has_more,
+        )
+        .with_next_cursor(page.next_cursor)
+    }
+}
+
+#[derive(Serialize)]
+pub struct MetricSummaryResponse {
+    min: f32,
+    max: f32,
+    avg: f32,
+}
+
+impl From<MetricSummary> for MetricSummaryResponse {
+    fn from(summary: MetricSummary) -> Self {
+        MetricSummaryResponse {
+            min: summary.min,
+            max: summary.max,
+            avg: summary.avg,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct MeasurementRollupResponse {
+    #[serde(rename = "bucketStart")]
+    bucket_start: String,
+    count: i64,
+    #[serde(rename = "soilMoisture")]
+    soil_moisture: MetricSummaryResponse,
+    temperature: MetricSummaryResponse,
+    #[serde(rename = "lightLevel")]
+    light_level: MetricSummaryResponse,
+    humidity: MetricSummaryResponse,
+}
+
+impl From<MeasurementRollup> for MeasurementRollupResponse {
+    fn from(rollup: MeasurementRollup) -> Self {
+        MeasurementRollupResponse {
+            bucket_start: rollup.bucket_start.and_utc().to_rfc3339(),
+            count: rollup.count,
+            soil_moisture: rollup.soil_moisture.into(),
+            temperature: rollup.temperature.into(),
+            light_level: rollup.light_level.into(),
+            humidity: rollup.humidity.into(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct RollupQuery {
+    bucket: Bucket,
+    #[serde(rename = "startDate")]
+    start_date: Option<String>,
+    #[serde(rename = "endDate")]
+    end_date: Option<String>,
+}
+
+pub async fn get_pot_rollups(
+    State(pool): State<PgPool>,
+    RequireAuth(claims): RequireAuth,
+    Path(pot_id): Path<i32>,
+    Query(query): Query<RollupQuery>,
+) -> Result<Json<Vec<MeasurementRollupResponse>>, ApiError> {
+    get_rollups(&pool, &claims, MeasurementSource::Pot(pot_id), query).await
+}
+
+pub async fn get_plant_rollups(
+    State(pool): State<PgPool>,
+    RequireAuth(claims): RequireAuth,
+    Path(plant_id): Path<i32>,
+    Query(query): Query<RollupQuery>,
+) -> Result<Json<Vec<MeasurementRollupResponse>>, ApiError> {
+    get_rollups(&pool, &claims, MeasurementSource::Plant(plant_id), query).await
+}
+
+async fn get_rollups(
+    pool: &PgPool,
+    claims: &Claims,
+    source: MeasurementSource,
+    query: RollupQuery,
+) -> Result<Json<Vec<MeasurementRollupResponse>>, ApiError> {
+    let user = services::user::get_user_by_email(pool, claims.sub.as_str())
+        .await?
+        .ok_or_else(ApiError::unauthorized)?;
+
+    let rollups = services::rollup::get_rollups(
+        pool,
+        user.id,
+        source,
+        query.bucket,
+        parse_date("startDate", query.start_date)?,
+        parse_date("endDate", query.end_date)?,
+    )
+    .await?;
+
+    Ok(Json(
+        rollups
+            .into_iter()
+            .map(MeasurementRollupResponse::from)
+            .collect(),
+    ))
+}
+
+const DEFAULT_DOWNSAMPLE_RANGE: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+#[derive(Deserialize)]
+pub struct DownsampleQuery {
+    #[serde(default)]
+    metric: Metric,
+    #[serde(rename = "maxPoints")]
+    max_points: Option<usize>,
+    #[serde(rename = "startDate")]
+    start_date: Option<String>,
+    #[serde(rename = "endDate")]
+    end_date: Option<String>,
+}
+
+pub async fn get_pot_downsampled(
+    State(pool): State<PgPool>,
+    RequireAuth(claims): RequireAuth,
+    Path(pot_id): Path<i32>,
+    Query(query): Query<DownsampleQuery>,
+) -> Result<Json<Vec<MeasurementResponse>>, ApiError> {
+    get_downsampled(&pool, &claims, MeasurementSource::Pot(pot_id), query).await
+}
+
+pub async fn get_plant_downsampled(
+    State(pool): State<PgPool>,
+    RequireAuth(claims): RequireAuth,
+    Path(plant_id): Path<i32>,
+    Query(query): Query<DownsampleQuery>,
+) -> Result<Json<Vec<MeasurementResponse>>, ApiError> {
+    get_downsampled(&pool, &claims, MeasurementSource::Plant(plant_id), query).await
+}
+
+/// Without an explicit range the last week up to now is downsampled.
+async fn get_downsampled(
+    pool: &PgPool,
+    claims: &Claims,
+    source: MeasurementSource,
+    query: DownsampleQuery,
+) -> Result<Json<Vec<MeasurementResponse>>, ApiError> {
+    let user = services::user::get_user_by_email(pool, claims.sub.as_str())
+        .await?
+        .ok_or_else(ApiError::unauthorized)?;
+
+    let end = parse_date("endDate", query.end_date)?.unwrap_or_else(|| Utc::now().naive_utc());
+    let start = parse_date("startDate", query.start_date)?
+        .unwrap_or_else(|| end - DEFAULT_DOWNSAMPLE_RANGE);
+
+    let measurements = services::downsample::get_downsampled(
+        pool,
+        user.id,
+        source,
+        query.metric,
+        query.max_points.unwrap_or(DEFAULT_MAX_POINTS),
+        start,
+        end,
+    )
+    .await?;
 
     Ok(Json(
         measurements
@@ -1,43 +1,223 @@
-use crate::services::jwt::{Claims, verify_jwt};
+use std::{collections::HashMap, net::SocketAddr, sync::Arc};
+
+use crate::{
+    controllers::error::ApiError,
+    services::{
+        self,
+        jwt::{Claims, JwtKeys},
+    },
+};
 use anyhow::Result;
 use axum::{
-    extract::FromRequestParts,
-    http::{HeaderMap, StatusCode, header, request::Parts},
+    extract::{ConnectInfo, FromRef, FromRequestParts, Path, Query},
+    http::{HeaderMap, header, request::Parts},
 };
 use log::debug;
+use sqlx::PgPool;
 
+/// Requires a valid access token whose session has not been revoked, so
+/// logging out or revoking a session cuts off its access tokens at once.
 pub struct RequireAuth(pub Claims);
 
 impl<S> FromRequestParts<S> for RequireAuth
 where
+    Arc<JwtKeys>: FromRef<S>,
+    PgPool: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let Some(token) = get_token(&parts.headers) else {
+            debug!("Authorization header not found");
+            return Err(ApiError::unauthorized());
+        };
+
+        Ok(RequireAuth(authenticate(state, token).await?))
+    }
+}
+
+/// Like [`RequireAuth`], but also accepts the access token as a `token` query
+/// parameter, since browsers cannot set headers on WebSocket upgrades.
+pub struct RequireSocketAuth(pub Claims);
+
+impl<S> FromRequestParts<S> for RequireSocketAuth
+where
+    Arc<JwtKeys>: FromRef<S>,
+    PgPool: FromRef<S>,
     S: Send + Sync,
 {
-    type Rejection = StatusCode;
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let Query(mut params) = Query::<HashMap<String, String>>::try_from_uri(&parts.uri)
+            .map_err(|_| ApiError::unauthorized())?;
+        let Some(token) = get_token(&parts.headers)
+            .map(str::to_string)
+            .or_else(|| params.remove("token"))
+        else {
+            debug!("Neither authorization header nor token parameter found");
+            return Err(ApiError::unauthorized());
+        };
+
+        Ok(RequireSocketAuth(authenticate(state, &token).await?))
+    }
+}
 
-    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
-        if let Some(token) = get_token(&parts.headers) {
-            let claims: Claims = verify_jwt(token).map_err(|_| {
-                debug!("Invalid JWT token");
-                StatusCode::UNAUTHORIZED
+/// Verifies the access token and checks that its session is still active.
+async fn authenticate<S>(state: &S, token: &str) -> Result<Claims, ApiError>
+where
+    Arc<JwtKeys>: FromRef<S>,
+    PgPool: FromRef<S>,
+{
+    let keys = Arc::<JwtKeys>::from_ref(state);
+    let claims = keys.verify(token).map_err(|e| {
+        debug!("Invalid JWT token: {}", e);
+        ApiError::unauthorized()
+    })?;
+
+    let pool = PgPool::from_ref(state);
+    if !services::session::is_active(&pool, claims.sid).await? {
+        debug!("Access token of revoked session {}", claims.sid);
+        return Err(ApiError::unauthorized());
+    }
+
+    Ok(claims)
+}
+
+/// Authenticates a sensor pot by its device credential, sent as
+/// `Authorization: Device <credential>`. The credential must have been issued
+/// for the pot in the `pot_id` path parameter, whose id is extracted.
+pub struct RequireDevice(pub i32);
+
+impl<S> FromRequestParts<S> for RequireDevice
+where
+    PgPool: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let Path(params) =
+            Path::<HashMap<String, String>>::from_request_parts(parts, state).await?;
+        let pot_id = params
+            .get("pot_id")
+            .and_then(|pot_id| pot_id.parse::<i32>().ok())
+            .ok_or_else(|| ApiError::validation("pot_id", "must be an integer"))?;
+
+        let Some(credential) = get_device_credential(&parts.headers) else {
+            debug!("Device credential not found");
+            return Err(ApiError::unauthorized());
+        };
+
+        let pool = PgPool::from_ref(state);
+        let credential_pot_id = services::device::verify_credential(&pool, credential)
+            .await?
+            .ok_or_else(|| {
+                debug!("Invalid device credential");
+                ApiError::unauthorized()
             })?;
 
-            Ok(RequireAuth(claims))
+        if credential_pot_id != pot_id {
+            debug!(
+                "Device credential for pot {} used for pot {}",
+                credential_pot_id, pot_id
+            );
+            return Err(ApiError::forbidden(
+                "The device credential was issued for another pot",
+            ));
+        }
+
+        Ok(RequireDevice(pot_id))
+    }
+}
+
+/// Accepts either the owner's access token or the device credential of the
+/// pot in the `pot_id` path parameter, for resources both of them read.
+pub enum RequireUserOrDevice {
+    User(Claims),
+    Device(i32),
+}
+
+impl<S> FromRequestParts<S> for RequireUserOrDevice
+where
+    Arc<JwtKeys>: FromRef<S>,
+    PgPool: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        if get_device_credential(&parts.headers).is_some() {
+            let RequireDevice(pot_id) = RequireDevice::from_request_parts(parts, state).await?;
+            Ok(RequireUserOrDevice::Device(pot_id))
         } else {
-            debug!("Authorization header not found");
-            Err(StatusCode::UNAUTHORIZED)
+            let RequireAuth(claims) = RequireAuth::from_request_parts(parts, state).await?;
+            Ok(RequireUserOrDevice::User(claims))
         }
     }
 }
 
 fn get_token(headers: &HeaderMap) -> Option<&str> {
+    get_authorization(headers, "Bearer ")
+}
+
+fn get_device_credential(headers: &HeaderMap) -> Option<&str> {
+    get_authorization(headers, "Device ")
+}
+
+fn get_authorization<'a>(headers: &'a HeaderMap, scheme: &str) -> Option<&'a str> {
     headers
         .get(header::AUTHORIZATION)
         .and_then(|value| value.to_str().ok())
-        .and_then(|auth_header| {
-            if auth_header.starts_with("Bearer ") {
-                Some(&auth_header[7..])
-            } else {
-                None
-            }
-        })
+        .and_then(|auth_header| auth_header.strip_prefix(scheme))
+}
+
+/// Whether the server runs behind a proxy that appends the address of its
+/// peer to `X-Forwarded-For`, read from `TRUST_PROXY`.
+#[derive(Clone, Copy)]
+pub struct TrustProxy(pub bool);
+
+impl TrustProxy {
+    pub fn from_env() -> Result<Self> {
+        match std::env::var("TRUST_PROXY") {
+            Ok(value) => Ok(TrustProxy(value.parse()?)),
+            Err(_) => Ok(TrustProxy(false)),
+        }
+    }
+}
+
+/// The network address of the client. Behind a trusted proxy this is the
+/// last `X-Forwarded-For` entry, the one the proxy added; earlier entries
+/// are up to the client.
+pub struct ClientAddr(pub String);
+
+impl<S> FromRequestParts<S> for ClientAddr
+where
+    TrustProxy: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        if TrustProxy::from_ref(state).0
+            && let Some(addr) = parts
+                .headers
+                .get_all("x-forwarded-for")
+                .iter()
+                .filter_map(|value| value.to_str().ok())
+                .flat_map(|value| value.split(','))
+                .map(str::trim)
+                .rfind(|addr| !addr.is_empty())
+        {
+            return Ok(ClientAddr(addr.to_string()));
+        }
+
+        let ConnectInfo(addr) = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .ok_or_else(ApiError::internal)?;
+
+        Ok(ClientAddr(addr.ip().to_string()))
+    }
 }
@@ -1,9 +1,22 @@
 mod auth;
+mod automation;
+mod error;
+mod extract;
+mod identify;
 mod link;
+mod live;
 mod measurement;
 mod middleware;
+mod pagination;
+mod pairing;
+mod photo;
 mod plant;
+mod plant_type;
 mod pot;
+mod pot_config;
 mod routes;
+mod task;
+mod timelapse;
 
+pub use middleware::TrustProxy;
 pub use routes::create_routes;
@@ -0,0 +1,39 @@
+use serde::Serialize;
+
+/// The `{data, pagination}` envelope returned by list endpoints.
+#[derive(Serialize)]
+pub struct PaginatedResponse<T> {
+    data: Vec<T>,
+    pagination: Pagination,
+}
+
+#[derive(Serialize)]
+pub struct Pagination {
+    total: i64,
+    limit: i64,
+    offset: i64,
+    #[serde(rename = "hasMore")]
+    has_more: bool,
+    #[serde(rename = "nextCursor", skip_serializing_if = "Option::is_none")]
+    next_cursor: Option<String>,
+}
+
+impl<T> PaginatedResponse<T> {
+    pub fn new(data: Vec<T>, total: i64, limit: i64, offset: i64, has_more: bool) -> Self {
+        PaginatedResponse {
+            data,
+            pagination: Pagination {
+                total,
+                limit,
+                offset,
+                has_more,
+                next_cursor: None,
+            },
+        }
+    }
+
+    pub fn with_next_cursor(mut self, next_cursor: Option<String>) -> Self {
+        self.pagination.next_cursor = next_cursor;
+        self
+    }
+}
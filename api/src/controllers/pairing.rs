@@ -0,0 +1,106 @@
+use axum::{extract::State, http::StatusCode};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+
+use crate::{
+    controllers::{
+        error::ApiError, extract::Json, middleware::ClientAddr, pot::IssuedCredentialResponse,
+    },
+    services::{
+        self,
+        pairing::{PairingCode, PairingRequest, PairingStatus},
+    },
+};
+
+#[derive(Deserialize)]
+pub struct PairingPayload {
+    #[serde(rename = "serialNumber")]
+    serial_number: String,
+    model: Option<String>,
+    #[serde(rename = "firmwareVersion")]
+    firmware_version: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct PairingResponse {
+    /// Shown to the user, who claims the pot with it.
+    #[serde(rename = "pairingCode")]
+    pairing_code: String,
+    /// Kept by the device to collect its credential.
+    #[serde(rename = "pairingToken")]
+    pairing_token: String,
+    #[serde(rename = "expiresAt")]
+    expires_at: String,
+}
+
+impl From<PairingCode> for PairingResponse {
+    fn from(code: PairingCode) -> Self {
+        PairingResponse {
+            pairing_code: code.code,
+            pairing_token: code.token,
+            expires_at: code.expires_at.to_rfc3339(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct PairingStatusPayload {
+    #[serde(rename = "pairingToken")]
+    pairing_token: String,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "status", rename_all = "camelCase")]
+pub enum PairingStatusResponse {
+    Pending {
+        #[serde(rename = "expiresAt")]
+        expires_at: String,
+    },
+    Claimed {
+        #[serde(rename = "potId")]
+        pot_id: i32,
+        #[serde(rename = "deviceCredential")]
+        device_credential: IssuedCredentialResponse,
+    },
+}
+
+impl From<PairingStatus> for PairingStatusResponse {
+    fn from(status: PairingStatus) -> Self {
+        match status {
+            PairingStatus::Pending { expires_at } => PairingStatusResponse::Pending {
+                expires_at: expires_at.to_rfc3339(),
+            },
+            PairingStatus::Claimed { pot_id, credential } => PairingStatusResponse::Claimed {
+                pot_id,
+                device_credential: IssuedCredentialResponse::from(credential),
+            },
+        }
+    }
+}
+
+/// Called by an unclaimed device to get a pairing code.
+pub async fn start_pairing(
+    State(pool): State<PgPool>,
+    ClientAddr(client): ClientAddr,
+    Json(payload): Json<PairingPayload>,
+) -> Result<(StatusCode, Json<PairingResponse>), ApiError> {
+    let request = PairingRequest {
+        serial_number: payload.serial_number,
+        model: payload.model,
+        firmware_version: payload.firmware_version,
+    };
+    let code = services::pairing::start_pairing(&pool, &request, &client).await?;
+
+    Ok((StatusCode::CREATED, Json(PairingResponse::from(code))))
+}
+
+/// Polled by the device until its pot is claimed. The credential is only
+/// returned once.
+pub async fn get_pairing_status(
+    State(pool): State<PgPool>,
+    Json(payload): Json<PairingStatusPayload>,
+) -> Result<Json<PairingStatusResponse>, ApiError> {
+    let status = services::pairing::get_pairing_status(&pool, &payload.pairing_token).await?;
+
+    Ok(Json(PairingStatusResponse::from(status)))
+}
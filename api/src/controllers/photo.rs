@@ -0,0 +1,235 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::State,
+    http::{StatusCode, header},
+    response::IntoResponse,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::{
+    PgPool,
+    types::chrono::{DateTime, Utc},
+};
+
+use crate::{
+    controllers::{
+        error::ApiError,
+        extract::{Json, Multipart, Path, Query},
+        middleware::RequireAuth,
+        pagination::PaginatedResponse,
+    },
+    entities::PlantPhoto,
+    services::{
+        self,
+        photo::{PhotoPage, PhotoUpload, PhotoVariant},
+        storage::Storage,
+    },
+};
+
+#[derive(Serialize)]
+pub struct PhotoResponse {
+    id: i32,
+    #[serde(rename = "plantId")]
+    plant_id: i32,
+    #[serde(rename = "contentType")]
+    content_type: String,
+    width: i32,
+    height: i32,
+    #[serde(rename = "capturedAt")]
+    captured_at: String,
+    /// `explicit`, `exif` or `upload`.
+    #[serde(rename = "captureSource")]
+    capture_source: &'static str,
+    #[serde(rename = "imageUrl")]
+    image_url: String,
+    #[serde(rename = "thumbnailUrl")]
+    thumbnail_url: String,
+    #[serde(rename = "createdAt")]
+    created_at: String,
+}
+
+impl From<PlantPhoto> for PhotoResponse {
+    fn from(photo: PlantPhoto) -> Self {
+        let url = format!("/api/v1/plants/{}/photos/{}", photo.plant_id, photo.id);
+        PhotoResponse {
+            id: photo.id,
+            plant_id: photo.plant_id,
+            content_type: photo.content_type,
+            width: photo.width,
+            height: photo.height,
+            captured_at: photo.captured_at.to_rfc3339(),
+            capture_source: photo.capture_source.as_str(),
+            image_url: format!("{url}/image"),
+            thumbnail_url: format!("{url}/thumbnail"),
+            created_at: photo.created_at.to_rfc3339(),
+        }
+    }
+}
+
+impl From<PhotoPage> for PaginatedResponse<PhotoResponse> {
+    fn from(page: PhotoPage) -> Self {
+        PaginatedResponse::new(
+            page.photos.into_iter().map(PhotoResponse::from).collect(),
+            page.total,
+            page.limit,
+            page.offset,
+            page.has_more,
+        )
+    }
+}
+
+#[derive(Deserialize)]
+pub struct PhotoQuery {
+    limit: Option<i64>,
+    offset: Option<i64>,
+}
+
+/// Reads the `photo` file part and the optional `capturedAt` text part.
+async fn read_upload(mut multipart: axum::extract::Multipart) -> Result<PhotoUpload, ApiError> {
+    let mut data = None;
+    let mut captured_at = None;
+
+    while let Some(field) = multipart.next_field().await? {
+        match field.name() {
+            Some("photo") => data = Some(field.bytes().await?),
+            Some("capturedAt") => {
+                let value = field.text().await?;
+                let date = DateTime::parse_from_rfc3339(value.trim()).map_err(|_| {
+                    ApiError::validation("capturedAt", "must be an RFC 3339 date-time")
+                })?;
+                captured_at = Some(date.with_timezone(&Utc));
+            }
+            _ => {}
+        }
+    }
+
+    Ok(PhotoUpload {
+        data: data.ok_or_else(|| ApiError::validation("photo", "is required"))?,
+        captured_at,
+    })
+}
+
+pub async fn upload_photo(
+    State(pool): State<PgPool>,
+    State(storage): State<Arc<dyn Storage>>,
+    RequireAuth(claims): RequireAuth,
+    Path(plant_id): Path<i32>,
+    Multipart(multipart): Multipart,
+) -> Result<(StatusCode, Json<PhotoResponse>), ApiError> {
+    let user = services::user::get_user_by_email(&pool, claims.sub.as_str())
+        .await?
+        .ok_or_else(ApiError::unauthorized)?;
+
+    let upload = read_upload(multipart).await?;
+    let photo =
+        services::photo::upload_photo(&pool, storage.as_ref(), user.id, plant_id, upload).await?;
+
+    Ok((StatusCode::CREATED, Json(PhotoResponse::from(photo))))
+}
+
+pub async fn get_photos(
+    State(pool): State<PgPool>,
+    RequireAuth(claims): RequireAuth,
+    Path(plant_id): Path<i32>,
+    Query(query): Query<PhotoQuery>,
+) -> Result<Json<PaginatedResponse<PhotoResponse>>, ApiError> {
+    let user = services::user::get_user_by_email(&pool, claims.sub.as_str())
+        .await?
+        .ok_or_else(ApiError::unauthorized)?;
+
+    let page =
+        services::photo::get_photos(&pool, user.id, plant_id, query.limit, query.offset).await?;
+
+    Ok(Json(PaginatedResponse::from(page)))
+}
+
+pub async fn get_photo(
+    State(pool): State<PgPool>,
+    RequireAuth(claims): RequireAuth,
+    Path((plant_id, photo_id)): Path<(i32, i32)>,
+) -> Result<Json<PhotoResponse>, ApiError> {
+    let user = services::user::get_user_by_email(&pool, claims.sub.as_str())
+        .await?
+        .ok_or_else(ApiError::unauthorized)?;
+
+    let photo = services::photo::get_photo(&pool, user.id, plant_id, photo_id).await?;
+
+    Ok(Json(PhotoResponse::from(photo)))
+}
+
+async fn serve_photo(
+    pool: &PgPool,
+    storage: &dyn Storage,
+    email: &str,
+    plant_id: i32,
+    photo_id: i32,
+    variant: PhotoVariant,
+) -> Result<impl IntoResponse + use<>, ApiError> {
+    let user = services::user::get_user_by_email(pool, email)
+        .await?
+        .ok_or_else(ApiError::unauthorized)?;
+
+    let photo = services::photo::get_photo(pool, user.id, plant_id, photo_id).await?;
+    let (data, content_type) = services::photo::read_photo(storage, &photo, variant).await?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, content_type),
+            // Stored files never change, a new upload gets a new id.
+            (
+                header::CACHE_CONTROL,
+                "private, max-age=31536000, immutable".to_string(),
+            ),
+        ],
+        data,
+    ))
+}
+
+pub async fn get_photo_image(
+    State(pool): State<PgPool>,
+    State(storage): State<Arc<dyn Storage>>,
+    RequireAuth(claims): RequireAuth,
+    Path((plant_id, photo_id)): Path<(i32, i32)>,
+) -> Result<impl IntoResponse, ApiError> {
+    serve_photo(
+        &pool,
+        storage.as_ref(),
+        claims.sub.as_str(),
+        plant_id,
+        photo_id,
+        PhotoVariant::Original,
+    )
+    .await
+}
+
+pub async fn get_photo_thumbnail(
+    State(pool): State<PgPool>,
+    State(storage): State<Arc<dyn Storage>>,
+    RequireAuth(claims): RequireAuth,
+    Path((plant_id, photo_id)): Path<(i32, i32)>,
+) -> Result<impl IntoResponse, ApiError> {
+    serve_photo(
+        &pool,
+        storage.as_ref(),
+        claims.sub.as_str(),
+        plant_id,
+        photo_id,
+        PhotoVariant::Thumbnail,
+    )
+    .await
+}
+
+pub async fn delete_photo(
+    State(pool): State<PgPool>,
+    State(storage): State<Arc<dyn Storage>>,
+    RequireAuth(claims): RequireAuth,
+    Path((plant_id, photo_id)): Path<(i32, i32)>,
+) -> Result<StatusCode, ApiError> {
+    let user = services::user::get_user_by_email(&pool, claims.sub.as_str())
+        .await?
+        .ok_or_else(ApiError::unauthorized)?;
+
+    services::photo::delete_photo(&pool, storage.as_ref(), user.id, plant_id, photo_id).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
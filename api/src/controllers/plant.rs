@@ -1,15 +1,21 @@
-use axum::{
-    Json,
-    extract::{Path, State},
-    http::StatusCode,
-};
-use serde::{Deserialize, Serialize};
-use sqlx::PgPool;
+use std::sync::Arc;
+
+use axum::{extract::State, http::StatusCode};
+use serde::{Deserialize, Deserializer, Serialize};
+use sqlx::{PgPool, types::chrono::NaiveDate};
 
 use crate::{
-    controllers::middleware::RequireAuth,
+    controllers::{
+        error::ApiError,
+        extract::{Json, Path, Query},
+        middleware::RequireAuth,
+    },
     entities::{Plant, User},
-    services::{self},
+    services::{
+        self,
+        plant::{PlantInput, PlantPatch},
+        storage::Storage,
+    },
 };
 
 #[derive(Serialize, Deserialize)]
@@ -18,6 +24,16 @@ pub struct PlantResponse {
     name: String,
     #[serde(rename = "owner")]
     owner_email: String,
+    #[serde(rename = "plantTypeId")]
+    plant_type_id: Option<i32>,
+    species: Option<String>,
+    location: Option<String>,
+    notes: Option<String>,
+    #[serde(rename = "imageUrl")]
+    image_url: Option<String>,
+    #[serde(rename = "acquiredOn")]
+    acquired_on: Option<String>,
+    archived: bool,
 }
 
 impl PlantResponse {
@@ -26,6 +42,29 @@ impl PlantResponse {
             id: plant.id,
             name: plant.name,
             owner_email: owner.email,
+            plant_type_id: plant.plant_type_id,
+            species: plant.species,
+            location: plant.location,
+            notes: plant.notes,
+            image_url: plant.image_url,
+            acquired_on: plant.acquired_on.map(|date| date.to_string()),
+            archived: plant.archived,
+        }
+    }
+}
+
+/// The plant as shown next to the pot it is in.
+#[derive(Serialize, Deserialize)]
+pub struct PlantSummaryResponse {
+    id: i32,
+    name: String,
+}
+
+impl From<Plant> for PlantSummaryResponse {
+    fn from(plant: Plant) -> Self {
+        PlantSummaryResponse {
+            id: plant.id,
+            name: plant.name,
         }
     }
 }
@@ -33,21 +72,105 @@ impl PlantResponse {
 #[derive(Serialize, Deserialize)]
 pub struct CreatePlantPayload {
     name: String,
+    #[serde(rename = "plantTypeId")]
+    plant_type_id: Option<i32>,
+    species: Option<String>,
+    location: Option<String>,
+    notes: Option<String>,
+    #[serde(rename = "imageUrl")]
+    image_url: Option<String>,
+    #[serde(rename = "acquiredOn")]
+    acquired_on: Option<String>,
+    #[serde(default)]
+    archived: bool,
+}
+
+impl CreatePlantPayload {
+    fn to_input(&self) -> Result<PlantInput, ApiError> {
+        Ok(PlantInput {
+            name: self.name.clone(),
+            plant_type_id: self.plant_type_id,
+            species: self.species.clone(),
+            location: self.location.clone(),
+            notes: self.notes.clone(),
+            image_url: self.image_url.clone(),
+            acquired_on: self.acquired_on.as_deref().map(parse_date).transpose()?,
+            archived: self.archived,
+        })
+    }
+}
+
+/// Body of `PATCH /plants/{plant_id}`. Omitted fields are left unchanged,
+/// `null` clears a field.
+#[derive(Deserialize)]
+pub struct PatchPlantPayload {
+    name: Option<String>,
+    #[serde(rename = "plantTypeId", default, deserialize_with = "nullable")]
+    plant_type_id: Option<Option<i32>>,
+    #[serde(default, deserialize_with = "nullable")]
+    species: Option<Option<String>>,
+    #[serde(default, deserialize_with = "nullable")]
+    location: Option<Option<String>>,
+    #[serde(default, deserialize_with = "nullable")]
+    notes: Option<Option<String>>,
+    #[serde(rename = "imageUrl", default, deserialize_with = "nullable")]
+    image_url: Option<Option<String>>,
+    #[serde(rename = "acquiredOn", default, deserialize_with = "nullable")]
+    acquired_on: Option<Option<String>>,
+    archived: Option<bool>,
+}
+
+impl PatchPlantPayload {
+    fn into_patch(self) -> Result<PlantPatch, ApiError> {
+        let acquired_on = match self.acquired_on {
+            Some(Some(value)) => Some(Some(parse_date(&value)?)),
+            Some(None) => Some(None),
+            None => None,
+        };
+
+        Ok(PlantPatch {
+            name: self.name,
+            plant_type_id: self.plant_type_id,
+            species: self.species,
+            location: self.location,
+            notes: self.notes,
+            image_url: self.image_url,
+            acquired_on,
+            archived: self.archived,
+        })
+    }
+}
+
+/// Tells a field set to `null` apart from a missing one, which `default`
+/// turns into `None`.
+fn nullable<'de, T, D>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+fn parse_date(value: &str) -> Result<NaiveDate, ApiError> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map_err(|_| ApiError::validation("acquiredOn", "must be a date in YYYY-MM-DD format"))
+}
+
+#[derive(Deserialize)]
+pub struct PlantQuery {
+    archived: Option<bool>,
 }
 
 pub async fn create_plant(
     State(pool): State<PgPool>,
     RequireAuth(claims): RequireAuth,
     Json(payload): Json<CreatePlantPayload>,
-) -> Result<Json<PlantResponse>, StatusCode> {
+) -> Result<Json<PlantResponse>, ApiError> {
     let user = services::user::get_user_by_email(&pool, claims.sub.as_str())
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
-        .ok_or(StatusCode::UNAUTHORIZED)?;
+        .await?
+        .ok_or_else(ApiError::unauthorized)?;
 
-    let plant = services::plant::create_plant(&pool, payload.name.as_str(), user.id)
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let plant = services::plant::create_plant(&pool, &payload.to_input()?, user.id).await?;
 
     Ok(Json(PlantResponse::from(plant, user)))
 }
@@ -56,32 +179,26 @@ pub async fn get_plant(
     State(pool): State<PgPool>,
     RequireAuth(claims): RequireAuth,
     Path(plant_id): Path<i32>,
-) -> Result<Json<PlantResponse>, StatusCode> {
+) -> Result<Json<PlantResponse>, ApiError> {
     let user = services::user::get_user_by_email(&pool, claims.sub.as_str())
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
-        .ok_or(StatusCode::UNAUTHORIZED)?;
+        .await?
+        .ok_or_else(ApiError::unauthorized)?;
 
-    let plants = services::plant::find_plant_by_id(&pool, plant_id)
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
-        .ok_or(StatusCode::NOT_FOUND)?;
+    let plant = services::plant::get_plant(&pool, user.id, plant_id).await?;
 
-    Ok(Json(PlantResponse::from(plants, user)))
+    Ok(Json(PlantResponse::from(plant, user)))
 }
 
 pub async fn get_plants(
     State(pool): State<PgPool>,
     RequireAuth(claims): RequireAuth,
-) -> Result<Json<Vec<PlantResponse>>, StatusCode> {
+    Query(query): Query<PlantQuery>,
+) -> Result<Json<Vec<PlantResponse>>, ApiError> {
     let user = services::user::get_user_by_email(&pool, claims.sub.as_str())
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
-        .ok_or(StatusCode::UNAUTHORIZED)?;
+        .await?
+        .ok_or_else(ApiError::unauthorized)?;
 
-    let plants = services::plant::get_plants(&pool)
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let plants = services::plant::get_plants(&pool, user.id, query.archived).await?;
 
     let response: Vec<PlantResponse> = plants
         .into_iter()
@@ -96,26 +213,48 @@ pub async fn update_plant(
     RequireAuth(claims): RequireAuth,
     Path(plant_id): Path<i32>,
     Json(payload): Json<CreatePlantPayload>,
-) -> Result<Json<PlantResponse>, StatusCode> {
+) -> Result<Json<PlantResponse>, ApiError> {
+    let user = services::user::get_user_by_email(&pool, claims.sub.as_str())
+        .await?
+        .ok_or_else(ApiError::unauthorized)?;
+
+    let plant =
+        services::plant::update_plant(&pool, user.id, plant_id, &payload.to_input()?).await?;
+
+    Ok(Json(PlantResponse::from(plant, user)))
+}
+
+pub async fn patch_plant(
+    State(pool): State<PgPool>,
+    RequireAuth(claims): RequireAuth,
+    Path(plant_id): Path<i32>,
+    Json(payload): Json<PatchPlantPayload>,
+) -> Result<Json<PlantResponse>, ApiError> {
     let user = services::user::get_user_by_email(&pool, claims.sub.as_str())
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
-        .ok_or(StatusCode::UNAUTHORIZED)?;
+        .await?
+        .ok_or_else(ApiError::unauthorized)?;
 
-    let plant = services::plant::update_plant(&pool, plant_id, payload.name.as_str())
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let plant =
+        services::plant::patch_plant(&pool, user.id, plant_id, payload.into_patch()?).await?;
 
     Ok(Json(PlantResponse::from(plant, user)))
 }
 
 pub async fn delete_plant(
     State(pool): State<PgPool>,
+    State(storage): State<Arc<dyn Storage>>,
+    RequireAuth(claims): RequireAuth,
     Path(plant_id): Path<i32>,
-) -> Result<StatusCode, StatusCode> {
-    services::plant::delete_plant(&pool, plant_id)
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+) -> Result<StatusCode, ApiError> {
+    let user = services::user::get_user_by_email(&pool, claims.sub.as_str())
+        .await?
+        .ok_or_else(ApiError::unauthorized)?;
+
+    // The rows would cascade, but the stored files have to go explicitly.
+    services::photo::delete_plant_photos(&pool, storage.as_ref(), user.id, plant_id).await?;
+    services::timelapse::delete_plant_timelapses(&pool, storage.as_ref(), user.id, plant_id)
+        .await?;
+    services::plant::delete_plant(&pool, user.id, plant_id).await?;
 
     Ok(StatusCode::NO_CONTENT)
 }
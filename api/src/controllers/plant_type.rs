@@ -0,0 +1,86 @@
+use axum::extract::State;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+
+use crate::{
+    controllers::{
+        error::ApiError,
+        extract::{Json, Path, Query},
+    },
+    entities::PlantType,
+    services,
+};
+
+#[derive(Serialize)]
+pub struct RangeResponse {
+    min: f32,
+    max: f32,
+}
+
+impl From<(f32, f32)> for RangeResponse {
+    fn from((min, max): (f32, f32)) -> Self {
+        RangeResponse { min, max }
+    }
+}
+
+#[derive(Serialize)]
+pub struct PlantTypeResponse {
+    id: i32,
+    #[serde(rename = "commonName")]
+    common_name: String,
+    #[serde(rename = "scientificName")]
+    scientific_name: String,
+    #[serde(rename = "wateringIntervalDays")]
+    watering_interval_days: i32,
+    #[serde(rename = "fertilizationIntervalDays")]
+    fertilization_interval_days: i32,
+    #[serde(rename = "moistureRange")]
+    moisture_range: RangeResponse,
+    #[serde(rename = "lightRange")]
+    light_range: RangeResponse,
+    #[serde(rename = "temperatureRange")]
+    temperature_range: RangeResponse,
+}
+
+impl From<PlantType> for PlantTypeResponse {
+    fn from(plant_type: PlantType) -> Self {
+        PlantTypeResponse {
+            id: plant_type.id,
+            common_name: plant_type.common_name,
+            scientific_name: plant_type.scientific_name,
+            watering_interval_days: plant_type.watering_interval_days,
+            fertilization_interval_days: plant_type.fertilization_interval_days,
+            moisture_range: RangeResponse::from(plant_type.moisture_range),
+            light_range: RangeResponse::from(plant_type.light_range),
+            temperature_range: RangeResponse::from(plant_type.temperature_range),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct PlantTypeQuery {
+    q: Option<String>,
+}
+
+pub async fn search_plant_types(
+    State(pool): State<PgPool>,
+    Query(query): Query<PlantTypeQuery>,
+) -> Result<Json<Vec<PlantTypeResponse>>, ApiError> {
+    let plant_types = services::plant_type::search_plant_types(&pool, query.q.as_deref()).await?;
+
+    Ok(Json(
+        plant_types
+            .into_iter()
+            .map(PlantTypeResponse::from)
+            .collect(),
+    ))
+}
+
+pub async fn get_plant_type(
+    State(pool): State<PgPool>,
+    Path(plant_type_id): Path<i32>,
+) -> Result<Json<PlantTypeResponse>, ApiError> {
+    let plant_type = services::plant_type::get_plant_type(&pool, plant_type_id).await?;
+
+    Ok(Json(PlantTypeResponse::from(plant_type)))
+}
@@ -1,65 +1,200 @@
-use axum::{
-    Json,
-    extract::{Path, State},
-    http::StatusCode,
-};
+use axum::{extract::State, http::StatusCode};
 use serde::{Deserialize, Serialize};
-use sqlx::PgPool;
+use sqlx::{
+    PgPool,
+    types::{Uuid, chrono::Utc},
+};
 
 use crate::{
-    controllers::middleware::RequireAuth,
-    entities::{Plant, Pot},
-    services,
+    controllers::{
+        error::ApiError,
+        extract::{Json, Path, Query},
+        middleware::{RequireAuth, RequireDevice},
+        plant::PlantSummaryResponse,
+    },
+    entities::{DeviceCredential, Plant, Pot, PotStatus},
+    services::{
+        self,
+        device::IssuedCredential,
+        heartbeat::{self, Heartbeat},
+        pot::{PotRegistration, PotUpdate},
+    },
 };
 
 #[derive(Serialize, Deserialize)]
 pub struct PotResponse {
     pot_id: i32,
-    #[serde(skip_serializing_if = "Option::is_none", rename = "plant")]
-    plant_name: Option<String>,
+    /// The plant currently in the pot.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    plant: Option<PlantSummaryResponse>,
+    #[serde(rename = "serialNumber")]
+    serial_number: Option<String>,
+    name: Option<String>,
+    model: Option<String>,
+    #[serde(rename = "firmwareVersion")]
+    firmware_version: Option<String>,
+    /// `active`, `inactive` or `maintenance`.
+    status: String,
+    #[serde(rename = "createdAt")]
+    created_at: String,
+    #[serde(rename = "updatedAt")]
+    updated_at: String,
+    /// Set once the pot is unregistered, its measurements stay readable.
+    #[serde(rename = "archivedAt")]
+    archived_at: Option<String>,
+    #[serde(rename = "lastSeenAt")]
+    last_seen_at: Option<String>,
+    #[serde(rename = "batteryLevel")]
+    battery_level: Option<i32>,
+    #[serde(rename = "reportIntervalSeconds")]
+    report_interval_seconds: i32,
+    /// `online`, `stale` (missed a report) or `offline`.
+    connectivity: String,
+    /// Set while the pot is reported offline.
+    #[serde(rename = "offlineSince")]
+    offline_since: Option<String>,
 }
 
 impl PotResponse {
     fn from(pot: Pot, plant: Option<Plant>) -> Self {
+        let connectivity = heartbeat::connectivity(&pot, Utc::now());
         PotResponse {
             pot_id: pot.id,
-            plant_name: plant.map(|p| p.name),
+            plant: plant.map(PlantSummaryResponse::from),
+            serial_number: pot.serial_number,
+            name: pot.name,
+            model: pot.model,
+            firmware_version: pot.firmware_version,
+            status: pot.status.as_str().to_string(),
+            created_at: pot.created_at.to_rfc3339(),
+            updated_at: pot.updated_at.to_rfc3339(),
+            archived_at: pot.archived_at.map(|at| at.to_rfc3339()),
+            last_seen_at: pot.last_seen_at.map(|at| at.to_rfc3339()),
+            battery_level: pot.battery_level,
+            report_interval_seconds: pot.report_interval_seconds,
+            connectivity: connectivity.as_str().to_string(),
+            offline_since: pot.offline_since.map(|at| at.to_rfc3339()),
+        }
+    }
+}
+
+/// The pot as shown next to the plant in it.
+#[derive(Serialize)]
+pub struct PotSummaryResponse {
+    id: i32,
+    name: Option<String>,
+    #[serde(rename = "serialNumber")]
+    serial_number: Option<String>,
+}
+
+impl From<Pot> for PotSummaryResponse {
+    fn from(pot: Pot) -> Self {
+        PotSummaryResponse {
+            id: pot.id,
+            name: pot.name,
+            serial_number: pot.serial_number,
         }
     }
 }
 
+#[derive(Deserialize, Default)]
+pub struct CreatePotPayload {
+    #[serde(rename = "serialNumber")]
+    serial_number: Option<String>,
+    name: Option<String>,
+    model: Option<String>,
+    #[serde(rename = "firmwareVersion")]
+    firmware_version: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct UpdatePotPayload {
+    name: Option<String>,
+    /// Defaults to `active`.
+    status: Option<String>,
+    #[serde(rename = "firmwareVersion")]
+    firmware_version: Option<String>,
+}
+
+impl UpdatePotPayload {
+    fn to_update(&self) -> Result<PotUpdate, ApiError> {
+        let status = match self.status.as_deref() {
+            Some(status) => status.parse().map_err(|_| {
+                ApiError::validation("status", "must be active, inactive or maintenance")
+            })?,
+            None => PotStatus::default(),
+        };
+
+        Ok(PotUpdate {
+            name: self.name.clone(),
+            status,
+            firmware_version: self.firmware_version.clone(),
+        })
+    }
+}
+
+#[derive(Deserialize)]
+pub struct PotQuery {
+    /// Lists the archived pots instead of the registered ones.
+    archived: Option<bool>,
+}
+
+#[derive(Deserialize)]
+pub struct DeletePotQuery {
+    /// Deletes the pot with its measurements even if it has any.
+    purge: Option<bool>,
+}
+
+#[derive(Serialize)]
+pub struct CreatePotResponse {
+    #[serde(flatten)]
+    pot: PotResponse,
+    #[serde(rename = "deviceCredential")]
+    device_credential: IssuedCredentialResponse,
+}
+
 pub async fn create_pot(
     State(pool): State<PgPool>,
     RequireAuth(claims): RequireAuth,
-) -> Result<Json<PotResponse>, StatusCode> {
-    let user = sqlx::query!(r#"SELECT id FROM "user" WHERE email = $1"#, claims.sub)
-        .fetch_one(&pool)
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    payload: Option<Json<CreatePotPayload>>,
+) -> Result<Json<CreatePotResponse>, ApiError> {
+    let user = services::user::get_user_by_email(&pool, claims.sub.as_str())
+        .await?
+        .ok_or_else(ApiError::unauthorized)?;
 
-    let pot = services::pot::create_pot(&pool, user.id)
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let payload = payload.map(|Json(payload)| payload).unwrap_or_default();
+    let registration = PotRegistration {
+        serial_number: payload.serial_number,
+        name: payload.name,
+        model: payload.model,
+        firmware_version: payload.firmware_version,
+    };
+    let (pot, credential) = services::pot::create_pot(&pool, user.id, &registration).await?;
 
-    Ok(Json(PotResponse::from(pot, None)))
+    Ok(Json(CreatePotResponse {
+        pot: PotResponse::from(pot, None),
+        device_credential: IssuedCredentialResponse::from(credential),
+    }))
 }
 
 pub async fn get_all_pots(
     State(pool): State<PgPool>,
     RequireAuth(claims): RequireAuth,
-) -> Result<Json<Vec<PotResponse>>, StatusCode> {
+    Query(query): Query<PotQuery>,
+) -> Result<Json<Vec<PotResponse>>, ApiError> {
     let user = services::user::get_user_by_email(&pool, claims.sub.as_str())
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
-        .ok_or(StatusCode::UNAUTHORIZED)?;
+        .await?
+        .ok_or_else(ApiError::unauthorized)?;
 
-    let pot = services::pot::get_all_pots(&pool, user.id)
-        .await
-        .map_err(|_| StatusCode::NOT_FOUND)?;
+    let pots = services::pot::get_all_pots(&pool, user.id, query.archived.unwrap_or(false)).await?;
+    let mut plants = services::link::get_current_plants(&pool, user.id).await?;
 
     Ok(Json(
-        pot.into_iter()
-            .map(|p| PotResponse::from(p, None))
+        pots.into_iter()
+            .map(|p| {
+                let plant = plants.remove(&p.id);
+                PotResponse::from(p, plant)
+            })
             .collect(),
     ))
 }
@@ -68,15 +203,184 @@ pub async fn get_pot(
     State(pool): State<PgPool>,
     RequireAuth(claims): RequireAuth,
     Path(pot_id): Path<i32>,
-) -> Result<Json<PotResponse>, StatusCode> {
+) -> Result<Json<PotResponse>, ApiError> {
+    let user = services::user::get_user_by_email(&pool, claims.sub.as_str())
+        .await?
+        .ok_or_else(ApiError::unauthorized)?;
+
+    let pot = services::pot::get_pot(&pool, user.id, pot_id).await?;
+    let plant = services::link::get_current_plant(&pool, pot.id).await?;
+
+    Ok(Json(PotResponse::from(pot, plant)))
+}
+
+pub async fn update_pot(
+    State(pool): State<PgPool>,
+    RequireAuth(claims): RequireAuth,
+    Path(pot_id): Path<i32>,
+    Json(payload): Json<UpdatePotPayload>,
+) -> Result<Json<PotResponse>, ApiError> {
+    let user = services::user::get_user_by_email(&pool, claims.sub.as_str())
+        .await?
+        .ok_or_else(ApiError::unauthorized)?;
+
+    let pot = services::pot::update_pot(&pool, user.id, pot_id, &payload.to_update()?).await?;
+    let plant = services::link::get_current_plant(&pool, pot.id).await?;
+
+    Ok(Json(PotResponse::from(pot, plant)))
+}
+
+#[derive(Deserialize)]
+pub struct ClaimPotPayload {
+    code: String,
+}
+
+/// Binds the pot showing the pairing code to the user.
+pub async fn claim_pot(
+    State(pool): State<PgPool>,
+    RequireAuth(claims): RequireAuth,
+    Json(payload): Json<ClaimPotPayload>,
+) -> Result<Json<PotResponse>, ApiError> {
+    let user = services::user::get_user_by_email(&pool, claims.sub.as_str())
+        .await?
+        .ok_or_else(ApiError::unauthorized)?;
+
+    let pot = services::pairing::claim_pot(&pool, user.id, &payload.code).await?;
+    let plant = services::link::get_current_plant(&pool, pot.id).await?;
+
+    Ok(Json(PotResponse::from(pot, plant)))
+}
+
+#[derive(Deserialize, Default)]
+pub struct HeartbeatPayload {
+    #[serde(rename = "batteryLevel")]
+    battery_level: Option<i32>,
+    #[serde(rename = "firmwareVersion")]
+    firmware_version: Option<String>,
+}
+
+/// Sent by the device between measurements to show it is alive.
+pub async fn record_heartbeat(
+    State(pool): State<PgPool>,
+    RequireDevice(pot_id): RequireDevice,
+    payload: Option<Json<HeartbeatPayload>>,
+) -> Result<StatusCode, ApiError> {
+    let payload = payload.map(|Json(payload)| payload).unwrap_or_default();
+    let heartbeat = Heartbeat {
+        battery_level: payload.battery_level,
+        firmware_version: payload.firmware_version,
+    };
+    services::heartbeat::record_heartbeat(&pool, pot_id, &heartbeat).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Unregisters the pot. Pots with measurements are archived unless
+/// `?purge=true` is given.
+pub async fn delete_pot(
+    State(pool): State<PgPool>,
+    RequireAuth(claims): RequireAuth,
+    Path(pot_id): Path<i32>,
+    Query(query): Query<DeletePotQuery>,
+) -> Result<StatusCode, ApiError> {
+    let user = services::user::get_user_by_email(&pool, claims.sub.as_str())
+        .await?
+        .ok_or_else(ApiError::unauthorized)?;
+
+    services::pot::delete_pot(&pool, user.id, pot_id, query.purge.unwrap_or(false)).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Serialize)]
+pub struct IssuedCredentialResponse {
+    id: Uuid,
+    /// Only returned once, when the credential is issued.
+    credential: String,
+}
+
+impl From<IssuedCredential> for IssuedCredentialResponse {
+    fn from(credential: IssuedCredential) -> Self {
+        IssuedCredentialResponse {
+            id: credential.id,
+            credential: credential.credential(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct DeviceCredentialResponse {
+    id: Uuid,
+    #[serde(rename = "createdAt")]
+    created_at: String,
+    #[serde(rename = "lastUsedAt")]
+    last_used_at: Option<String>,
+}
+
+impl From<DeviceCredential> for DeviceCredentialResponse {
+    fn from(credential: DeviceCredential) -> Self {
+        DeviceCredentialResponse {
+            id: credential.id,
+            created_at: credential.created_at.to_rfc3339(),
+            last_used_at: credential.last_used_at.map(|t| t.to_rfc3339()),
+        }
+    }
+}
+
+pub async fn get_credentials(
+    State(pool): State<PgPool>,
+    RequireAuth(claims): RequireAuth,
+    Path(pot_id): Path<i32>,
+) -> Result<Json<Vec<DeviceCredentialResponse>>, ApiError> {
+    let user = services::user::get_user_by_email(&pool, claims.sub.as_str())
+        .await?
+        .ok_or_else(ApiError::unauthorized)?;
+
+    let credentials = services::device::get_credentials(&pool, user.id, pot_id).await?;
+
+    Ok(Json(
+        credentials
+            .into_iter()
+            .map(DeviceCredentialResponse::from)
+            .collect(),
+    ))
+}
+
+#[derive(Deserialize, Default)]
+pub struct RotateCredentialPayload {
+    #[serde(rename = "revokeExisting", default)]
+    revoke_existing: bool,
+}
+
+pub async fn rotate_credential(
+    State(pool): State<PgPool>,
+    RequireAuth(claims): RequireAuth,
+    Path(pot_id): Path<i32>,
+    payload: Option<Json<RotateCredentialPayload>>,
+) -> Result<Json<IssuedCredentialResponse>, ApiError> {
+    let user = services::user::get_user_by_email(&pool, claims.sub.as_str())
+        .await?
+        .ok_or_else(ApiError::unauthorized)?;
+
+    let payload = payload.map(|Json(payload)| payload).unwrap_or_default();
+
+    let credential =
+        services::device::rotate_credential(&pool, user.id, pot_id, payload.revoke_existing)
+            .await?;
+
+    Ok(Json(IssuedCredentialResponse::from(credential)))
+}
+
+pub async fn revoke_credential(
+    State(pool): State<PgPool>,
+    RequireAuth(claims): RequireAuth,
+    Path((pot_id, credential_id)): Path<(i32, Uuid)>,
+) -> Result<StatusCode, ApiError> {
     let user = services::user::get_user_by_email(&pool, claims.sub.as_str())
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
-        .ok_or(StatusCode::UNAUTHORIZED)?;
+        .await?
+        .ok_or_else(ApiError::unauthorized)?;
 
-    let pot = services::pot::get_pot(&pool, user.id, pot_id)
-        .await
-        .map_err(|_| StatusCode::NOT_FOUND)?;
+    services::device::revoke_credential(&pool, user.id, pot_id, credential_id).await?;
 
-    Ok(Json(PotResponse::from(pot, None)))
+    Ok(StatusCode::NO_CONTENT)
 }
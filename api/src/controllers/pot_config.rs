@@ -0,0 +1,144 @@
+use axum::extract::State;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+
+use crate::{
+    controllers::{
+        error::ApiError,
+        extract::{Json, Path},
+        middleware::{RequireAuth, RequireUserOrDevice},
+    },
+    entities::PotConfig,
+    services::{self, pot_config::PotConfigUpdate},
+};
+
+#[derive(Serialize, Deserialize)]
+pub struct MoistureCalibration {
+    /// Raw sensor reading in dry soil.
+    dry: i32,
+    /// Raw sensor reading in water.
+    wet: i32,
+}
+
+#[derive(Serialize)]
+pub struct ConfigAuthorResponse {
+    id: i32,
+    email: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct PotConfigResponse {
+    #[serde(rename = "potId")]
+    pot_id: i32,
+    /// 0 while the pot runs with the defaults.
+    version: i32,
+    #[serde(rename = "samplingIntervalSeconds")]
+    sampling_interval_seconds: i32,
+    #[serde(rename = "uploadIntervalSeconds")]
+    upload_interval_seconds: i32,
+    #[serde(rename = "moistureCalibration")]
+    moisture_calibration: Option<MoistureCalibration>,
+    #[serde(rename = "changedBy")]
+    changed_by: Option<ConfigAuthorResponse>,
+    #[serde(rename = "changedAt")]
+    changed_at: Option<String>,
+}
+
+impl From<PotConfig> for PotConfigResponse {
+    fn from(config: PotConfig) -> Self {
+        PotConfigResponse {
+            pot_id: config.pot_id,
+            version: config.version,
+            sampling_interval_seconds: config.sampling_interval_seconds,
+            upload_interval_seconds: config.upload_interval_seconds,
+            moisture_calibration: config
+                .moisture_dry
+                .zip(config.moisture_wet)
+                .map(|(dry, wet)| MoistureCalibration { dry, wet }),
+            changed_by: config.changed_by.map(|id| ConfigAuthorResponse {
+                id,
+                email: config.changed_by_email,
+            }),
+            changed_at: config.changed_at.map(|at| at.to_rfc3339()),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct UpdatePotConfigPayload {
+    #[serde(rename = "samplingIntervalSeconds")]
+    sampling_interval_seconds: i32,
+    #[serde(rename = "uploadIntervalSeconds")]
+    upload_interval_seconds: i32,
+    #[serde(rename = "moistureCalibration")]
+    moisture_calibration: Option<MoistureCalibration>,
+    /// The version the change is based on, to detect concurrent changes.
+    version: Option<i32>,
+}
+
+impl UpdatePotConfigPayload {
+    fn to_update(&self) -> PotConfigUpdate {
+        PotConfigUpdate {
+            sampling_interval_seconds: self.sampling_interval_seconds,
+            upload_interval_seconds: self.upload_interval_seconds,
+            moisture_dry: self.moisture_calibration.as_ref().map(|c| c.dry),
+            moisture_wet: self.moisture_calibration.as_ref().map(|c| c.wet),
+            expected_version: self.version,
+        }
+    }
+}
+
+/// Read by the owner, and by the device once the version it got with its
+/// last measurement changed.
+pub async fn get_config(
+    State(pool): State<PgPool>,
+    access: RequireUserOrDevice,
+    Path(pot_id): Path<i32>,
+) -> Result<Json<PotConfigResponse>, ApiError> {
+    let pot_id = match access {
+        RequireUserOrDevice::User(claims) => {
+            let user = services::user::get_user_by_email(&pool, claims.sub.as_str())
+                .await?
+                .ok_or_else(ApiError::unauthorized)?;
+            services::pot::get_pot(&pool, user.id, pot_id).await?.id
+        }
+        // The credential was checked against the pot in the path.
+        RequireUserOrDevice::Device(pot_id) => pot_id,
+    };
+
+    let config = services::pot_config::get_config(&pool, pot_id).await?;
+
+    Ok(Json(PotConfigResponse::from(config)))
+}
+
+pub async fn update_config(
+    State(pool): State<PgPool>,
+    RequireAuth(claims): RequireAuth,
+    Path(pot_id): Path<i32>,
+    Json(payload): Json<UpdatePotConfigPayload>,
+) -> Result<Json<PotConfigResponse>, ApiError> {
+    let user = services::user::get_user_by_email(&pool, claims.sub.as_str())
+        .await?
+        .ok_or_else(ApiError::unauthorized)?;
+
+    let config =
+        services::pot_config::update_config(&pool, user.id, pot_id, &payload.to_update()).await?;
+
+    Ok(Json(PotConfigResponse::from(config)))
+}
+
+pub async fn get_config_history(
+    State(pool): State<PgPool>,
+    RequireAuth(claims): RequireAuth,
+    Path(pot_id): Path<i32>,
+) -> Result<Json<Vec<PotConfigResponse>>, ApiError> {
+    let user = services::user::get_user_by_email(&pool, claims.sub.as_str())
+        .await?
+        .ok_or_else(ApiError::unauthorized)?;
+
+    let history = services::pot_config::get_config_history(&pool, user.id, pot_id).await?;
+
+    Ok(Json(
+        history.into_iter().map(PotConfigResponse::from).collect(),
+    ))
+}
@@ -1,68 +1,213 @@
 use axum::{
     Router,
-    middleware::from_extractor,
-    routing::{delete, get, post, put},
+    extract::DefaultBodyLimit,
+    middleware::from_extractor_with_state,
+    routing::{delete, get, patch, post, put},
 };
-use sqlx::PgPool;
 
-use crate::controllers::{auth, link, measurement, middleware::RequireAuth, plant, pot};
+use crate::{
+    controllers::{
+        auth, automation, identify, link, live, measurement, middleware::RequireAuth, pairing,
+        photo, plant, plant_type, pot, pot_config, task, timelapse,
+    },
+    services::photo::MAX_PHOTO_BYTES,
+    state::AppState,
+};
 
-pub fn create_routes() -> Router<PgPool> {
+pub fn create_routes(state: AppState) -> Router<AppState> {
     Router::new().nest(
         "/api/v1",
         Router::new()
-            .merge(plant_routes())
-            .merge(pot_routes())
+            .merge(plant_routes(&state))
+            .merge(plant_type_routes(&state))
+            .merge(pot_routes(&state))
             .merge(auth_routes())
-            .merge(link_routes()),
+            .merge(link_routes(&state))
+            .merge(measurement_query_routes(&state))
+            .merge(task_routes(&state))
+            .merge(automation_routes(&state))
+            // WebSockets authenticate themselves, see `RequireSocketAuth`.
+            .route("/live", get(live::user_live)),
     )
 }
 
-fn auth_routes() -> Router<PgPool> {
+fn auth_routes() -> Router<AppState> {
     Router::new().nest(
         "/auth",
         Router::new()
             .route("/login", post(auth::login))
-            .route("/register", post(auth::register)),
+            .route("/register", post(auth::register))
+            .route("/refresh", post(auth::refresh))
+            .route("/logout", post(auth::logout))
+            .route("/sessions", get(auth::get_sessions))
+            .route("/sessions/{session_id}", delete(auth::revoke_session)),
     )
 }
 
-fn plant_routes() -> Router<PgPool> {
+fn plant_routes(state: &AppState) -> Router<AppState> {
     Router::new()
         .nest(
             "/plants",
             Router::new()
                 .route("/", get(plant::get_plants))
                 .route("/", post(plant::create_plant))
+                .route(
+                    "/identify",
+                    post(identify::identify_plant)
+                        .layer(DefaultBodyLimit::max(MAX_PHOTO_BYTES + 64 * 1024)),
+                )
                 .route("/{plant_id}", get(plant::get_plant))
                 .route("/{plant_id}", put(plant::update_plant))
-                .route("/{plant_id}", delete(plant::delete_plant)),
+                .route("/{plant_id}", patch(plant::patch_plant))
+                .route("/{plant_id}", delete(plant::delete_plant))
+                .route("/{plant_id}/pots/history", get(link::get_plant_history))
+                .route(
+                    "/{plant_id}/measurements",
+                    get(measurement::get_plant_measurements),
+                )
+                .route(
+                    "/{plant_id}/measurements/aggregate",
+                    get(measurement::get_plant_rollups),
+                )
+                .route(
+                    "/{plant_id}/measurements/downsample",
+                    get(measurement::get_plant_downsampled),
+                )
+                .route("/{plant_id}/photos", get(photo::get_photos))
+                .route(
+                    "/{plant_id}/photos",
+                    // Leaves room for the multipart framing around the file.
+                    post(photo::upload_photo)
+                        .layer(DefaultBodyLimit::max(MAX_PHOTO_BYTES + 64 * 1024)),
+                )
+                .route("/{plant_id}/photos/{photo_id}", get(photo::get_photo))
+                .route("/{plant_id}/photos/{photo_id}", delete(photo::delete_photo))
+                .route(
+                    "/{plant_id}/photos/{photo_id}/image",
+                    get(photo::get_photo_image),
+                )
+                .route(
+                    "/{plant_id}/photos/{photo_id}/thumbnail",
+                    get(photo::get_photo_thumbnail),
+                )
+                .route("/{plant_id}/timelapse", get(timelapse::get_timelapses))
+                .route("/{plant_id}/timelapse", post(timelapse::create_timelapse))
+                .route(
+                    "/{plant_id}/timelapse/{timelapse_id}",
+                    get(timelapse::get_timelapse),
+                )
+                .route(
+                    "/{plant_id}/timelapse/{timelapse_id}/download",
+                    get(timelapse::download_timelapse),
+                ),
+        )
+        .route_layer(from_extractor_with_state::<RequireAuth, _>(state.clone()))
+}
+
+fn plant_type_routes(state: &AppState) -> Router<AppState> {
+    Router::new()
+        .route("/plant-types", get(plant_type::search_plant_types))
+        .route(
+            "/plant-types/{plant_type_id}",
+            get(plant_type::get_plant_type),
         )
-        .route_layer(from_extractor::<RequireAuth>())
+        .route_layer(from_extractor_with_state::<RequireAuth, _>(state.clone()))
 }
 
-fn measurement_routes() -> Router<PgPool> {
+fn measurement_routes(state: &AppState) -> Router<AppState> {
     Router::new()
+        // Measurements are reported by the pot itself and authenticated with
+        // its device credential instead of a user token.
         .route("/", post(measurement::create_measurement))
-        .route("/", get(measurement::get_measurements))
+        .merge(
+            Router::new()
+                .route("/", get(measurement::get_pot_measurements))
+                .route("/aggregate", get(measurement::get_pot_rollups))
+                .route("/downsample", get(measurement::get_pot_downsampled))
+                .route("/quarantine", get(measurement::get_pot_quarantine))
+                .route_layer(from_extractor_with_state::<RequireAuth, _>(state.clone())),
+        )
 }
 
-fn pot_routes() -> Router<PgPool> {
+fn measurement_query_routes(state: &AppState) -> Router<AppState> {
+    Router::new()
+        .route("/measurements", get(measurement::get_measurements))
+        .route_layer(from_extractor_with_state::<RequireAuth, _>(state.clone()))
+}
+
+fn pot_routes(state: &AppState) -> Router<AppState> {
+    Router::new().nest(
+        "/pots",
+        Router::new()
+            .route("/", get(pot::get_all_pots))
+            .route("/", post(pot::create_pot))
+            .route("/claim", post(pot::claim_pot))
+            .route("/{pot_id}", get(pot::get_pot))
+            .route("/{pot_id}", put(pot::update_pot))
+            .route("/{pot_id}", delete(pot::delete_pot))
+            .route("/{pot_id}/credentials", get(pot::get_credentials))
+            .route("/{pot_id}/credentials", post(pot::rotate_credential))
+            .route(
+                "/{pot_id}/credentials/{credential_id}",
+                delete(pot::revoke_credential),
+            )
+            .route("/{pot_id}/link", get(link::get_current_link))
+            .route("/{pot_id}/plants/history", get(link::get_pot_history))
+            .route("/{pot_id}/config", put(pot_config::update_config))
+            .route(
+                "/{pot_id}/config/history",
+                get(pot_config::get_config_history),
+            )
+            .route_layer(from_extractor_with_state::<RequireAuth, _>(state.clone()))
+            // Unclaimed devices have no credential yet, see `services::pairing`.
+            .route("/pairing", post(pairing::start_pairing))
+            .route("/pairing/status", post(pairing::get_pairing_status))
+            .route("/{pot_id}/live", get(live::pot_live))
+            // Authenticated with the device credential, like measurements.
+            .route("/{pot_id}/heartbeat", post(pot::record_heartbeat))
+            // Read by the owner and by the device, see `RequireUserOrDevice`.
+            .route("/{pot_id}/config", get(pot_config::get_config))
+            .nest("/{pot_id}/measurements", measurement_routes(state)),
+    )
+}
+
+fn task_routes(state: &AppState) -> Router<AppState> {
     Router::new()
         .nest(
-            "/pots",
+            "/tasks",
             Router::new()
-                .route("/", get(pot::get_all_pots))
-                .route("/", post(pot::create_pot))
-                .route("/{pot_id}", get(pot::get_pot))
-                .nest("/{pot_id}/measurements", measurement_routes()),
+                .route("/", get(task::get_tasks))
+                .route("/", post(task::create_task))
+                .route("/suggestions", get(task::get_schedule_suggestions))
+                .route("/{task_id}", get(task::get_task))
+                .route("/{task_id}", put(task::update_task))
+                .route("/{task_id}", delete(task::delete_task))
+                .route("/{task_id}/complete", post(task::complete_task))
+                .route("/{task_id}/snooze", post(task::snooze_task))
+                .route("/{task_id}/completions", get(task::get_completions))
+                .route("/{task_id}/schedule", get(task::get_schedule)),
+        )
+        .route_layer(from_extractor_with_state::<RequireAuth, _>(state.clone()))
+}
+
+fn automation_routes(state: &AppState) -> Router<AppState> {
+    Router::new()
+        .route("/automations", get(automation::get_automations))
+        .route(
+            "/automations/{automation_id}/revert",
+            post(automation::revert_automation),
         )
-        .route_layer(from_extractor::<RequireAuth>())
+        .route_layer(from_extractor_with_state::<RequireAuth, _>(state.clone()))
 }
 
-fn link_routes() -> Router<PgPool> {
+fn link_routes(state: &AppState) -> Router<AppState> {
     Router::new()
         .route("/link", post(link::link_plant_to_pot))
         .route("/link", delete(link::unlink_plant_from_pot))
-        .route_layer(from_extractor::<RequireAuth>())
+        .route("/link/switch", post(link::switch_plant))
+        .route("/pot-links", post(link::create_link))
+        .route("/pot-links/{link_id}", get(link::get_link))
+        .route("/pot-links/{link_id}", put(link::update_link))
+        .route("/pot-links/{link_id}", delete(link::delete_link))
+        .route_layer(from_extractor_with_state::<RequireAuth, _>(state.clone()))
 }
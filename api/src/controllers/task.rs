@@ -0,0 +1,341 @@
+use axum::{extract::State, http::StatusCode};
+use serde::{Deserialize, Serialize};
+use sqlx::{
+    PgPool,
+    types::chrono::{DateTime, Utc},
+};
+
+use crate::{
+    controllers::{
+        error::ApiError,
+        extract::{Json, Path, Query},
+        middleware::RequireAuth,
+    },
+    entities::{Task, TaskCompletion, TaskType},
+    services::{
+        self,
+        schedule::{Schedule, Suggestion},
+        task::{TaskFilter, TaskInput},
+    },
+};
+
+#[derive(Serialize)]
+pub struct TaskResponse {
+    id: i32,
+    name: String,
+    #[serde(rename = "type")]
+    task_type: &'static str,
+    #[serde(rename = "plantId")]
+    plant_id: Option<i32>,
+    #[serde(rename = "dueAt")]
+    due_at: String,
+    #[serde(rename = "intervalDays")]
+    interval_days: Option<i32>,
+    done: bool,
+    #[serde(rename = "createdAt")]
+    created_at: String,
+    #[serde(rename = "updatedAt")]
+    updated_at: String,
+}
+
+impl From<Task> for TaskResponse {
+    fn from(task: Task) -> Self {
+        TaskResponse {
+            id: task.id,
+            name: task.name,
+            task_type: task.task_type.as_str(),
+            plant_id: task.plant_id,
+            due_at: task.due_at.to_rfc3339(),
+            interval_days: task.interval_days,
+            done: task.done,
+            created_at: task.created_at.to_rfc3339(),
+            updated_at: task.updated_at.to_rfc3339(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct TaskCompletionResponse {
+    id: i32,
+    #[serde(rename = "dueAt")]
+    due_at: String,
+    #[serde(rename = "completedAt")]
+    completed_at: String,
+}
+
+impl From<TaskCompletion> for TaskCompletionResponse {
+    fn from(completion: TaskCompletion) -> Self {
+        TaskCompletionResponse {
+            id: completion.id,
+            due_at: completion.due_at.to_rfc3339(),
+            completed_at: completion.completed_at.to_rfc3339(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct TaskPayload {
+    name: String,
+    #[serde(rename = "type")]
+    task_type: String,
+    #[serde(rename = "plantId")]
+    plant_id: Option<i32>,
+    #[serde(rename = "dueAt")]
+    due_at: String,
+    #[serde(rename = "intervalDays")]
+    interval_days: Option<i32>,
+    #[serde(default)]
+    done: bool,
+}
+
+impl TaskPayload {
+    fn to_input(&self) -> Result<TaskInput, ApiError> {
+        let task_type = self.task_type.parse::<TaskType>().map_err(|_| {
+            ApiError::validation(
+                "type",
+                "must be one of watering, fertilization, repotting, custom",
+            )
+        })?;
+
+        Ok(TaskInput {
+            name: self.name.clone(),
+            task_type,
+            plant_id: self.plant_id,
+            due_at: parse_date("dueAt", &self.due_at)?,
+            interval_days: self.interval_days,
+        })
+    }
+}
+
+fn parse_date(field: &str, value: &str) -> Result<DateTime<Utc>, ApiError> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|date| date.with_timezone(&Utc))
+        .map_err(|_| ApiError::validation(field, "must be an RFC 3339 date-time"))
+}
+
+#[derive(Deserialize)]
+pub struct TaskQuery {
+    #[serde(rename = "plantId")]
+    plant_id: Option<i32>,
+    done: Option<bool>,
+    #[serde(rename = "dueBefore")]
+    due_before: Option<String>,
+}
+
+pub async fn get_tasks(
+    State(pool): State<PgPool>,
+    RequireAuth(claims): RequireAuth,
+    Query(query): Query<TaskQuery>,
+) -> Result<Json<Vec<TaskResponse>>, ApiError> {
+    let user = services::user::get_user_by_email(&pool, claims.sub.as_str())
+        .await?
+        .ok_or_else(ApiError::unauthorized)?;
+
+    let filter = TaskFilter {
+        plant_id: query.plant_id,
+        done: query.done,
+        due_before: query
+            .due_before
+            .as_deref()
+            .map(|due_before| parse_date("dueBefore", due_before))
+            .transpose()?,
+    };
+    let tasks = services::task::get_tasks(&pool, user.id, &filter).await?;
+
+    Ok(Json(tasks.into_iter().map(TaskResponse::from).collect()))
+}
+
+pub async fn create_task(
+    State(pool): State<PgPool>,
+    RequireAuth(claims): RequireAuth,
+    Json(payload): Json<TaskPayload>,
+) -> Result<(StatusCode, Json<TaskResponse>), ApiError> {
+    let user = services::user::get_user_by_email(&pool, claims.sub.as_str())
+        .await?
+        .ok_or_else(ApiError::unauthorized)?;
+
+    let task = services::task::create_task(&pool, user.id, &payload.to_input()?).await?;
+
+    Ok((StatusCode::CREATED, Json(TaskResponse::from(task))))
+}
+
+pub async fn get_task(
+    State(pool): State<PgPool>,
+    RequireAuth(claims): RequireAuth,
+    Path(task_id): Path<i32>,
+) -> Result<Json<TaskResponse>, ApiError> {
+    let user = services::user::get_user_by_email(&pool, claims.sub.as_str())
+        .await?
+        .ok_or_else(ApiError::unauthorized)?;
+
+    let task = services::task::get_task(&pool, user.id, task_id).await?;
+
+    Ok(Json(TaskResponse::from(task)))
+}
+
+pub async fn update_task(
+    State(pool): State<PgPool>,
+    RequireAuth(claims): RequireAuth,
+    Path(task_id): Path<i32>,
+    Json(payload): Json<TaskPayload>,
+) -> Result<Json<TaskResponse>, ApiError> {
+    let user = services::user::get_user_by_email(&pool, claims.sub.as_str())
+        .await?
+        .ok_or_else(ApiError::unauthorized)?;
+
+    let task =
+        services::task::update_task(&pool, user.id, task_id, &payload.to_input()?, payload.done)
+            .await?;
+
+    Ok(Json(TaskResponse::from(task)))
+}
+
+pub async fn delete_task(
+    State(pool): State<PgPool>,
+    RequireAuth(claims): RequireAuth,
+    Path(task_id): Path<i32>,
+) -> Result<StatusCode, ApiError> {
+    let user = services::user::get_user_by_email(&pool, claims.sub.as_str())
+        .await?
+        .ok_or_else(ApiError::unauthorized)?;
+
+    services::task::delete_task(&pool, user.id, task_id).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Deserialize, Default)]
+pub struct CompleteTaskPayload {
+    #[serde(rename = "completedAt")]
+    completed_at: Option<String>,
+}
+
+pub async fn complete_task(
+    State(pool): State<PgPool>,
+    RequireAuth(claims): RequireAuth,
+    Path(task_id): Path<i32>,
+    payload: Option<Json<CompleteTaskPayload>>,
+) -> Result<Json<TaskResponse>, ApiError> {
+    let user = services::user::get_user_by_email(&pool, claims.sub.as_str())
+        .await?
+        .ok_or_else(ApiError::unauthorized)?;
+
+    let payload = payload.map(|Json(payload)| payload).unwrap_or_default();
+    let completed_at = match payload.completed_at {
+        Some(completed_at) => parse_date("completedAt", &completed_at)?,
+        None => Utc::now(),
+    };
+    let task = services::task::complete_task(&pool, user.id, task_id, completed_at).await?;
+
+    Ok(Json(TaskResponse::from(task)))
+}
+
+#[derive(Deserialize)]
+pub struct SnoozeTaskPayload {
+    #[serde(default = "default_snooze_days")]
+    days: i64,
+}
+
+impl Default for SnoozeTaskPayload {
+    fn default() -> Self {
+        SnoozeTaskPayload {
+            days: default_snooze_days(),
+        }
+    }
+}
+
+fn default_snooze_days() -> i64 {
+    1
+}
+
+pub async fn snooze_task(
+    State(pool): State<PgPool>,
+    RequireAuth(claims): RequireAuth,
+    Path(task_id): Path<i32>,
+    payload: Option<Json<SnoozeTaskPayload>>,
+) -> Result<Json<TaskResponse>, ApiError> {
+    let user = services::user::get_user_by_email(&pool, claims.sub.as_str())
+        .await?
+        .ok_or_else(ApiError::unauthorized)?;
+
+    let payload = payload.map(|Json(payload)| payload).unwrap_or_default();
+    let task = services::task::snooze_task(&pool, user.id, task_id, payload.days).await?;
+
+    Ok(Json(TaskResponse::from(task)))
+}
+
+pub async fn get_completions(
+    State(pool): State<PgPool>,
+    RequireAuth(claims): RequireAuth,
+    Path(task_id): Path<i32>,
+) -> Result<Json<Vec<TaskCompletionResponse>>, ApiError> {
+    let user = services::user::get_user_by_email(&pool, claims.sub.as_str())
+        .await?
+        .ok_or_else(ApiError::unauthorized)?;
+
+    let completions = services::task::get_completions(&pool, user.id, task_id).await?;
+
+    Ok(Json(
+        completions
+            .into_iter()
+            .map(TaskCompletionResponse::from)
+            .collect(),
+    ))
+}
+
+#[derive(Serialize)]
+pub struct ScheduleResponse {
+    #[serde(rename = "taskId")]
+    task_id: i32,
+    #[serde(rename = "currentIntervalDays")]
+    current_interval_days: i32,
+    #[serde(rename = "suggestedIntervalDays")]
+    suggested_interval_days: i32,
+    #[serde(rename = "nextDueAt")]
+    next_due_at: String,
+    explanation: String,
+}
+
+impl ScheduleResponse {
+    fn new(task_id: i32, schedule: Schedule) -> Self {
+        ScheduleResponse {
+            task_id,
+            current_interval_days: schedule.base_interval_days,
+            suggested_interval_days: schedule.interval_days,
+            next_due_at: schedule.next_due_at.to_rfc3339(),
+            explanation: schedule.explanation,
+        }
+    }
+}
+
+pub async fn get_schedule(
+    State(pool): State<PgPool>,
+    RequireAuth(claims): RequireAuth,
+    Path(task_id): Path<i32>,
+) -> Result<Json<ScheduleResponse>, ApiError> {
+    let user = services::user::get_user_by_email(&pool, claims.sub.as_str())
+        .await?
+        .ok_or_else(ApiError::unauthorized)?;
+
+    let schedule = services::schedule::get_schedule(&pool, user.id, task_id).await?;
+
+    Ok(Json(ScheduleResponse::new(task_id, schedule)))
+}
+
+pub async fn get_schedule_suggestions(
+    State(pool): State<PgPool>,
+    RequireAuth(claims): RequireAuth,
+) -> Result<Json<Vec<ScheduleResponse>>, ApiError> {
+    let user = services::user::get_user_by_email(&pool, claims.sub.as_str())
+        .await?
+        .ok_or_else(ApiError::unauthorized)?;
+
+    let suggestions = services::schedule::get_suggestions(&pool, user.id).await?;
+
+    Ok(Json(
+        suggestions
+            .into_iter()
+            .map(|Suggestion { task, schedule }| ScheduleResponse::new(task.id, schedule))
+            .collect(),
+    ))
+}
@@ -0,0 +1,179 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::State,
+    http::{StatusCode, header},
+    response::IntoResponse,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::{
+    PgPool,
+    types::chrono::{DateTime, Utc},
+};
+
+use crate::{
+    controllers::{
+        error::ApiError,
+        extract::{Json, Path},
+        middleware::RequireAuth,
+    },
+    entities::{Timelapse, TimelapseStatus},
+    services::{self, storage::Storage, timelapse::TimelapseRequest},
+};
+
+#[derive(Serialize)]
+pub struct TimelapseResponse {
+    id: i32,
+    #[serde(rename = "plantId")]
+    plant_id: i32,
+    from: String,
+    to: String,
+    #[serde(rename = "frameDelayMs")]
+    frame_delay_ms: i32,
+    /// `pending`, `running`, `completed` or `failed`.
+    status: &'static str,
+    #[serde(rename = "frameCount")]
+    frame_count: Option<i32>,
+    error: Option<String>,
+    /// Present once the time-lapse is completed.
+    #[serde(rename = "downloadUrl")]
+    download_url: Option<String>,
+    #[serde(rename = "createdAt")]
+    created_at: String,
+    #[serde(rename = "startedAt")]
+    started_at: Option<String>,
+    #[serde(rename = "finishedAt")]
+    finished_at: Option<String>,
+}
+
+impl From<Timelapse> for TimelapseResponse {
+    fn from(timelapse: Timelapse) -> Self {
+        let download_url = (timelapse.status == TimelapseStatus::Completed).then(|| {
+            format!(
+                "/api/v1/plants/{}/timelapse/{}/download",
+                timelapse.plant_id, timelapse.id
+            )
+        });
+
+        TimelapseResponse {
+            id: timelapse.id,
+            plant_id: timelapse.plant_id,
+            from: timelapse.range_start.to_rfc3339(),
+            to: timelapse.range_end.to_rfc3339(),
+            frame_delay_ms: timelapse.frame_delay_ms,
+            status: timelapse.status.as_str(),
+            frame_count: timelapse.frame_count,
+            error: timelapse.error,
+            download_url,
+            created_at: timelapse.created_at.to_rfc3339(),
+            started_at: timelapse.started_at.map(|at| at.to_rfc3339()),
+            finished_at: timelapse.finished_at.map(|at| at.to_rfc3339()),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct TimelapsePayload {
+    from: Option<String>,
+    to: Option<String>,
+    #[serde(rename = "frameDelayMs")]
+    frame_delay_ms: Option<i32>,
+}
+
+impl TimelapsePayload {
+    fn to_request(&self) -> Result<TimelapseRequest, ApiError> {
+        Ok(TimelapseRequest {
+            start: self
+                .from
+                .as_deref()
+                .map(|v| parse_date("from", v))
+                .transpose()?,
+            end: self
+                .to
+                .as_deref()
+                .map(|v| parse_date("to", v))
+                .transpose()?,
+            frame_delay_ms: self.frame_delay_ms,
+        })
+    }
+}
+
+fn parse_date(field: &str, value: &str) -> Result<DateTime<Utc>, ApiError> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|date| date.with_timezone(&Utc))
+        .map_err(|_| ApiError::validation(field, "must be an RFC 3339 date-time"))
+}
+
+pub async fn create_timelapse(
+    State(pool): State<PgPool>,
+    RequireAuth(claims): RequireAuth,
+    Path(plant_id): Path<i32>,
+    payload: Option<Json<TimelapsePayload>>,
+) -> Result<(StatusCode, Json<TimelapseResponse>), ApiError> {
+    let user = services::user::get_user_by_email(&pool, claims.sub.as_str())
+        .await?
+        .ok_or_else(ApiError::unauthorized)?;
+
+    let request = match payload {
+        Some(Json(payload)) => payload.to_request()?,
+        None => TimelapseRequest::default(),
+    };
+    let timelapse =
+        services::timelapse::enqueue_timelapse(&pool, user.id, plant_id, &request).await?;
+
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(TimelapseResponse::from(timelapse)),
+    ))
+}
+
+pub async fn get_timelapses(
+    State(pool): State<PgPool>,
+    RequireAuth(claims): RequireAuth,
+    Path(plant_id): Path<i32>,
+) -> Result<Json<Vec<TimelapseResponse>>, ApiError> {
+    let user = services::user::get_user_by_email(&pool, claims.sub.as_str())
+        .await?
+        .ok_or_else(ApiError::unauthorized)?;
+
+    let timelapses = services::timelapse::get_timelapses(&pool, user.id, plant_id).await?;
+
+    Ok(Json(
+        timelapses
+            .into_iter()
+            .map(TimelapseResponse::from)
+            .collect(),
+    ))
+}
+
+pub async fn get_timelapse(
+    State(pool): State<PgPool>,
+    RequireAuth(claims): RequireAuth,
+    Path((plant_id, timelapse_id)): Path<(i32, i32)>,
+) -> Result<Json<TimelapseResponse>, ApiError> {
+    let user = services::user::get_user_by_email(&pool, claims.sub.as_str())
+        .await?
+        .ok_or_else(ApiError::unauthorized)?;
+
+    let timelapse =
+        services::timelapse::get_timelapse(&pool, user.id, plant_id, timelapse_id).await?;
+
+    Ok(Json(TimelapseResponse::from(timelapse)))
+}
+
+pub async fn download_timelapse(
+    State(pool): State<PgPool>,
+    State(storage): State<Arc<dyn Storage>>,
+    RequireAuth(claims): RequireAuth,
+    Path((plant_id, timelapse_id)): Path<(i32, i32)>,
+) -> Result<impl IntoResponse, ApiError> {
+    let user = services::user::get_user_by_email(&pool, claims.sub.as_str())
+        .await?
+        .ok_or_else(ApiError::unauthorized)?;
+
+    let timelapse =
+        services::timelapse::get_timelapse(&pool, user.id, plant_id, timelapse_id).await?;
+    let data = services::timelapse::read_timelapse(storage.as_ref(), &timelapse).await?;
+
+    Ok(([(header::CONTENT_TYPE, "image/gif")], data))
+}
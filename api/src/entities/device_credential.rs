@@ -0,0 +1,32 @@
+use sqlx::types::{
+    Uuid,
+    chrono::{DateTime, Utc},
+};
+
+pub struct DeviceCredential {
+    pub id: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+pub struct DeviceCredentialDb {
+    pub id: Uuid,
+    #[allow(dead_code)]
+    pub pot_id: i32,
+    #[allow(dead_code)]
+    pub secret_hash: String,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    #[allow(dead_code)]
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+impl From<DeviceCredentialDb> for DeviceCredential {
+    fn from(db: DeviceCredentialDb) -> Self {
+        DeviceCredential {
+            id: db.id,
+            created_at: db.created_at,
+            last_used_at: db.last_used_at,
+        }
+    }
+}
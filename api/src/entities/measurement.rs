@@ -1,7 +1,10 @@
 use sqlx::types::chrono::NaiveDateTime;
 
 pub struct Measurement {
+    pub id: i32,
     pub pot_id: i32,
+    /// The plant the pot is linked to, if any.
+    pub plant_id: Option<i32>,
     pub timestamp: NaiveDateTime,
     pub soil_moisture: f32,
     pub temperature: f32,
@@ -13,6 +16,7 @@ pub struct Measurement {
 pub struct MeasurementDb {
     pub id: i32,
     pub pot_id: i32,
+    pub plant_id: Option<i32>,
     pub timestamp: NaiveDateTime,
     pub soil_moisture: f32,
     pub temperature: f32,
@@ -24,7 +28,9 @@ pub struct MeasurementDb {
 impl From<MeasurementDb> for Measurement {
     fn from(db: MeasurementDb) -> Self {
         Measurement {
+            id: db.id,
             pot_id: db.pot_id,
+            plant_id: db.plant_id,
             timestamp: db.timestamp,
             soil_moisture: db.soil_moisture,
             temperature: db.temperature,
@@ -0,0 +1,44 @@
+use sqlx::types::chrono::{DateTime, NaiveDateTime, Utc};
+
+use crate::entities::Measurement;
+
+/// A stored measurement that failed the plausibility checks.
+pub struct QuarantinedMeasurement {
+    pub measurement: Measurement,
+    pub reasons: Vec<String>,
+    pub quarantined_at: DateTime<Utc>,
+}
+
+pub struct QuarantinedMeasurementDb {
+    pub id: i32,
+    pub pot_id: i32,
+    pub plant_id: Option<i32>,
+    pub timestamp: NaiveDateTime,
+    pub soil_moisture: f32,
+    pub temperature: f32,
+    pub light_level: f32,
+    pub humidity: f32,
+    pub battery_level: i32,
+    pub reasons: Vec<String>,
+    pub quarantined_at: DateTime<Utc>,
+}
+
+impl From<QuarantinedMeasurementDb> for QuarantinedMeasurement {
+    fn from(db: QuarantinedMeasurementDb) -> Self {
+        QuarantinedMeasurement {
+            measurement: Measurement {
+                id: db.id,
+                pot_id: db.pot_id,
+                plant_id: db.plant_id,
+                timestamp: db.timestamp,
+                soil_moisture: db.soil_moisture,
+                temperature: db.temperature,
+                light_level: db.light_level,
+                humidity: db.humidity,
+                battery_level: db.battery_level,
+            },
+            reasons: db.reasons,
+            quarantined_at: db.quarantined_at,
+        }
+    }
+}
@@ -0,0 +1,63 @@
+use sqlx::types::chrono::NaiveDateTime;
+
+/// Minimum, maximum and mean of one metric over a bucket.
+pub struct MetricSummary {
+    pub min: f32,
+    pub max: f32,
+    pub avg: f32,
+}
+
+pub struct MeasurementRollup {
+    pub bucket_start: NaiveDateTime,
+    pub count: i64,
+    pub soil_moisture: MetricSummary,
+    pub temperature: MetricSummary,
+    pub light_level: MetricSummary,
+    pub humidity: MetricSummary,
+}
+
+pub struct MeasurementRollupDb {
+    pub bucket_start: NaiveDateTime,
+    pub count: i64,
+    pub soil_moisture_min: f32,
+    pub soil_moisture_max: f32,
+    pub soil_moisture_avg: f32,
+    pub temperature_min: f32,
+    pub temperature_max: f32,
+    pub temperature_avg: f32,
+    pub light_level_min: f32,
+    pub light_level_max: f32,
+    pub light_level_avg: f32,
+    pub humidity_min: f32,
+    pub humidity_max: f32,
+    pub humidity_avg: f32,
+}
+
+impl From<MeasurementRollupDb> for MeasurementRollup {
+    fn from(db: MeasurementRollupDb) -> Self {
+        MeasurementRollup {
+            bucket_start: db.bucket_start,
+            count: db.count,
+            soil_moisture: MetricSummary {
+                min: db.soil_moisture_min,
+                max: db.soil_moisture_max,
+                avg: db.soil_moisture_avg,
+            },
+            temperature: MetricSummary {
+                min: db.temperature_min,
+                max: db.temperature_max,
+                avg: db.temperature_avg,
+            },
+            light_level: MetricSummary {
+                min: db.light_level_min,
+                max: db.light_level_max,
+                avg: db.light_level_avg,
+            },
+            humidity: MetricSummary {
+                min: db.humidity_min,
+                max: db.humidity_max,
+                avg: db.humidity_avg,
+            },
+        }
+    }
+}
@@ -1,17 +1,58 @@
+mod device_credential;
 mod measurement;
+mod measurement_quarantine;
+mod measurement_rollup;
 mod plant;
+mod plant_photo;
+mod plant_pot_assignment;
 mod plant_type;
 mod pot;
+mod pot_config;
+mod session;
 mod task;
+mod task_automation;
+mod task_completion;
+mod task_snooze;
+mod timelapse;
 mod user;
 
+pub use device_credential::DeviceCredential;
+pub use device_credential::DeviceCredentialDb;
 pub use measurement::Measurement;
 pub use measurement::MeasurementDb;
+pub use measurement_quarantine::QuarantinedMeasurement;
+pub use measurement_quarantine::QuarantinedMeasurementDb;
+pub use measurement_rollup::MeasurementRollup;
+pub use measurement_rollup::MeasurementRollupDb;
+pub use measurement_rollup::MetricSummary;
 pub use plant::Plant;
 pub use plant::PlantDb;
+pub use plant_photo::CaptureSource;
+pub use plant_photo::PlantPhoto;
+pub use plant_photo::PlantPhotoDb;
+pub use plant_pot_assignment::PlantPotAssignment;
+pub use plant_pot_assignment::PlantPotAssignmentDb;
+pub use plant_type::PlantType;
+pub use plant_type::PlantTypeDb;
 pub use pot::Pot;
 pub use pot::PotDb;
+pub use pot::PotStatus;
+pub use pot_config::PotConfig;
+pub use pot_config::PotConfigDb;
+pub use session::Session;
+pub use session::SessionDb;
 pub use task::Task;
 pub use task::TaskDb;
+pub use task::TaskType;
+pub use task_automation::AutomationAction;
+pub use task_automation::TaskAutomation;
+pub use task_automation::TaskAutomationDb;
+pub use task_completion::TaskCompletion;
+pub use task_completion::TaskCompletionDb;
+pub use task_snooze::TaskSnooze;
+pub use task_snooze::TaskSnoozeDb;
+pub use timelapse::Timelapse;
+pub use timelapse::TimelapseDb;
+pub use timelapse::TimelapseStatus;
 pub use user::User;
 pub use user::UserDb;
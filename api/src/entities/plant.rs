@@ -1,13 +1,30 @@
+use sqlx::types::chrono::NaiveDate;
+
 pub struct Plant {
     pub id: i32,
     pub name: String,
+    #[allow(dead_code)]
     pub owner_id: i32,
+    pub plant_type_id: Option<i32>,
+    pub species: Option<String>,
+    pub location: Option<String>,
+    pub notes: Option<String>,
+    pub image_url: Option<String>,
+    pub acquired_on: Option<NaiveDate>,
+    pub archived: bool,
 }
 
 pub struct PlantDb {
     pub id: i32,
     pub name: String,
     pub owner_id: i32,
+    pub plant_type_id: Option<i32>,
+    pub species: Option<String>,
+    pub location: Option<String>,
+    pub notes: Option<String>,
+    pub image_url: Option<String>,
+    pub acquired_on: Option<NaiveDate>,
+    pub archived: bool,
 }
 
 impl From<PlantDb> for Plant {
@@ -16,6 +33,13 @@ impl From<PlantDb> for Plant {
             id: db.id,
             name: db.name,
             owner_id: db.owner_id,
+            plant_type_id: db.plant_type_id,
+            species: db.species,
+            location: db.location,
+            notes: db.notes,
+            image_url: db.image_url,
+            acquired_on: db.acquired_on,
+            archived: db.archived,
         }
     }
 }
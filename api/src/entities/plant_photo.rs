@@ -0,0 +1,81 @@
+use std::str::FromStr;
+
+use sqlx::types::chrono::{DateTime, Utc};
+
+/// Where the capture time of a photo comes from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CaptureSource {
+    /// Given by the client on upload.
+    Explicit,
+    /// Read from the EXIF `DateTimeOriginal` tag.
+    Exif,
+    /// Neither was available, the upload time is used.
+    Upload,
+}
+
+impl CaptureSource {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CaptureSource::Explicit => "explicit",
+            CaptureSource::Exif => "exif",
+            CaptureSource::Upload => "upload",
+        }
+    }
+}
+
+impl FromStr for CaptureSource {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "explicit" => Ok(CaptureSource::Explicit),
+            "exif" => Ok(CaptureSource::Exif),
+            "upload" => Ok(CaptureSource::Upload),
+            _ => Err(()),
+        }
+    }
+}
+
+pub struct PlantPhoto {
+    pub id: i32,
+    pub plant_id: i32,
+    pub image_key: String,
+    pub thumbnail_key: String,
+    pub content_type: String,
+    pub width: i32,
+    pub height: i32,
+    pub captured_at: DateTime<Utc>,
+    pub capture_source: CaptureSource,
+    pub created_at: DateTime<Utc>,
+}
+
+pub struct PlantPhotoDb {
+    pub id: i32,
+    pub plant_id: i32,
+    pub image_key: String,
+    pub thumbnail_key: String,
+    pub content_type: String,
+    pub width: i32,
+    pub height: i32,
+    pub captured_at: DateTime<Utc>,
+    pub capture_source: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<PlantPhotoDb> for PlantPhoto {
+    fn from(db: PlantPhotoDb) -> Self {
+        PlantPhoto {
+            id: db.id,
+            plant_id: db.plant_id,
+            image_key: db.image_key,
+            thumbnail_key: db.thumbnail_key,
+            content_type: db.content_type,
+            width: db.width,
+            height: db.height,
+            captured_at: db.captured_at,
+            // The column is constrained to the known sources.
+            capture_source: db.capture_source.parse().unwrap_or(CaptureSource::Upload),
+            created_at: db.created_at,
+        }
+    }
+}
@@ -0,0 +1,34 @@
+use sqlx::types::chrono::{DateTime, Utc};
+
+/// A period in which a plant sat in a pot.
+pub struct PlantPotAssignment {
+    pub id: i32,
+    pub plant_id: i32,
+    pub pot_id: i32,
+    pub linked_at: DateTime<Utc>,
+    /// Unset while the plant is still in the pot.
+    pub unlinked_at: Option<DateTime<Utc>>,
+    pub notes: Option<String>,
+}
+
+pub struct PlantPotAssignmentDb {
+    pub id: i32,
+    pub plant_id: i32,
+    pub pot_id: i32,
+    pub linked_at: DateTime<Utc>,
+    pub unlinked_at: Option<DateTime<Utc>>,
+    pub notes: Option<String>,
+}
+
+impl From<PlantPotAssignmentDb> for PlantPotAssignment {
+    fn from(db: PlantPotAssignmentDb) -> Self {
+        PlantPotAssignment {
+            id: db.id,
+            plant_id: db.plant_id,
+            pot_id: db.pot_id,
+            linked_at: db.linked_at,
+            unlinked_at: db.unlinked_at,
+            notes: db.notes,
+        }
+    }
+}
@@ -1 +1,40 @@
+#[derive(Clone, Debug)]
+pub struct PlantType {
+    pub id: i32,
+    pub common_name: String,
+    pub scientific_name: String,
+    pub watering_interval_days: i32,
+    pub fertilization_interval_days: i32,
+    pub moisture_range: (f32, f32),
+    pub light_range: (f32, f32),
+    pub temperature_range: (f32, f32),
+}
 
+pub struct PlantTypeDb {
+    pub id: i32,
+    pub common_name: String,
+    pub scientific_name: String,
+    pub watering_interval_days: i32,
+    pub fertilization_interval_days: i32,
+    pub moisture_min: f32,
+    pub moisture_max: f32,
+    pub light_min: f32,
+    pub light_max: f32,
+    pub temperature_min: f32,
+    pub temperature_max: f32,
+}
+
+impl From<PlantTypeDb> for PlantType {
+    fn from(db: PlantTypeDb) -> Self {
+        PlantType {
+            id: db.id,
+            common_name: db.common_name,
+            scientific_name: db.scientific_name,
+            watering_interval_days: db.watering_interval_days,
+            fertilization_interval_days: db.fertilization_interval_days,
+            moisture_range: (db.moisture_min, db.moisture_max),
+            light_range: (db.light_min, db.light_max),
+            temperature_range: (db.temperature_min, db.temperature_max),
+        }
+    }
+}
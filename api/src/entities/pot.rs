@@ -1,14 +1,93 @@
+use std::str::FromStr;
+
+use sqlx::types::chrono::{DateTime, Utc};
+
+/// Operational state of a pot, set by its owner.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PotStatus {
+    #[default]
+    Active,
+    Inactive,
+    Maintenance,
+}
+
+impl PotStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PotStatus::Active => "active",
+            PotStatus::Inactive => "inactive",
+            PotStatus::Maintenance => "maintenance",
+        }
+    }
+}
+
+impl FromStr for PotStatus {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "active" => Ok(PotStatus::Active),
+            "inactive" => Ok(PotStatus::Inactive),
+            "maintenance" => Ok(PotStatus::Maintenance),
+            _ => Err(()),
+        }
+    }
+}
+
 pub struct Pot {
     pub id: i32,
+    pub serial_number: Option<String>,
+    pub name: Option<String>,
+    pub model: Option<String>,
+    pub firmware_version: Option<String>,
+    pub status: PotStatus,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    /// Set once the pot is unregistered while its measurements are kept.
+    pub archived_at: Option<DateTime<Utc>>,
+    pub last_seen_at: Option<DateTime<Utc>>,
+    pub battery_level: Option<i32>,
+    /// How often the device is expected to report.
+    pub report_interval_seconds: i32,
+    /// Set while the pot is reported offline.
+    pub offline_since: Option<DateTime<Utc>>,
 }
 
 pub struct PotDb {
     pub id: i32,
+    #[allow(dead_code)]
     pub owner_id: i32,
+    pub serial_number: Option<String>,
+    pub name: Option<String>,
+    pub model: Option<String>,
+    pub firmware_version: Option<String>,
+    pub status: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub archived_at: Option<DateTime<Utc>>,
+    pub last_seen_at: Option<DateTime<Utc>>,
+    pub battery_level: Option<i32>,
+    pub report_interval_seconds: i32,
+    pub offline_since: Option<DateTime<Utc>>,
 }
 
 impl From<PotDb> for Pot {
     fn from(db: PotDb) -> Self {
-        Pot { id: db.id }
+        Pot {
+            id: db.id,
+            serial_number: db.serial_number,
+            name: db.name,
+            model: db.model,
+            firmware_version: db.firmware_version,
+            // The column is constrained to the known statuses.
+            status: db.status.parse().unwrap_or_default(),
+            created_at: db.created_at,
+            updated_at: db.updated_at,
+            archived_at: db.archived_at,
+            last_seen_at: db.last_seen_at,
+            battery_level: db.battery_level,
+            report_interval_seconds: db.report_interval_seconds,
+            offline_since: db.offline_since,
+        }
     }
 }
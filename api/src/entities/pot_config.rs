@@ -0,0 +1,46 @@
+use sqlx::types::chrono::{DateTime, Utc};
+
+/// One version of the settings a pot's device runs with.
+pub struct PotConfig {
+    pub pot_id: i32,
+    /// Starts at 1 with the first change, the defaults are version 0.
+    pub version: i32,
+    pub sampling_interval_seconds: i32,
+    pub upload_interval_seconds: i32,
+    /// Raw soil moisture sensor readings in dry soil and in water, used by
+    /// the device to turn readings into a percentage.
+    pub moisture_dry: Option<i32>,
+    pub moisture_wet: Option<i32>,
+    pub changed_by: Option<i32>,
+    pub changed_by_email: Option<String>,
+    /// Unset for the defaults.
+    pub changed_at: Option<DateTime<Utc>>,
+}
+
+pub struct PotConfigDb {
+    pub pot_id: i32,
+    pub version: i32,
+    pub sampling_interval_seconds: i32,
+    pub upload_interval_seconds: i32,
+    pub moisture_dry: Option<i32>,
+    pub moisture_wet: Option<i32>,
+    pub changed_by: Option<i32>,
+    pub changed_by_email: Option<String>,
+    pub changed_at: DateTime<Utc>,
+}
+
+impl From<PotConfigDb> for PotConfig {
+    fn from(db: PotConfigDb) -> Self {
+        PotConfig {
+            pot_id: db.pot_id,
+            version: db.version,
+            sampling_interval_seconds: db.sampling_interval_seconds,
+            upload_interval_seconds: db.upload_interval_seconds,
+            moisture_dry: db.moisture_dry,
+            moisture_wet: db.moisture_wet,
+            changed_by: db.changed_by,
+            changed_by_email: db.changed_by_email,
+            changed_at: Some(db.changed_at),
+        }
+    }
+}
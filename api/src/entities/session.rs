@@ -0,0 +1,33 @@
+use sqlx::types::{
+    Uuid,
+    chrono::{DateTime, Utc},
+};
+
+pub struct Session {
+    pub id: Uuid,
+    pub device_name: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: DateTime<Utc>,
+}
+
+pub struct SessionDb {
+    pub id: Uuid,
+    #[allow(dead_code)]
+    pub user_id: i32,
+    pub device_name: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: DateTime<Utc>,
+    #[allow(dead_code)]
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+impl From<SessionDb> for Session {
+    fn from(db: SessionDb) -> Self {
+        Session {
+            id: db.id,
+            device_name: db.device_name,
+            created_at: db.created_at,
+            last_used_at: db.last_used_at,
+        }
+    }
+}
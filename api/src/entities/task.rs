@@ -1,11 +1,70 @@
+use std::{fmt::Display, str::FromStr};
+
+use sqlx::types::chrono::{DateTime, Utc};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TaskType {
+    Watering,
+    Fertilization,
+    Repotting,
+    Custom,
+}
+
+impl TaskType {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TaskType::Watering => "watering",
+            TaskType::Fertilization => "fertilization",
+            TaskType::Repotting => "repotting",
+            TaskType::Custom => "custom",
+        }
+    }
+}
+
+impl Display for TaskType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for TaskType {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "watering" => Ok(TaskType::Watering),
+            "fertilization" => Ok(TaskType::Fertilization),
+            "repotting" => Ok(TaskType::Repotting),
+            "custom" => Ok(TaskType::Custom),
+            _ => Err(()),
+        }
+    }
+}
+
 pub struct Task {
     pub id: i32,
     pub name: String,
+    pub plant_id: Option<i32>,
+    pub task_type: TaskType,
+    pub due_at: DateTime<Utc>,
+    pub interval_days: Option<i32>,
+    pub done: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
 }
 
 pub struct TaskDb {
     pub id: i32,
     pub name: String,
+    #[allow(dead_code)]
+    pub owner_id: i32,
+    pub plant_id: Option<i32>,
+    pub task_type: String,
+    pub due_at: DateTime<Utc>,
+    pub interval_days: Option<i32>,
+    pub done: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
 }
 
 impl From<TaskDb> for Task {
@@ -13,6 +72,14 @@ impl From<TaskDb> for Task {
         Task {
             id: db.id,
             name: db.name,
+            plant_id: db.plant_id,
+            // The column is constrained to the known types.
+            task_type: db.task_type.parse().unwrap_or(TaskType::Custom),
+            due_at: db.due_at,
+            interval_days: db.interval_days,
+            done: db.done,
+            created_at: db.created_at,
+            updated_at: db.updated_at,
         }
     }
 }
@@ -0,0 +1,76 @@
+use std::str::FromStr;
+
+use sqlx::types::chrono::{DateTime, Utc};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AutomationAction {
+    /// A watering was detected and the task completed.
+    Completed,
+    /// The soil dried out and the task was brought forward.
+    DueEarly,
+}
+
+impl AutomationAction {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AutomationAction::Completed => "completed",
+            AutomationAction::DueEarly => "due_early",
+        }
+    }
+}
+
+impl FromStr for AutomationAction {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "completed" => Ok(AutomationAction::Completed),
+            "due_early" => Ok(AutomationAction::DueEarly),
+            _ => Err(()),
+        }
+    }
+}
+
+pub struct TaskAutomation {
+    pub id: i32,
+    pub task_id: i32,
+    pub action: AutomationAction,
+    pub measurement_ids: Vec<i32>,
+    pub reason: String,
+    pub previous_due_at: DateTime<Utc>,
+    pub previous_done: bool,
+    pub completion_id: Option<i32>,
+    pub created_at: DateTime<Utc>,
+    pub reverted_at: Option<DateTime<Utc>>,
+}
+
+pub struct TaskAutomationDb {
+    pub id: i32,
+    pub task_id: i32,
+    pub action: String,
+    pub measurement_ids: Vec<i32>,
+    pub reason: String,
+    pub previous_due_at: DateTime<Utc>,
+    pub previous_done: bool,
+    pub completion_id: Option<i32>,
+    pub created_at: DateTime<Utc>,
+    pub reverted_at: Option<DateTime<Utc>>,
+}
+
+impl From<TaskAutomationDb> for TaskAutomation {
+    fn from(db: TaskAutomationDb) -> Self {
+        TaskAutomation {
+            id: db.id,
+            task_id: db.task_id,
+            // The column is constrained to the known actions.
+            action: db.action.parse().unwrap_or(AutomationAction::Completed),
+            measurement_ids: db.measurement_ids,
+            reason: db.reason,
+            previous_due_at: db.previous_due_at,
+            previous_done: db.previous_done,
+            completion_id: db.completion_id,
+            created_at: db.created_at,
+            reverted_at: db.reverted_at,
+        }
+    }
+}
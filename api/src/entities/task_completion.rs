@@ -0,0 +1,24 @@
+use sqlx::types::chrono::{DateTime, Utc};
+
+pub struct TaskCompletion {
+    pub id: i32,
+    pub due_at: DateTime<Utc>,
+    pub completed_at: DateTime<Utc>,
+}
+
+pub struct TaskCompletionDb {
+    pub id: i32,
+    pub task_id: i32,
+    pub due_at: DateTime<Utc>,
+    pub completed_at: DateTime<Utc>,
+}
+
+impl From<TaskCompletionDb> for TaskCompletion {
+    fn from(db: TaskCompletionDb) -> Self {
+        TaskCompletion {
+            id: db.id,
+            due_at: db.due_at,
+            completed_at: db.completed_at,
+        }
+    }
+}
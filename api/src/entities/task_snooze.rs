@@ -0,0 +1,28 @@
+use sqlx::types::chrono::{DateTime, Utc};
+
+pub struct TaskSnooze {
+    pub id: i32,
+    pub from_due_at: DateTime<Utc>,
+    pub to_due_at: DateTime<Utc>,
+    pub snoozed_at: DateTime<Utc>,
+}
+
+pub struct TaskSnoozeDb {
+    pub id: i32,
+    #[allow(dead_code)]
+    pub task_id: i32,
+    pub from_due_at: DateTime<Utc>,
+    pub to_due_at: DateTime<Utc>,
+    pub snoozed_at: DateTime<Utc>,
+}
+
+impl From<TaskSnoozeDb> for TaskSnooze {
+    fn from(db: TaskSnoozeDb) -> Self {
+        TaskSnooze {
+            id: db.id,
+            from_due_at: db.from_due_at,
+            to_due_at: db.to_due_at,
+            snoozed_at: db.snoozed_at,
+        }
+    }
+}
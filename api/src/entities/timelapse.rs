@@ -0,0 +1,86 @@
+use std::str::FromStr;
+
+use sqlx::types::chrono::{DateTime, Utc};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimelapseStatus {
+    Pending,
+    Running,
+    Completed,
+    Failed,
+}
+
+impl TimelapseStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TimelapseStatus::Pending => "pending",
+            TimelapseStatus::Running => "running",
+            TimelapseStatus::Completed => "completed",
+            TimelapseStatus::Failed => "failed",
+        }
+    }
+}
+
+impl FromStr for TimelapseStatus {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pending" => Ok(TimelapseStatus::Pending),
+            "running" => Ok(TimelapseStatus::Running),
+            "completed" => Ok(TimelapseStatus::Completed),
+            "failed" => Ok(TimelapseStatus::Failed),
+            _ => Err(()),
+        }
+    }
+}
+
+pub struct Timelapse {
+    pub id: i32,
+    pub plant_id: i32,
+    pub range_start: DateTime<Utc>,
+    pub range_end: DateTime<Utc>,
+    pub frame_delay_ms: i32,
+    pub status: TimelapseStatus,
+    pub frame_count: Option<i32>,
+    pub output_key: Option<String>,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+pub struct TimelapseDb {
+    pub id: i32,
+    pub plant_id: i32,
+    pub range_start: DateTime<Utc>,
+    pub range_end: DateTime<Utc>,
+    pub frame_delay_ms: i32,
+    pub status: String,
+    pub frame_count: Option<i32>,
+    pub output_key: Option<String>,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+impl From<TimelapseDb> for Timelapse {
+    fn from(db: TimelapseDb) -> Self {
+        Timelapse {
+            id: db.id,
+            plant_id: db.plant_id,
+            range_start: db.range_start,
+            range_end: db.range_end,
+            frame_delay_ms: db.frame_delay_ms,
+            // The column is constrained to the known statuses.
+            status: db.status.parse().unwrap_or(TimelapseStatus::Pending),
+            frame_count: db.frame_count,
+            output_key: db.output_key,
+            error: db.error,
+            created_at: db.created_at,
+            started_at: db.started_at,
+            finished_at: db.finished_at,
+        }
+    }
+}
@@ -7,6 +7,7 @@ pub struct User {
 pub struct UserDb {
     pub id: i32,
     pub email: String,
+    #[allow(dead_code)]
     pub password_hash: String,
 }
 
@@ -1,9 +1,25 @@
+use std::{net::SocketAddr, sync::Arc, time::Duration};
+
 use tokio::net::TcpListener;
 use tower_http::cors::CorsLayer;
 
+use crate::{
+    controllers::TrustProxy,
+    services::{
+        events::{self, EventBus},
+        heartbeat, identify,
+        jwt::JwtKeys,
+        plant_type,
+        plausibility::PlausibilityLimits,
+        rollup, storage, timelapse,
+    },
+    state::AppState,
+};
+
 mod controllers;
 mod entities;
 mod services;
+mod state;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
@@ -20,15 +36,55 @@ async fn main() -> anyhow::Result<()> {
         .await?;
 
     sqlx::migrate!("./migrations").run(&pool).await?;
+    let plant_types = plant_type::seed_catalog(&pool).await?;
+    log::info!("Seeded {plant_types} plant types");
+
+    let jwt_keys = JwtKeys::from_env()?;
+
+    let rollup_interval = match std::env::var("ROLLUP_REFRESH_INTERVAL_SECONDS") {
+        Ok(seconds) => Duration::from_secs(seconds.parse()?),
+        Err(_) => rollup::DEFAULT_REFRESH_INTERVAL,
+    };
+    rollup::spawn_refresh_task(pool.clone(), rollup_interval);
+
+    let sweep_interval = match std::env::var("OFFLINE_SWEEP_INTERVAL_SECONDS") {
+        Ok(seconds) => Duration::from_secs(seconds.parse()?),
+        Err(_) => heartbeat::SWEEP_INTERVAL,
+    };
+    heartbeat::spawn_sweeper(pool.clone(), sweep_interval);
+
+    let storage = storage::from_env()?;
+    timelapse::spawn_worker(pool.clone(), storage.clone(), timelapse::POLL_INTERVAL);
+
+    let identifier = identify::from_env()?;
+    let measurement_limits = PlausibilityLimits::from_env()?;
+    let trust_proxy = TrustProxy::from_env()?;
+
+    let events = EventBus::new(events::BUS_CAPACITY);
+    events.listen(&pool).await?;
+
+    let state = AppState {
+        pool,
+        jwt_keys: Arc::new(jwt_keys),
+        events,
+        storage,
+        identifier,
+        measurement_limits: Arc::new(measurement_limits),
+        trust_proxy,
+    };
 
-    let app = controllers::create_routes()
+    let app = controllers::create_routes(state.clone())
         .layer(CorsLayer::very_permissive())
-        .with_state(pool);
+        .with_state(state);
 
     let listener = TcpListener::bind("0.0.0.0:8000").await?;
 
     log::info!("Server running on http://localhost:8000");
-    axum::serve(listener, app).await?;
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await?;
 
     Ok(())
 }
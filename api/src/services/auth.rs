@@ -9,11 +9,17 @@ use argon2::{
 };
 use sqlx::{Pool, Postgres};
 
-use crate::services::jwt::generate_jwt;
+use crate::services::{
+    jwt::{AccessToken, JwtKeys},
+    session::{self, RefreshToken},
+};
 
+#[derive(Debug)]
 pub enum AuthError {
     UserAlreadyExists,
     InvalidCredentials,
+    InvalidToken,
+    TokenReused,
     InternalError(Error),
 }
 
@@ -22,50 +28,62 @@ impl Display for AuthError {
         match self {
             AuthError::UserAlreadyExists => write!(f, "User already exists"),
             AuthError::InvalidCredentials => write!(f, "Invalid credentials"),
+            AuthError::InvalidToken => write!(f, "Invalid or expired refresh token"),
+            AuthError::TokenReused => write!(f, "Refresh token has already been used"),
             AuthError::InternalError(e) => write!(f, "Internal error: {}", e),
         }
     }
 }
 
+pub struct AuthTokens {
+    pub access_token: AccessToken,
+    pub refresh_token: RefreshToken,
+}
+
 pub async fn register(
     pool: &Pool<Postgres>,
+    keys: &JwtKeys,
     email: &str,
     password: &str,
-) -> Result<String, AuthError> {
-    let password_hash = hash_password(password).map_err(|e| AuthError::InternalError(e))?;
+    device_name: Option<&str>,
+) -> Result<AuthTokens, AuthError> {
+    let password_hash = hash_password(password).map_err(AuthError::InternalError)?;
 
-    match sqlx::query!(
+    let user_id = match sqlx::query_scalar!(
         r#"
         INSERT INTO "user" (email, password_hash)
         VALUES ($1, $2)
+        RETURNING id
         "#,
         email,
         password_hash,
     )
-    .execute(pool)
+    .fetch_one(pool)
     .await
     {
-        Ok(_) => Ok(generate_jwt(email).map_err(|e| AuthError::InternalError(e))?),
+        Ok(user_id) => user_id,
         Err(sqlx::Error::Database(db_err)) => {
             if db_err.code() == Some("23505".into()) {
                 return Err(AuthError::UserAlreadyExists);
             }
             return Err(AuthError::InternalError(anyhow!(db_err)));
         }
-        Err(e) => {
-            return Err(AuthError::InternalError(anyhow!(e)));
-        }
-    }
+        Err(e) => return Err(AuthError::InternalError(anyhow!(e))),
+    };
+
+    start_session(pool, keys, user_id, email, device_name).await
 }
 
 pub async fn login(
     pool: &Pool<Postgres>,
+    keys: &JwtKeys,
     email: &str,
     password: &str,
-) -> Result<String, AuthError> {
+    device_name: Option<&str>,
+) -> Result<AuthTokens, AuthError> {
     let record = sqlx::query!(
         r#"
-        SELECT password_hash FROM "user" WHERE email = $1
+        SELECT id, password_hash FROM "user" WHERE email = $1
         "#,
         email,
     )
@@ -76,14 +94,50 @@ pub async fn login(
         _ => AuthError::InternalError(anyhow!(e)),
     })?;
 
-    let is_valid = verify_password(password, &record.password_hash)
-        .map_err(|e| AuthError::InternalError(e))?;
+    let is_valid =
+        verify_password(password, &record.password_hash).map_err(AuthError::InternalError)?;
 
     if !is_valid {
         return Err(AuthError::InvalidCredentials);
     }
 
-    Ok(generate_jwt(email).map_err(|e| AuthError::InternalError(e))?)
+    start_session(pool, keys, record.id, email, device_name).await
+}
+
+pub async fn refresh(
+    pool: &Pool<Postgres>,
+    keys: &JwtKeys,
+    refresh_token: &str,
+) -> Result<AuthTokens, AuthError> {
+    let rotated = session::rotate_refresh_token(pool, refresh_token).await?;
+
+    let access_token = keys
+        .generate(&rotated.email, rotated.session_id)
+        .map_err(AuthError::InternalError)?;
+
+    Ok(AuthTokens {
+        access_token,
+        refresh_token: rotated.refresh_token,
+    })
+}
+
+async fn start_session(
+    pool: &Pool<Postgres>,
+    keys: &JwtKeys,
+    user_id: i32,
+    email: &str,
+    device_name: Option<&str>,
+) -> Result<AuthTokens, AuthError> {
+    let (session_id, refresh_token) = session::create_session(pool, user_id, device_name).await?;
+
+    let access_token = keys
+        .generate(email, session_id)
+        .map_err(AuthError::InternalError)?;
+
+    Ok(AuthTokens {
+        access_token,
+        refresh_token,
+    })
 }
 
 fn hash_password(password: &str) -> Result<String> {
@@ -99,10 +153,8 @@ fn hash_password(password: &str) -> Result<String> {
 }
 
 fn verify_password(password: &str, password_hash: &str) -> Result<bool> {
-    let parsed_hash = PasswordHash::new(&password_hash).map_err(|e| anyhow!(e))?;
-    let _ = Argon2::default()
+    let parsed_hash = PasswordHash::new(password_hash).map_err(|e| anyhow!(e))?;
+    Ok(Argon2::default()
         .verify_password(password.as_bytes(), &parsed_hash)
-        .map_err(|e| anyhow!(e))?;
-
-    Ok(true)
+        .is_ok())
 }
@@ -0,0 +1,528 @@
+use std::time::Duration;
+
+use sqlx::{PgConnection, Pool, Postgres, types::chrono::NaiveDateTime};
+
+use crate::{
+    entities::{
+        AutomationAction, Measurement, Task, TaskAutomation, TaskAutomationDb, TaskDb, TaskType,
+    },
+    services::{
+        error::ServiceError,
+        task::{days, record_completion},
+    },
+};
+
+// Rules run on every measurement of a pot linked to a plant and act on the
+// pending watering tasks of that plant.
+
+/// Rise in soil moisture between two readings that counts as a watering.
+pub const RISE_THRESHOLD: f32 = 15.0;
+
+/// Readings further apart than this are not compared.
+pub const RISE_WINDOW: Duration = Duration::from_secs(6 * 60 * 60);
+
+/// Soil moisture below which watering is due.
+pub const DRY_THRESHOLD: f32 = 20.0;
+
+/// Watering tasks due later than this after a detected watering are left
+/// alone, so a recurring task is not completed twice in a row.
+pub const EARLY_COMPLETION_DAYS: i64 = 2;
+
+/// A soil moisture reading of a pot.
+#[derive(Clone, Copy, Debug)]
+pub struct Reading {
+    pub id: i32,
+    pub timestamp: NaiveDateTime,
+    pub soil_moisture: f32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Detection {
+    Watered,
+    Dry,
+}
+
+/// Compares a reading with the one before it. A dry soil is only reported
+/// when the moisture crosses [`DRY_THRESHOLD`], not on every dry reading.
+pub fn detect(previous: &Reading, current: &Reading) -> Option<Detection> {
+    if current.timestamp < previous.timestamp
+        || current.timestamp > previous.timestamp + RISE_WINDOW
+    {
+        return None;
+    }
+
+    if current.soil_moisture - previous.soil_moisture >= RISE_THRESHOLD {
+        Some(Detection::Watered)
+    } else if current.soil_moisture < DRY_THRESHOLD && previous.soil_moisture >= DRY_THRESHOLD {
+        Some(Detection::Dry)
+    } else {
+        None
+    }
+}
+
+/// Applies the rules to a newly recorded measurement and returns the actions
+/// taken.
+pub async fn apply_rules(
+    pool: &Pool<Postgres>,
+    measurement: &Measurement,
+) -> Result<Vec<TaskAutomation>, ServiceError> {
+    let Some(plant_id) = measurement.plant_id else {
+        return Ok(Vec::new());
+    };
+
+    let current = Reading {
+        id: measurement.id,
+        timestamp: measurement.timestamp,
+        soil_moisture: measurement.soil_moisture,
+    };
+    // Only readings taken since the plant was put into the pot compare, the
+    // ones before belong to another plant or an empty pot.
+    let previous = sqlx::query_as!(
+        Reading,
+        "SELECT m.id, m.timestamp, m.soil_moisture FROM measurement m
+        JOIN plant_pot_assignment a ON a.pot_id = m.pot_id AND a.plant_id = $4
+            AND a.linked_at <= $2::timestamp AT TIME ZONE 'UTC'
+            AND (a.unlinked_at IS NULL OR a.unlinked_at > $2::timestamp AT TIME ZONE 'UTC')
+        WHERE m.pot_id = $1 AND (m.timestamp, m.id) < ($2, $3)
+        AND m.timestamp AT TIME ZONE 'UTC' >= a.linked_at
+        AND NOT EXISTS (SELECT 1 FROM measurement_quarantine q WHERE q.measurement_id = m.id)
+        ORDER BY m.timestamp DESC, m.id DESC
+        LIMIT 1",
+        measurement.pot_id,
+        current.timestamp,
+        current.id,
+        plant_id,
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    let Some(previous) = previous else {
+        return Ok(Vec::new());
+    };
+    let Some(detection) = detect(&previous, &current) else {
+        return Ok(Vec::new());
+    };
+
+    let mut tx = pool.begin().await?;
+
+    let tasks: Vec<Task> = sqlx::query_as!(
+        TaskDb,
+        "SELECT * FROM task WHERE plant_id = $1 AND task_type = $2 AND NOT done
+        ORDER BY due_at, id
+        FOR UPDATE",
+        plant_id,
+        TaskType::Watering.as_str(),
+    )
+    .fetch_all(&mut *tx)
+    .await?
+    .into_iter()
+    .map(Task::from)
+    .collect();
+
+    let at = current.timestamp.and_utc();
+    let measurement_ids = [previous.id, current.id];
+    let mut automations = Vec::new();
+
+    for task in tasks {
+        let automation = match detection {
+            Detection::Watered if task.due_at <= at + days(EARLY_COMPLETION_DAYS) => {
+                let (_, completion_id) = record_completion(&mut tx, &task, at).await?;
+                let reason = format!(
+                    "soil moisture rose from {:.1}% to {:.1}%",
+                    previous.soil_moisture, current.soil_moisture
+                );
+                log_automation(
+                    &mut tx,
+                    &task,
+                    AutomationAction::Completed,
+                    &measurement_ids,
+                    &reason,
+                    Some(completion_id),
+                )
+                .await?
+            }
+            Detection::Dry if task.due_at > at => {
+                sqlx::query!(
+                    "UPDATE task SET due_at = $1, updated_at = now() WHERE id = $2",
+                    at,
+                    task.id
+                )
+                .execute(&mut *tx)
+                .await?;
+                let reason = format!(
+                    "soil moisture dropped to {:.1}%, below {:.0}%",
+                    current.soil_moisture, DRY_THRESHOLD
+                );
+                log_automation(
+                    &mut tx,
+                    &task,
+                    AutomationAction::DueEarly,
+                    &measurement_ids,
+                    &reason,
+                    None,
+                )
+                .await?
+            }
+            _ => continue,
+        };
+        automations.push(automation);
+    }
+
+    tx.commit().await?;
+
+    Ok(automations)
+}
+
+async fn log_automation(
+    conn: &mut PgConnection,
+    task: &Task,
+    action: AutomationAction,
+    measurement_ids: &[i32],
+    reason: &str,
+    completion_id: Option<i32>,
+) -> Result<TaskAutomation, ServiceError> {
+    let automation = sqlx::query_as!(
+        TaskAutomationDb,
+        "INSERT INTO task_automation
+            (task_id, action, measurement_ids, reason, previous_due_at, previous_done, completion_id)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        RETURNING *",
+        task.id,
+        action.as_str(),
+        measurement_ids,
+        reason,
+        task.due_at,
+        task.done,
+        completion_id,
+    )
+    .fetch_one(conn)
+    .await?;
+
+    Ok(TaskAutomation::from(automation))
+}
+
+/// Returns the automatic actions taken on the user's tasks, most recent first.
+pub async fn get_automations(
+    pool: &Pool<Postgres>,
+    user_id: i32,
+    task_id: Option<i32>,
+) -> Result<Vec<TaskAutomation>, ServiceError> {
+    let automations = sqlx::query_as!(
+        TaskAutomationDb,
+        "SELECT a.* FROM task_automation a
+        JOIN task t ON t.id = a.task_id
+        WHERE t.owner_id = $1 AND ($2::int IS NULL OR a.task_id = $2)
+        ORDER BY a.created_at DESC, a.id DESC",
+        user_id,
+        task_id,
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(TaskAutomation::from)
+    .collect();
+
+    Ok(automations)
+}
+
+/// Undoes an automatic action: removes the completion it recorded and
+/// restores the due date and done state of the task. Only possible while the
+/// task has not changed since.
+pub async fn revert_automation(
+    pool: &Pool<Postgres>,
+    user_id: i32,
+    automation_id: i32,
+) -> Result<TaskAutomation, ServiceError> {
+    let mut tx = pool.begin().await?;
+
+    let automation = sqlx::query_as!(
+        TaskAutomationDb,
+        "SELECT a.* FROM task_automation a
+        JOIN task t ON t.id = a.task_id
+        WHERE a.id = $1 AND t.owner_id = $2
+        FOR UPDATE",
+        automation_id,
+        user_id
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    .map(TaskAutomation::from)
+    .ok_or(ServiceError::NotFound("Task automation"))?;
+
+    if automation.reverted_at.is_some() {
+        return Err(ServiceError::conflict(
+            "AUTOMATION_ALREADY_REVERTED",
+            "Automatic action is already reverted",
+        ));
+    }
+
+    let updated_at = sqlx::query_scalar!(
+        "SELECT updated_at FROM task WHERE id = $1",
+        automation.task_id
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+    if updated_at > automation.created_at {
+        return Err(ServiceError::conflict(
+            "TASK_MODIFIED",
+            "Task changed after the automatic action",
+        ));
+    }
+
+    if let Some(completion_id) = automation.completion_id {
+        sqlx::query!("DELETE FROM task_completion WHERE id = $1", completion_id)
+            .execute(&mut *tx)
+            .await?;
+    }
+    sqlx::query!(
+        "UPDATE task SET due_at = $1, done = $2, updated_at = now() WHERE id = $3",
+        automation.previous_due_at,
+        automation.previous_done,
+        automation.task_id,
+    )
+    .execute(&mut *tx)
+    .await?;
+    let automation = sqlx::query_as!(
+        TaskAutomationDb,
+        "UPDATE task_automation SET reverted_at = now() WHERE id = $1 RETURNING *",
+        automation.id
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(TaskAutomation::from(automation))
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::{PgPool, types::chrono::NaiveDate};
+
+    use super::*;
+    use crate::services::{
+        link, measurement,
+        plant::{self, PlantInput},
+        plausibility::PlausibilityLimits,
+        pot::{self, PotRegistration},
+        task::{self, TaskInput},
+    };
+
+    fn time(hour: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2026, 1, 1)
+            .and_then(|date| date.and_hms_opt(hour, 0, 0))
+            .expect("time")
+    }
+
+    fn reading(id: i32, hour: u32, soil_moisture: f32) -> Reading {
+        Reading {
+            id,
+            timestamp: time(hour),
+            soil_moisture,
+        }
+    }
+
+    #[test]
+    fn detects_a_sharp_rise() {
+        assert_eq!(
+            detect(&reading(1, 0, 20.0), &reading(2, 1, 45.0)),
+            Some(Detection::Watered)
+        );
+        assert_eq!(detect(&reading(1, 0, 20.0), &reading(2, 1, 30.0)), None);
+    }
+
+    #[test]
+    fn detects_drying_out_once() {
+        assert_eq!(
+            detect(&reading(1, 0, 22.0), &reading(2, 1, 18.0)),
+            Some(Detection::Dry)
+        );
+        assert_eq!(detect(&reading(1, 0, 18.0), &reading(2, 1, 15.0)), None);
+    }
+
+    #[test]
+    fn ignores_readings_far_apart() {
+        assert_eq!(detect(&reading(1, 0, 20.0), &reading(2, 12, 45.0)), None);
+    }
+
+    /// A user with a pot linked to a plant, and a weekly watering task of
+    /// that plant due `due_in_days` after the first reading.
+    async fn setup(pool: &PgPool, due_in_days: i64) -> (i32, i32, i32) {
+        let user_id = sqlx::query_scalar!(
+            r#"INSERT INTO "user" (email, password_hash) VALUES ('a@example.com', 'unused') RETURNING id"#
+        )
+        .fetch_one(pool)
+        .await
+        .expect("user");
+        let plant = plant::create_plant(pool, &PlantInput::new("Monstera"), user_id)
+            .await
+            .expect("plant");
+        let (pot, _) = pot::create_pot(pool, user_id, &PotRegistration::default())
+            .await
+            .expect("pot");
+        link::link_plant_to_pot(pool, user_id, plant.id, pot.id)
+            .await
+            .expect("link");
+        // The readings are dated in the past, the link has to cover them.
+        sqlx::query!(
+            "UPDATE plant_pot_assignment SET linked_at = '2025-01-01Z' WHERE pot_id = $1",
+            pot.id
+        )
+        .execute(pool)
+        .await
+        .expect("backdate");
+        let task = task::create_task(
+            pool,
+            user_id,
+            &TaskInput {
+                name: "Water".to_string(),
+                task_type: TaskType::Watering,
+                plant_id: Some(plant.id),
+                due_at: time(0).and_utc() + days(due_in_days),
+                interval_days: Some(7),
+            },
+        )
+        .await
+        .expect("task");
+
+        (user_id, pot.id, task.id)
+    }
+
+    async fn measure(pool: &PgPool, pot_id: i32, hour: u32, soil_moisture: f32) {
+        measurement::create_measurement(
+            pool,
+            &PlausibilityLimits::without_age_limit(),
+            pot_id,
+            soil_moisture,
+            21.0,
+            300.0,
+            55.0,
+            90,
+            time(hour),
+        )
+        .await
+        .expect("measurement");
+    }
+
+    #[sqlx::test]
+    async fn completes_watering_and_reverts(pool: PgPool) {
+        let (user_id, pot_id, task_id) = setup(&pool, 0).await;
+
+        measure(&pool, pot_id, 0, 20.0).await;
+        measure(&pool, pot_id, 1, 45.0).await;
+
+        let automations = get_automations(&pool, user_id, Some(task_id))
+            .await
+            .expect("automations");
+        assert_eq!(automations.len(), 1);
+        let automation = &automations[0];
+        assert_eq!(automation.action, AutomationAction::Completed);
+        assert_eq!(automation.measurement_ids.len(), 2);
+        let completed = task::get_task(&pool, user_id, task_id).await.expect("task");
+        assert_eq!(completed.due_at, time(1).and_utc() + days(7));
+        assert_eq!(
+            task::get_completions(&pool, user_id, task_id)
+                .await
+                .expect("completions")
+                .len(),
+            1
+        );
+
+        let reverted = revert_automation(&pool, user_id, automation.id)
+            .await
+            .expect("revert");
+        assert!(reverted.reverted_at.is_some());
+        let restored = task::get_task(&pool, user_id, task_id).await.expect("task");
+        assert_eq!(restored.due_at, time(0).and_utc());
+        assert!(
+            task::get_completions(&pool, user_id, task_id)
+                .await
+                .expect("completions")
+                .is_empty()
+        );
+        assert!(matches!(
+            revert_automation(&pool, user_id, automation.id).await,
+            Err(ServiceError::Conflict { .. })
+        ));
+    }
+
+    #[sqlx::test]
+    async fn ignores_readings_from_before_the_link(pool: PgPool) {
+        let (user_id, pot_id, task_id) = setup(&pool, 0).await;
+
+        measure(&pool, pot_id, 0, 20.0).await;
+        sqlx::query!(
+            "UPDATE plant_pot_assignment SET linked_at = '2026-01-01 00:30Z' WHERE pot_id = $1",
+            pot_id
+        )
+        .execute(&pool)
+        .await
+        .expect("relink");
+        measure(&pool, pot_id, 1, 45.0).await;
+
+        assert!(
+            get_automations(&pool, user_id, Some(task_id))
+                .await
+                .expect("automations")
+                .is_empty()
+        );
+    }
+
+    #[sqlx::test]
+    async fn purged_readings_are_dropped_from_the_log(pool: PgPool) {
+        let (user_id, pot_id, task_id) = setup(&pool, 0).await;
+
+        measure(&pool, pot_id, 0, 20.0).await;
+        measure(&pool, pot_id, 1, 45.0).await;
+        pot::delete_pot(&pool, user_id, pot_id, true)
+            .await
+            .expect("purge");
+
+        let automations = get_automations(&pool, user_id, Some(task_id))
+            .await
+            .expect("automations");
+        assert_eq!(automations.len(), 1);
+        assert!(automations[0].measurement_ids.is_empty());
+        revert_automation(&pool, user_id, automations[0].id)
+            .await
+            .expect("revert");
+    }
+
+    #[sqlx::test]
+    async fn brings_watering_forward_when_dry(pool: PgPool) {
+        let (user_id, pot_id, task_id) = setup(&pool, 5).await;
+
+        measure(&pool, pot_id, 0, 25.0).await;
+        measure(&pool, pot_id, 1, 15.0).await;
+
+        let task = task::get_task(&pool, user_id, task_id).await.expect("task");
+        assert_eq!(task.due_at, time(1).and_utc());
+        let automations = get_automations(&pool, user_id, None)
+            .await
+            .expect("automations");
+        assert_eq!(automations.len(), 1);
+        assert_eq!(automations[0].action, AutomationAction::DueEarly);
+    }
+
+    #[sqlx::test]
+    async fn does_not_revert_over_later_changes(pool: PgPool) {
+        let (user_id, pot_id, task_id) = setup(&pool, 5).await;
+
+        measure(&pool, pot_id, 0, 25.0).await;
+        measure(&pool, pot_id, 1, 15.0).await;
+        task::complete_task(&pool, user_id, task_id, time(2).and_utc())
+            .await
+            .expect("complete");
+
+        let automations = get_automations(&pool, user_id, None)
+            .await
+            .expect("automations");
+        assert!(matches!(
+            revert_automation(&pool, user_id, automations[0].id).await,
+            Err(ServiceError::Conflict {
+                code: "TASK_MODIFIED",
+                ..
+            })
+        ));
+    }
+}
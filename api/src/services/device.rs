@@ -0,0 +1,266 @@
+use sqlx::{PgConnection, PgPool, types::Uuid};
+
+use crate::{
+    entities::{DeviceCredential, DeviceCredentialDb},
+    services::{
+        error::ServiceError,
+        token::{generate_token, hash_token},
+    },
+};
+
+/// A freshly issued device credential. The secret is only ever returned once,
+/// the database keeps a hash of it.
+pub struct IssuedCredential {
+    pub id: Uuid,
+    pub secret: String,
+}
+
+impl IssuedCredential {
+    /// The value a device sends as `Authorization: Device <credential>`.
+    pub fn credential(&self) -> String {
+        format!("{}.{}", self.id, self.secret)
+    }
+}
+
+pub async fn issue_credential(
+    conn: &mut PgConnection,
+    pot_id: i32,
+) -> Result<IssuedCredential, ServiceError> {
+    let secret = generate_token();
+
+    let id = sqlx::query_scalar!(
+        "INSERT INTO device_credential (pot_id, secret_hash) VALUES ($1, $2) RETURNING id",
+        pot_id,
+        hash_token(&secret),
+    )
+    .fetch_one(conn)
+    .await?;
+
+    Ok(IssuedCredential { id, secret })
+}
+
+/// Resolves a device credential to the pot it was issued for. Returns `None`
+/// for malformed, unknown or revoked credentials.
+pub async fn verify_credential(
+    pool: &PgPool,
+    credential: &str,
+) -> Result<Option<i32>, ServiceError> {
+    let Some((id, secret)) = credential.split_once('.') else {
+        return Ok(None);
+    };
+    let Ok(id) = id.parse::<Uuid>() else {
+        return Ok(None);
+    };
+
+    let pot_id = sqlx::query_scalar!(
+        "UPDATE device_credential SET last_used_at = now()
+        WHERE id = $1 AND secret_hash = $2 AND revoked_at IS NULL
+        RETURNING pot_id",
+        id,
+        hash_token(secret),
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(pot_id)
+}
+
+pub async fn get_credentials(
+    pool: &PgPool,
+    user_id: i32,
+    pot_id: i32,
+) -> Result<Vec<DeviceCredential>, ServiceError> {
+    sqlx::query_scalar!(
+        "SELECT id FROM pot WHERE id = $1 AND owner_id = $2",
+        pot_id,
+        user_id
+    )
+    .fetch_optional(pool)
+    .await?
+    .ok_or(ServiceError::NotFound("Pot"))?;
+
+    let credentials = sqlx::query_as!(
+        DeviceCredentialDb,
+        "SELECT * FROM device_credential
+        WHERE pot_id = $1 AND revoked_at IS NULL
+        ORDER BY created_at",
+        pot_id
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(DeviceCredential::from)
+    .collect();
+
+    Ok(credentials)
+}
+
+/// Issues a new credential for a pot owned by the user, optionally revoking
+/// all previous ones.
+pub async fn rotate_credential(
+    pool: &PgPool,
+    user_id: i32,
+    pot_id: i32,
+    revoke_existing: bool,
+) -> Result<IssuedCredential, ServiceError> {
+    let mut tx = pool.begin().await?;
+
+    let archived_at = sqlx::query_scalar!(
+        "SELECT archived_at FROM pot WHERE id = $1 AND owner_id = $2 FOR UPDATE",
+        pot_id,
+        user_id
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or(ServiceError::NotFound("Pot"))?;
+
+    if archived_at.is_some() {
+        return Err(ServiceError::conflict(
+            "POT_ARCHIVED",
+            "Pot is unregistered and can no longer report measurements",
+        ));
+    }
+
+    if revoke_existing {
+        sqlx::query!(
+            "UPDATE device_credential SET revoked_at = now()
+            WHERE pot_id = $1 AND revoked_at IS NULL",
+            pot_id
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    let credential = issue_credential(&mut tx, pot_id).await?;
+
+    tx.commit().await?;
+
+    Ok(credential)
+}
+
+/// Revokes an active credential of a pot owned by the user.
+pub async fn revoke_credential(
+    pool: &PgPool,
+    user_id: i32,
+    pot_id: i32,
+    credential_id: Uuid,
+) -> Result<(), ServiceError> {
+    let result = sqlx::query!(
+        "UPDATE device_credential SET revoked_at = now()
+        FROM pot
+        WHERE pot.id = device_credential.pot_id
+        AND device_credential.id = $1
+        AND device_credential.pot_id = $2
+        AND pot.owner_id = $3
+        AND device_credential.revoked_at IS NULL",
+        credential_id,
+        pot_id,
+        user_id
+    )
+    .execute(pool)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(ServiceError::NotFound("Device credential"));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::pot::{self, PotRegistration};
+
+    async fn create_user(pool: &PgPool, email: &str) -> i32 {
+        sqlx::query_scalar!(
+            r#"INSERT INTO "user" (email, password_hash) VALUES ($1, 'x') RETURNING id"#,
+            email
+        )
+        .fetch_one(pool)
+        .await
+        .expect("user")
+    }
+
+    async fn verify(pool: &PgPool, credential: &IssuedCredential) -> Option<i32> {
+        verify_credential(pool, &credential.credential())
+            .await
+            .expect("verify")
+    }
+
+    #[sqlx::test]
+    async fn rotated_credentials_stop_working(pool: PgPool) {
+        let user_id = create_user(&pool, "a@example.com").await;
+        let (pot, first) = pot::create_pot(&pool, user_id, &PotRegistration::default())
+            .await
+            .expect("pot");
+
+        // Without revoking, both credentials work during the changeover.
+        let second = rotate_credential(&pool, user_id, pot.id, false)
+            .await
+            .expect("rotate");
+        assert_eq!(verify(&pool, &first).await, Some(pot.id));
+        assert_eq!(verify(&pool, &second).await, Some(pot.id));
+
+        let third = rotate_credential(&pool, user_id, pot.id, true)
+            .await
+            .expect("rotate");
+        assert_eq!(verify(&pool, &first).await, None);
+        assert_eq!(verify(&pool, &second).await, None);
+        assert_eq!(verify(&pool, &third).await, Some(pot.id));
+        assert_eq!(
+            get_credentials(&pool, user_id, pot.id)
+                .await
+                .expect("credentials")
+                .len(),
+            1
+        );
+    }
+
+    #[sqlx::test]
+    async fn revoked_credentials_are_refused(pool: PgPool) {
+        let user_id = create_user(&pool, "a@example.com").await;
+        let (pot, credential) = pot::create_pot(&pool, user_id, &PotRegistration::default())
+            .await
+            .expect("pot");
+
+        revoke_credential(&pool, user_id, pot.id, credential.id)
+            .await
+            .expect("revoke");
+
+        assert_eq!(verify(&pool, &credential).await, None);
+        assert!(matches!(
+            revoke_credential(&pool, user_id, pot.id, credential.id).await,
+            Err(ServiceError::NotFound(_))
+        ));
+        // A wrong secret for a known id is refused as well.
+        let forged = IssuedCredential {
+            id: credential.id,
+            secret: generate_token(),
+        };
+        assert_eq!(verify(&pool, &forged).await, None);
+    }
+
+    #[sqlx::test]
+    async fn credentials_of_foreign_pots_cannot_be_managed(pool: PgPool) {
+        let owner = create_user(&pool, "a@example.com").await;
+        let other = create_user(&pool, "b@example.com").await;
+        let (pot, credential) = pot::create_pot(&pool, owner, &PotRegistration::default())
+            .await
+            .expect("pot");
+
+        assert!(matches!(
+            get_credentials(&pool, other, pot.id).await,
+            Err(ServiceError::NotFound(_))
+        ));
+        assert!(matches!(
+            rotate_credential(&pool, other, pot.id, true).await,
+            Err(ServiceError::NotFound(_))
+        ));
+        assert!(matches!(
+            revoke_credential(&pool, other, pot.id, credential.id).await,
+            Err(ServiceError::NotFound(_))
+        ));
+        assert_eq!(verify(&pool, &credential).await, Some(pot.id));
+    }
+}
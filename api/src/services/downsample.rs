@@ -0,0 +1,247 @@
+use std::time::Duration;
+
+use serde::Deserialize;
+use sqlx::{Pool, Postgres, types::chrono::NaiveDateTime};
+
+use crate::{
+    entities::{Measurement, MeasurementDb},
+    services::{
+        error::ServiceError,
+        measurement::{MeasurementSource, resolve_pot_ids},
+    },
+};
+
+pub const DEFAULT_MAX_POINTS: usize = 500;
+pub const MAX_MAX_POINTS: usize = 5000;
+
+/// Every raw measurement in the range is loaded to pick the points from, so
+/// the range is bounded. Longer ranges are served by the rollups.
+pub const MAX_RANGE: Duration = Duration::from_secs(92 * 24 * 60 * 60);
+
+/// The metric whose shape is preserved when downsampling.
+#[derive(Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Metric {
+    #[default]
+    SoilMoisture,
+    Temperature,
+    LightLevel,
+    Humidity,
+}
+
+impl Metric {
+    fn value(self, measurement: &Measurement) -> f32 {
+        match self {
+            Metric::SoilMoisture => measurement.soil_moisture,
+            Metric::Temperature => measurement.temperature,
+            Metric::LightLevel => measurement.light_level,
+            Metric::Humidity => measurement.humidity,
+        }
+    }
+}
+
+/// Returns at most `max_points` raw measurements of a pot or plant between
+/// `start` and `end`, oldest first, picked with [`lttb`] on `metric`.
+pub async fn get_downsampled(
+    pool: &Pool<Postgres>,
+    user_id: i32,
+    source: MeasurementSource,
+    metric: Metric,
+    max_points: usize,
+    start: NaiveDateTime,
+    end: NaiveDateTime,
+) -> Result<Vec<Measurement>, ServiceError> {
+    if !(3..=MAX_MAX_POINTS).contains(&max_points) {
+        return Err(ServiceError::validation(
+            "maxPoints",
+            format!("must be between 3 and {}", MAX_MAX_POINTS),
+        ));
+    }
+    if start > end {
+        return Err(ServiceError::validation(
+            "startDate",
+            "must not be after endDate",
+        ));
+    }
+    if start < end - MAX_RANGE {
+        return Err(ServiceError::validation(
+            "startDate",
+            format!(
+                "must be at most {} days before endDate, use the aggregates for longer ranges",
+                MAX_RANGE.as_secs() / (24 * 60 * 60)
+            ),
+        ));
+    }
+
+    let pot_ids = resolve_pot_ids(pool, user_id, source).await?;
+
+    let measurements: Vec<Measurement> = sqlx::query_as!(
+        MeasurementDb,
+        r#"SELECT m.id, m.pot_id, a.plant_id AS "plant_id?", m.timestamp, m.soil_moisture,
+            m.temperature, m.light_level, m.humidity, m.battery_level
+        FROM measurement m
+        LEFT JOIN plant_pot_assignment a ON a.pot_id = m.pot_id
+            AND a.linked_at <= m.timestamp AT TIME ZONE 'UTC'
+            AND (a.unlinked_at IS NULL OR a.unlinked_at > m.timestamp AT TIME ZONE 'UTC')
+        WHERE m.pot_id = ANY($1) AND m.timestamp >= $2 AND m.timestamp <= $3
+        AND NOT EXISTS (SELECT 1 FROM measurement_quarantine q WHERE q.measurement_id = m.id)
+        AND ($4::int IS NULL OR a.plant_id = $4)
+        ORDER BY m.timestamp, m.id"#,
+        &pot_ids,
+        start,
+        end,
+        source.plant_id(),
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(Measurement::from)
+    .collect();
+
+    let points: Vec<(f64, f64)> = measurements
+        .iter()
+        .map(|m| {
+            (
+                m.timestamp.and_utc().timestamp_millis() as f64,
+                f64::from(metric.value(m)),
+            )
+        })
+        .collect();
+    let selected = lttb(&points, max_points);
+
+    let mut selected = selected.into_iter().peekable();
+    Ok(measurements
+        .into_iter()
+        .enumerate()
+        .filter_map(|(i, m)| selected.next_if_eq(&i).map(|_| m))
+        .collect())
+}
+
+/// Largest-Triangle-Three-Buckets: picks `threshold` of `points` (sorted by
+/// x) that preserve the visual shape of the series. Returns the indices of the
+/// picked points in ascending order. The first and last point are always kept.
+pub fn lttb(points: &[(f64, f64)], threshold: usize) -> Vec<usize> {
+    let n = points.len();
+    if threshold >= n || threshold < 3 {
+        return (0..n).collect();
+    }
+
+    let bucket_size = (n - 2) as f64 / (threshold - 2) as f64;
+    let mut selected = Vec::with_capacity(threshold);
+    selected.push(0);
+    let mut a = 0;
+
+    for i in 0..threshold - 2 {
+        // Average of the next bucket, the third corner of the triangle.
+        let next_start = ((i + 1) as f64 * bucket_size) as usize + 1;
+        let next_end = (((i + 2) as f64 * bucket_size) as usize + 1).min(n);
+        let next = &points[next_start..next_end];
+        let avg_x = next.iter().map(|p| p.0).sum::<f64>() / next.len() as f64;
+        let avg_y = next.iter().map(|p| p.1).sum::<f64>() / next.len() as f64;
+
+        let start = (i as f64 * bucket_size) as usize + 1;
+        let end = ((i + 1) as f64 * bucket_size) as usize + 1;
+        let (ax, ay) = points[a];
+        let mut best = start;
+        let mut best_area = -1.0;
+        for (j, &(bx, by)) in points.iter().enumerate().take(end).skip(start) {
+            let area = ((ax - avg_x) * (by - ay) - (ax - bx) * (avg_y - ay)).abs();
+            if area > best_area {
+                best_area = area;
+                best = j;
+            }
+        }
+
+        selected.push(best);
+        a = best;
+    }
+
+    selected.push(n - 1);
+    selected
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::{PgPool, types::chrono::NaiveDate};
+
+    use super::*;
+
+    fn series(values: &[f64]) -> Vec<(f64, f64)> {
+        values
+            .iter()
+            .enumerate()
+            .map(|(x, &y)| (x as f64, y))
+            .collect()
+    }
+
+    #[test]
+    fn keeps_short_series() {
+        let points = series(&[1.0, 2.0, 3.0]);
+
+        assert_eq!(lttb(&points, 5), vec![0, 1, 2]);
+        assert_eq!(lttb(&points, 3), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn returns_threshold_points_including_endpoints() {
+        let points = series(
+            &(0..1000)
+                .map(|x| (x as f64 / 10.0).sin())
+                .collect::<Vec<_>>(),
+        );
+
+        let selected = lttb(&points, 50);
+
+        assert_eq!(selected.len(), 50);
+        assert_eq!(selected.first(), Some(&0));
+        assert_eq!(selected.last(), Some(&999));
+        assert!(selected.windows(2).all(|w| w[0] < w[1]));
+    }
+
+    #[test]
+    fn keeps_spikes() {
+        let mut values = vec![0.0; 100];
+        values[37] = 50.0;
+        values[71] = -50.0;
+
+        let selected = lttb(&series(&values), 10);
+
+        assert!(selected.contains(&37));
+        assert!(selected.contains(&71));
+    }
+
+    #[sqlx::test]
+    async fn rejects_long_ranges(pool: PgPool) {
+        let end = NaiveDate::from_ymd_opt(2026, 6, 1)
+            .and_then(|date| date.and_hms_opt(0, 0, 0))
+            .expect("end");
+
+        assert!(matches!(
+            get_downsampled(
+                &pool,
+                1,
+                MeasurementSource::Pot(1),
+                Metric::default(),
+                DEFAULT_MAX_POINTS,
+                end - MAX_RANGE - Duration::from_secs(1),
+                end,
+            )
+            .await,
+            Err(ServiceError::Validation(_))
+        ));
+        // The range itself is fine, the pot does not exist.
+        assert!(matches!(
+            get_downsampled(
+                &pool,
+                1,
+                MeasurementSource::Pot(1),
+                Metric::default(),
+                DEFAULT_MAX_POINTS,
+                end - MAX_RANGE,
+                end,
+            )
+            .await,
+            Err(ServiceError::NotFound(_))
+        ));
+    }
+}
@@ -0,0 +1,74 @@
+use std::fmt::Display;
+
+use anyhow::anyhow;
+
+/// Failures a service can report to its caller. Controllers map each variant
+/// onto an HTTP status and a machine-readable error code.
+#[derive(Debug)]
+pub enum ServiceError {
+    /// The resource does not exist or belongs to another user.
+    NotFound(&'static str),
+    Conflict {
+        code: &'static str,
+        message: String,
+    },
+    Validation(Vec<FieldError>),
+    /// An external service the request depends on failed or is not set up.
+    Unavailable(String),
+    /// The caller made too many attempts and has to wait.
+    RateLimited(String),
+    Internal(anyhow::Error),
+}
+
+#[derive(Debug)]
+pub struct FieldError {
+    pub field: &'static str,
+    pub issue: String,
+}
+
+impl ServiceError {
+    pub fn conflict(code: &'static str, message: impl Into<String>) -> Self {
+        ServiceError::Conflict {
+            code,
+            message: message.into(),
+        }
+    }
+
+    pub fn validation(field: &'static str, issue: impl Into<String>) -> Self {
+        ServiceError::Validation(vec![FieldError {
+            field,
+            issue: issue.into(),
+        }])
+    }
+}
+
+impl Display for ServiceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ServiceError::NotFound(resource) => write!(f, "{} not found", resource),
+            ServiceError::Conflict { message, .. } => write!(f, "{}", message),
+            ServiceError::Validation(errors) => {
+                let fields: Vec<String> = errors
+                    .iter()
+                    .map(|e| format!("{}: {}", e.field, e.issue))
+                    .collect();
+                write!(f, "Invalid input: {}", fields.join(", "))
+            }
+            ServiceError::Unavailable(message) => write!(f, "{}", message),
+            ServiceError::RateLimited(message) => write!(f, "{}", message),
+            ServiceError::Internal(e) => write!(f, "Internal error: {}", e),
+        }
+    }
+}
+
+impl From<sqlx::Error> for ServiceError {
+    fn from(e: sqlx::Error) -> Self {
+        ServiceError::Internal(anyhow!(e))
+    }
+}
+
+impl From<anyhow::Error> for ServiceError {
+    fn from(e: anyhow::Error) -> Self {
+        ServiceError::Internal(e)
+    }
+}
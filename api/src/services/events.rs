@@ -0,0 +1,287 @@
+use std::sync::Arc;
+
+use log::{debug, error, warn};
+use serde::{Deserialize, Serialize};
+use sqlx::{Connection, Executor, PgConnection, Pool, Postgres, postgres::PgListener};
+use tokio::sync::broadcast;
+
+use crate::entities::Measurement;
+
+/// Postgres channel every replica listens on.
+const CHANNEL: &str = "live_events";
+
+/// Number of events buffered per subscriber before it starts lagging.
+pub const BUS_CAPACITY: usize = 1024;
+
+/// An event pushed to live subscribers, serialized as
+/// `{"type": "measurement" | "linked" | "unlinked" | "potOffline" |
+/// "potOnline", ...}`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(
+    tag = "type",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum LiveEvent {
+    Measurement {
+        pot_id: i32,
+        measurement: MeasurementEvent,
+    },
+    Linked {
+        pot_id: i32,
+        plant_id: i32,
+    },
+    Unlinked {
+        pot_id: i32,
+        plant_id: i32,
+    },
+    /// The pot stopped reporting.
+    PotOffline {
+        pot_id: i32,
+        last_seen_at: String,
+    },
+    /// A pot reported offline is back.
+    PotOnline {
+        pot_id: i32,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MeasurementEvent {
+    pub id: i32,
+    pub plant_id: Option<i32>,
+    pub timestamp: String,
+    pub soil_moisture: f32,
+    pub temperature: f32,
+    pub light_level: f32,
+    pub humidity: f32,
+    pub battery_level: i32,
+}
+
+impl LiveEvent {
+    pub fn pot_id(&self) -> i32 {
+        match self {
+            LiveEvent::Measurement { pot_id, .. }
+            | LiveEvent::Linked { pot_id, .. }
+            | LiveEvent::Unlinked { pot_id, .. }
+            | LiveEvent::PotOffline { pot_id, .. }
+            | LiveEvent::PotOnline { pot_id } => *pot_id,
+        }
+    }
+}
+
+impl From<&Measurement> for LiveEvent {
+    fn from(measurement: &Measurement) -> Self {
+        LiveEvent::Measurement {
+            pot_id: measurement.pot_id,
+            measurement: MeasurementEvent {
+                id: measurement.id,
+                plant_id: measurement.plant_id,
+                timestamp: measurement.timestamp.and_utc().to_rfc3339(),
+                soil_moisture: measurement.soil_moisture,
+                temperature: measurement.temperature,
+                light_level: measurement.light_level,
+                humidity: measurement.humidity,
+                battery_level: measurement.battery_level,
+            },
+        }
+    }
+}
+
+/// A [`LiveEvent`] together with the user it belongs to.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Notification {
+    pub owner_id: i32,
+    pub event: LiveEvent,
+}
+
+/// Publishes an event about a pot to every replica. Delivery is best effort:
+/// failures are logged and never fail the operation that caused the event.
+/// Inside a transaction use [`publish_in_tx`] instead, a failed statement
+/// would abort it.
+pub async fn publish(pool: &Pool<Postgres>, event: LiveEvent) {
+    if let Err(e) = notify(pool, &event).await {
+        error!("Failed to publish live event: {}", e);
+    }
+}
+
+/// Publishes an event from within the caller's transaction, it is only sent
+/// on commit. The notification runs in a savepoint, so failing to publish
+/// leaves the transaction intact; only failing to manage the savepoint is
+/// reported.
+pub async fn publish_in_tx(conn: &mut PgConnection, event: LiveEvent) -> Result<(), sqlx::Error> {
+    let mut savepoint = conn.begin().await?;
+    match notify(&mut *savepoint, &event).await {
+        Ok(()) => savepoint.commit().await,
+        Err(e) => {
+            error!("Failed to publish live event: {}", e);
+            savepoint.rollback().await
+        }
+    }
+}
+
+async fn notify<'e>(
+    executor: impl Executor<'e, Database = Postgres>,
+    event: &LiveEvent,
+) -> Result<(), sqlx::Error> {
+    let payload = match serde_json::to_value(event) {
+        Ok(payload) => payload,
+        Err(e) => {
+            error!("Failed to serialize live event: {}", e);
+            return Ok(());
+        }
+    };
+
+    // The owner is resolved in the same statement that sends the notification.
+    sqlx::query!(
+        r#"SELECT pg_notify($1, json_build_object('ownerId', owner_id, 'event', $3::jsonb)::text)
+        FROM pot WHERE id = $2"#,
+        CHANNEL,
+        event.pot_id(),
+        payload,
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// In-process fan-out of the notifications received from Postgres.
+#[derive(Clone)]
+pub struct EventBus {
+    sender: broadcast::Sender<Arc<Notification>>,
+}
+
+impl EventBus {
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity);
+        EventBus { sender }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<Arc<Notification>> {
+        self.sender.subscribe()
+    }
+
+    /// Starts listening for notifications and forwarding them to the
+    /// subscribers. Returns once the listener is connected.
+    pub async fn listen(&self, pool: &Pool<Postgres>) -> Result<(), sqlx::Error> {
+        let mut listener = PgListener::connect_with(pool).await?;
+        listener.listen(CHANNEL).await?;
+
+        let sender = self.sender.clone();
+        tokio::spawn(async move {
+            loop {
+                // `recv` reconnects by itself if the connection drops; events
+                // published in the meantime are lost.
+                let notification = match listener.recv().await {
+                    Ok(notification) => notification,
+                    Err(e) => {
+                        warn!("Live event listener error: {}", e);
+                        tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+                        continue;
+                    }
+                };
+
+                match serde_json::from_str::<Notification>(notification.payload()) {
+                    // Sending only fails when nobody is subscribed.
+                    Ok(notification) => {
+                        let _ = sender.send(Arc::new(notification));
+                    }
+                    Err(e) => debug!("Ignoring malformed live event: {}", e),
+                }
+            }
+        });
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use sqlx::PgPool;
+
+    use super::*;
+
+    #[test]
+    fn serializes_tagged_camel_case() {
+        let event = LiveEvent::Linked {
+            pot_id: 1,
+            plant_id: 2,
+        };
+
+        assert_eq!(
+            serde_json::to_value(&event).expect("json"),
+            serde_json::json!({ "type": "linked", "potId": 1, "plantId": 2 })
+        );
+    }
+
+    #[sqlx::test]
+    async fn delivers_published_events_to_subscribers(pool: PgPool) {
+        let owner_id = sqlx::query_scalar!(
+            r#"INSERT INTO "user" (email, password_hash) VALUES ('a@example.com', 'unused') RETURNING id"#
+        )
+        .fetch_one(&pool)
+        .await
+        .expect("user");
+        let pot_id = sqlx::query_scalar!(
+            "INSERT INTO pot (owner_id) VALUES ($1) RETURNING id",
+            owner_id
+        )
+        .fetch_one(&pool)
+        .await
+        .expect("pot");
+
+        let bus = EventBus::new(16);
+        let mut receiver = bus.subscribe();
+        bus.listen(&pool).await.expect("listen");
+
+        let event = LiveEvent::Unlinked {
+            pot_id,
+            plant_id: 7,
+        };
+        publish(&pool, event.clone()).await;
+
+        let notification = tokio::time::timeout(Duration::from_secs(5), receiver.recv())
+            .await
+            .expect("timely delivery")
+            .expect("notification");
+        assert_eq!(notification.owner_id, owner_id);
+        assert_eq!(notification.event, event);
+    }
+
+    #[sqlx::test]
+    async fn failed_publishing_keeps_the_transaction(pool: PgPool) {
+        let owner_id = sqlx::query_scalar!(
+            r#"INSERT INTO "user" (email, password_hash) VALUES ('a@example.com', 'unused') RETURNING id"#
+        )
+        .fetch_one(&pool)
+        .await
+        .expect("user");
+
+        let mut tx = pool.begin().await.expect("transaction");
+        let pot_id = sqlx::query_scalar!(
+            "INSERT INTO pot (owner_id) VALUES ($1) RETURNING id",
+            owner_id
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .expect("pot");
+        // Notification payloads are limited to 8000 bytes.
+        let oversized = LiveEvent::PotOffline {
+            pot_id,
+            last_seen_at: "x".repeat(10_000),
+        };
+        publish_in_tx(&mut tx, oversized).await.expect("savepoint");
+        tx.commit().await.expect("commit");
+
+        let pots = sqlx::query_scalar!("SELECT count(*) FROM pot WHERE id = $1", pot_id)
+            .fetch_one(&pool)
+            .await
+            .expect("count");
+        assert_eq!(pots, Some(1));
+    }
+}
@@ -0,0 +1,259 @@
+//! Tracks when pots were last heard from. Every measurement and heartbeat
+//! counts as a sign of life; a background sweeper reports pots that stay
+//! silent for too long.
+
+use std::time::Duration;
+
+use log::{error, info};
+use sqlx::{
+    Pool, Postgres,
+    types::chrono::{DateTime, Utc},
+};
+
+use crate::{
+    entities::Pot,
+    services::{
+        error::ServiceError,
+        events::{self, LiveEvent},
+    },
+};
+
+pub const SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// A pot is stale once it missed a report, allowing for some delay...
+pub const STALE_AFTER_INTERVALS: f64 = 1.5;
+/// ...and offline, which raises an alert, after missing a few more.
+pub const OFFLINE_AFTER_INTERVALS: f64 = 3.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Connectivity {
+    Online,
+    Stale,
+    Offline,
+}
+
+impl Connectivity {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Connectivity::Online => "online",
+            Connectivity::Stale => "stale",
+            Connectivity::Offline => "offline",
+        }
+    }
+}
+
+/// Classifies the pot by how long ago it was last seen, relative to its
+/// report interval. Pots that were never seen are offline.
+pub fn connectivity(pot: &Pot, now: DateTime<Utc>) -> Connectivity {
+    let Some(last_seen_at) = pot.last_seen_at else {
+        return Connectivity::Offline;
+    };
+    let silent_intervals =
+        (now - last_seen_at).as_seconds_f64() / pot.report_interval_seconds as f64;
+
+    if silent_intervals <= STALE_AFTER_INTERVALS {
+        Connectivity::Online
+    } else if silent_intervals <= OFFLINE_AFTER_INTERVALS {
+        Connectivity::Stale
+    } else {
+        Connectivity::Offline
+    }
+}
+
+/// What a device may report along with a heartbeat.
+#[derive(Clone, Default)]
+pub struct Heartbeat {
+    pub battery_level: Option<i32>,
+    pub firmware_version: Option<String>,
+}
+
+/// Marks the pot as seen now. A pot that was reported offline is announced
+/// as back online.
+pub async fn record_heartbeat(
+    pool: &Pool<Postgres>,
+    pot_id: i32,
+    heartbeat: &Heartbeat,
+) -> Result<(), ServiceError> {
+    if let Some(battery_level) = heartbeat.battery_level
+        && !(0..=100).contains(&battery_level)
+    {
+        return Err(ServiceError::validation(
+            "batteryLevel",
+            "must be between 0 and 100",
+        ));
+    }
+    let firmware_version = heartbeat
+        .firmware_version
+        .as_deref()
+        .map(str::trim)
+        .filter(|version| !version.is_empty());
+
+    let was_offline = sqlx::query_scalar!(
+        r#"UPDATE pot SET last_seen_at = now(),
+            battery_level = COALESCE($2, pot.battery_level),
+            firmware_version = COALESCE($3, pot.firmware_version),
+            offline_since = NULL
+        FROM (SELECT id, offline_since FROM pot WHERE id = $1 FOR UPDATE) previous
+        WHERE pot.id = previous.id
+        RETURNING previous.offline_since IS NOT NULL AS "was_offline!""#,
+        pot_id,
+        heartbeat.battery_level,
+        firmware_version,
+    )
+    .fetch_optional(pool)
+    .await?
+    .ok_or(ServiceError::NotFound("Pot"))?;
+
+    if was_offline {
+        events::publish(pool, LiveEvent::PotOnline { pot_id }).await;
+    }
+
+    Ok(())
+}
+
+/// Reports active pots that have been silent for `OFFLINE_AFTER_INTERVALS`
+/// report intervals. Each outage is reported once. Returns the number of
+/// pots reported.
+pub async fn sweep_offline_pots(pool: &Pool<Postgres>) -> Result<usize, ServiceError> {
+    let pots = sqlx::query!(
+        r#"UPDATE pot SET offline_since = now()
+        WHERE archived_at IS NULL
+        AND status = 'active'
+        AND offline_since IS NULL
+        AND last_seen_at < now() - make_interval(secs => report_interval_seconds * $1::float8)
+        RETURNING id, last_seen_at AS "last_seen_at!""#,
+        OFFLINE_AFTER_INTERVALS,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    for pot in &pots {
+        events::publish(
+            pool,
+            LiveEvent::PotOffline {
+                pot_id: pot.id,
+                last_seen_at: pot.last_seen_at.to_rfc3339(),
+            },
+        )
+        .await;
+    }
+
+    Ok(pots.len())
+}
+
+pub fn spawn_sweeper(pool: Pool<Postgres>, interval: Duration) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            match sweep_offline_pots(&pool).await {
+                Ok(0) => {}
+                Ok(count) => info!("{} pots went offline", count),
+                Err(e) => error!("Failed to look for offline pots: {}", e),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::{PgPool, types::chrono::TimeZone};
+
+    use super::*;
+    use crate::services::pot::{self, PotRegistration};
+
+    fn seen(seconds_ago: i64) -> Pot {
+        let now = Utc.with_ymd_and_hms(2026, 5, 1, 12, 0, 0).unwrap();
+        Pot {
+            id: 1,
+            serial_number: None,
+            name: None,
+            model: None,
+            firmware_version: None,
+            status: Default::default(),
+            created_at: now,
+            updated_at: now,
+            archived_at: None,
+            last_seen_at: Some(now - Duration::from_secs(seconds_ago as u64)),
+            battery_level: None,
+            report_interval_seconds: 600,
+            offline_since: None,
+        }
+    }
+
+    #[test]
+    fn classifies_by_missed_reports() {
+        let now = Utc.with_ymd_and_hms(2026, 5, 1, 12, 0, 0).unwrap();
+
+        assert_eq!(connectivity(&seen(0), now), Connectivity::Online);
+        assert_eq!(connectivity(&seen(900), now), Connectivity::Online);
+        assert_eq!(connectivity(&seen(901), now), Connectivity::Stale);
+        assert_eq!(connectivity(&seen(1800), now), Connectivity::Stale);
+        assert_eq!(connectivity(&seen(1801), now), Connectivity::Offline);
+
+        let never = Pot {
+            last_seen_at: None,
+            ..seen(0)
+        };
+        assert_eq!(connectivity(&never, now), Connectivity::Offline);
+    }
+
+    #[sqlx::test]
+    async fn silent_pots_are_reported_once(pool: PgPool) {
+        let user_id = sqlx::query_scalar!(
+            r#"INSERT INTO "user" (email, password_hash) VALUES ('a@example.com', 'x') RETURNING id"#
+        )
+        .fetch_one(&pool)
+        .await
+        .expect("user");
+        let (pot, _) = pot::create_pot(&pool, user_id, &PotRegistration::default())
+            .await
+            .expect("pot");
+
+        // Never seen, nothing to compare against.
+        assert_eq!(sweep_offline_pots(&pool).await.expect("sweep"), 0);
+
+        let heartbeat = Heartbeat {
+            battery_level: Some(80),
+            firmware_version: Some("1.2.0".to_string()),
+        };
+        record_heartbeat(&pool, pot.id, &heartbeat)
+            .await
+            .expect("heartbeat");
+        let seen = pot::get_pot(&pool, user_id, pot.id).await.expect("pot");
+        assert_eq!(seen.battery_level, Some(80));
+        assert_eq!(seen.firmware_version.as_deref(), Some("1.2.0"));
+        assert_eq!(connectivity(&seen, Utc::now()), Connectivity::Online);
+        assert_eq!(sweep_offline_pots(&pool).await.expect("sweep"), 0);
+
+        sqlx::query!(
+            "UPDATE pot SET last_seen_at = now() - interval '1 day' WHERE id = $1",
+            pot.id
+        )
+        .execute(&pool)
+        .await
+        .expect("age");
+        assert_eq!(sweep_offline_pots(&pool).await.expect("sweep"), 1);
+        assert_eq!(sweep_offline_pots(&pool).await.expect("sweep"), 0);
+
+        record_heartbeat(&pool, pot.id, &Heartbeat::default())
+            .await
+            .expect("heartbeat");
+        let back = pot::get_pot(&pool, user_id, pot.id).await.expect("pot");
+        assert_eq!(back.offline_since, None);
+        assert_eq!(back.battery_level, Some(80));
+
+        assert!(matches!(
+            record_heartbeat(
+                &pool,
+                pot.id,
+                &Heartbeat {
+                    battery_level: Some(101),
+                    ..Heartbeat::default()
+                }
+            )
+            .await,
+            Err(ServiceError::Validation(_))
+        ));
+    }
+}
@@ -0,0 +1,441 @@
+//! Species identification from a photo. The provider is picked on startup by
+//! `IDENTIFIER_BACKEND`:
+//!
+//! - `none` (default) turns identification off, requests fail with 503.
+//! - `stub` answers deterministically from the bundled catalog without
+//!   looking at the picture, for development and tests.
+//! - `http` posts the image to `IDENTIFIER_URL` with its content type and,
+//!   if `IDENTIFIER_API_KEY` is set, a bearer token. The provider answers
+//!   with `{"candidates": [{"scientificName", "commonName", "confidence"}]}`,
+//!   where `scientificName` may be `null` or `"unknown"`.
+
+use std::{sync::Arc, time::Duration};
+
+use anyhow::{Context, bail};
+use async_trait::async_trait;
+use bytes::Bytes;
+use log::error;
+use reqwest::header;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use sqlx::{Pool, Postgres};
+
+use crate::{
+    entities::PlantType,
+    services::{
+        error::ServiceError,
+        photo::{self, MAX_PHOTO_BYTES},
+        plant_type,
+    },
+};
+
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// At most this many candidates are returned, best first.
+pub const MAX_CANDIDATES: usize = 5;
+
+/// A catalog match below this confidence still leaves the plant unknown.
+pub const MIN_CONFIDENCE: f32 = 0.2;
+
+/// A species guess as reported by the provider.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Candidate {
+    pub scientific_name: Option<String>,
+    pub common_name: Option<String>,
+    pub confidence: f32,
+}
+
+#[async_trait]
+pub trait PlantIdentifier: Send + Sync {
+    /// Ranks species that may be shown in the image. An empty list means the
+    /// provider does not know.
+    async fn identify(&self, image: Bytes, content_type: &str) -> anyhow::Result<Vec<Candidate>>;
+}
+
+pub fn from_env() -> anyhow::Result<Arc<dyn PlantIdentifier>> {
+    let backend = std::env::var("IDENTIFIER_BACKEND").unwrap_or_else(|_| "none".to_string());
+
+    match backend.as_str() {
+        "none" => Ok(Arc::new(DisabledIdentifier)),
+        "stub" => Ok(Arc::new(StubIdentifier::new(plant_type::catalog_names()?))),
+        "http" => Ok(Arc::new(HttpIdentifier::from_env()?)),
+        other => bail!("Unknown IDENTIFIER_BACKEND {other}, expected none, stub or http"),
+    }
+}
+
+pub struct DisabledIdentifier;
+
+#[async_trait]
+impl PlantIdentifier for DisabledIdentifier {
+    async fn identify(&self, _: Bytes, _: &str) -> anyhow::Result<Vec<Candidate>> {
+        bail!("Plant identification is not configured")
+    }
+}
+
+/// Picks up to three species from a fixed list based on a hash of the image,
+/// so the same image always gets the same answer. About one image in ten is
+/// not recognized.
+pub struct StubIdentifier {
+    species: Vec<String>,
+}
+
+impl StubIdentifier {
+    pub fn new(species: Vec<String>) -> Self {
+        StubIdentifier { species }
+    }
+}
+
+#[async_trait]
+impl PlantIdentifier for StubIdentifier {
+    async fn identify(&self, image: Bytes, _: &str) -> anyhow::Result<Vec<Candidate>> {
+        let hash = Sha256::digest(&image);
+        if self.species.is_empty() || hash[0] < 26 {
+            return Ok(Vec::new());
+        }
+
+        let start = u16::from_be_bytes([hash[1], hash[2]]) as usize;
+        let best = 0.5 + hash[3] as f32 / 510.0;
+        let confidences = [best, (1.0 - best) * 0.6, (1.0 - best) * 0.3];
+
+        Ok(confidences
+            .iter()
+            .take(self.species.len())
+            .enumerate()
+            .map(|(i, &confidence)| Candidate {
+                scientific_name: Some(self.species[(start + i) % self.species.len()].clone()),
+                common_name: None,
+                confidence,
+            })
+            .collect())
+    }
+}
+
+/// Talks to an external provider, see the module documentation.
+pub struct HttpIdentifier {
+    client: reqwest::Client,
+    url: String,
+    api_key: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct HttpResponse {
+    #[serde(default)]
+    candidates: Vec<Candidate>,
+}
+
+impl HttpIdentifier {
+    pub fn new(url: String, api_key: Option<String>, timeout: Duration) -> anyhow::Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(timeout)
+            .build()
+            .context("Could not create identification client")?;
+
+        Ok(HttpIdentifier {
+            client,
+            url,
+            api_key,
+        })
+    }
+
+    pub fn from_env() -> anyhow::Result<Self> {
+        let url = std::env::var("IDENTIFIER_URL").context("IDENTIFIER_URL must be set")?;
+        let api_key = std::env::var("IDENTIFIER_API_KEY").ok();
+        let timeout = match std::env::var("IDENTIFIER_TIMEOUT_SECONDS") {
+            Ok(seconds) => Duration::from_secs(seconds.parse()?),
+            Err(_) => DEFAULT_TIMEOUT,
+        };
+
+        Self::new(url, api_key, timeout)
+    }
+}
+
+#[async_trait]
+impl PlantIdentifier for HttpIdentifier {
+    async fn identify(&self, image: Bytes, content_type: &str) -> anyhow::Result<Vec<Candidate>> {
+        let mut request = self
+            .client
+            .post(&self.url)
+            .header(header::CONTENT_TYPE, content_type)
+            .body(image);
+        if let Some(api_key) = &self.api_key {
+            request = request.bearer_auth(api_key);
+        }
+
+        let response: HttpResponse = request
+            .send()
+            .await
+            .context("Identification request failed")?
+            .error_for_status()
+            .context("Identification request failed")?
+            .json()
+            .await
+            .context("Invalid identification response")?;
+
+        Ok(response.candidates)
+    }
+}
+
+/// A candidate mapped onto the species catalog.
+#[derive(Debug)]
+pub struct IdentifiedSpecies {
+    pub scientific_name: String,
+    pub common_name: Option<String>,
+    pub confidence: f32,
+    /// The catalog entry, if the species is in the catalog.
+    pub plant_type: Option<PlantType>,
+}
+
+#[derive(Debug)]
+pub struct Identification {
+    pub candidates: Vec<IdentifiedSpecies>,
+    /// No catalog species matched with at least `MIN_CONFIDENCE`.
+    pub unknown: bool,
+}
+
+/// Genus and species in lower case, so that authors and cultivars do not
+/// get in the way, e.g. `Epipremnum aureum 'Marble Queen'`.
+fn binomial(name: &str) -> String {
+    name.split_whitespace()
+        .take(2)
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// Empty names and `unknown` mean the provider had no name.
+fn known_name(name: Option<String>) -> Option<String> {
+    name.map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty() && !name.eq_ignore_ascii_case("unknown"))
+}
+
+/// Drops unnamed and malformed candidates, ranks the rest and looks each up
+/// in the catalog by scientific name, falling back to the common name.
+fn match_catalog(candidates: Vec<Candidate>, catalog: &[PlantType]) -> Identification {
+    let mut candidates: Vec<Candidate> = candidates
+        .into_iter()
+        .filter(|c| c.confidence.is_finite())
+        .filter_map(|c| {
+            Some(Candidate {
+                scientific_name: Some(known_name(c.scientific_name)?),
+                common_name: known_name(c.common_name),
+                confidence: c.confidence.clamp(0.0, 1.0),
+            })
+        })
+        .collect();
+    candidates.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+
+    let mut identified: Vec<IdentifiedSpecies> = Vec::new();
+    for candidate in candidates {
+        let scientific_name = candidate.scientific_name.unwrap_or_default();
+        let key = binomial(&scientific_name);
+        let plant_type = catalog
+            .iter()
+            .find(|t| binomial(&t.scientific_name) == key)
+            .or_else(|| {
+                let common_name = candidate.common_name.as_deref()?;
+                catalog
+                    .iter()
+                    .find(|t| t.common_name.eq_ignore_ascii_case(common_name))
+            });
+
+        // Only the best guess per species is kept.
+        let duplicate = identified
+            .iter()
+            .any(|other| match (&other.plant_type, plant_type) {
+                (Some(a), Some(b)) => a.id == b.id,
+                (None, None) => binomial(&other.scientific_name) == key,
+                _ => false,
+            });
+        if duplicate {
+            continue;
+        }
+
+        identified.push(IdentifiedSpecies {
+            scientific_name,
+            common_name: candidate.common_name,
+            confidence: candidate.confidence,
+            plant_type: plant_type.cloned(),
+        });
+    }
+    identified.truncate(MAX_CANDIDATES);
+
+    let unknown = !identified
+        .iter()
+        .any(|c| c.plant_type.is_some() && c.confidence >= MIN_CONFIDENCE);
+
+    Identification {
+        candidates: identified,
+        unknown,
+    }
+}
+
+pub async fn identify_plant(
+    pool: &Pool<Postgres>,
+    identifier: &dyn PlantIdentifier,
+    data: Bytes,
+) -> Result<Identification, ServiceError> {
+    if data.len() > MAX_PHOTO_BYTES {
+        return Err(ServiceError::validation(
+            "photo",
+            format!(
+                "must not be larger than {} MiB",
+                MAX_PHOTO_BYTES / 1024 / 1024
+            ),
+        ));
+    }
+
+    // Decoding is CPU bound, keep it off the async workers.
+    let image = data.clone();
+    let (format, _) = tokio::task::spawn_blocking(move || photo::decode(&image))
+        .await
+        .map_err(|e| ServiceError::Internal(e.into()))??;
+
+    let candidates = identifier
+        .identify(data, format.to_mime_type())
+        .await
+        .map_err(|e| {
+            error!("Plant identification failed: {e:#}");
+            ServiceError::Unavailable("Plant identification is unavailable".to_string())
+        })?;
+    let catalog = plant_type::get_plant_types(pool).await?;
+
+    Ok(match_catalog(candidates, &catalog))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use image::{ImageBuffer, ImageFormat, Rgb};
+    use sqlx::PgPool;
+
+    use super::*;
+
+    struct FixedIdentifier(Vec<Candidate>);
+
+    #[async_trait]
+    impl PlantIdentifier for FixedIdentifier {
+        async fn identify(&self, _: Bytes, content_type: &str) -> anyhow::Result<Vec<Candidate>> {
+            assert_eq!(content_type, "image/png");
+            Ok(self.0.clone())
+        }
+    }
+
+    fn candidate(scientific_name: &str, confidence: f32) -> Candidate {
+        Candidate {
+            scientific_name: Some(scientific_name.to_string()),
+            common_name: None,
+            confidence,
+        }
+    }
+
+    fn plant_type(id: i32, common_name: &str, scientific_name: &str) -> PlantType {
+        PlantType {
+            id,
+            common_name: common_name.to_string(),
+            scientific_name: scientific_name.to_string(),
+            watering_interval_days: 7,
+            fertilization_interval_days: 30,
+            moisture_range: (30.0, 60.0),
+            light_range: (1000.0, 5000.0),
+            temperature_range: (18.0, 26.0),
+        }
+    }
+
+    fn png() -> Bytes {
+        let image = ImageBuffer::from_pixel(32, 32, Rgb([40u8, 160, 60]));
+        let mut data = Cursor::new(Vec::new());
+        image.write_to(&mut data, ImageFormat::Png).expect("encode");
+        Bytes::from(data.into_inner())
+    }
+
+    #[test]
+    fn maps_candidates_onto_the_catalog() {
+        let catalog = [
+            plant_type(1, "Monstera", "Monstera deliciosa"),
+            plant_type(2, "Golden pothos", "Epipremnum aureum"),
+        ];
+        let identification = match_catalog(
+            vec![
+                candidate("Rosa canina", 0.1),
+                candidate("Monstera deliciosa Liebm.", 0.3),
+                Candidate {
+                    scientific_name: Some("Scindapsus aureus".to_string()),
+                    common_name: Some("golden POTHOS".to_string()),
+                    confidence: 0.6,
+                },
+                candidate("Epipremnum aureum 'Marble Queen'", 0.05),
+                candidate("unknown", 0.9),
+                Candidate::default(),
+                candidate("Ficus lyrata", f32::NAN),
+            ],
+            &catalog,
+        );
+
+        let ids: Vec<Option<i32>> = identification
+            .candidates
+            .iter()
+            .map(|c| c.plant_type.as_ref().map(|t| t.id))
+            .collect();
+        assert_eq!(ids, vec![Some(2), Some(1), None]);
+        assert_eq!(identification.candidates[0].confidence, 0.6);
+        assert_eq!(identification.candidates[2].scientific_name, "Rosa canina");
+        assert!(!identification.unknown);
+    }
+
+    #[test]
+    fn tolerates_unknown_species() {
+        let catalog = [plant_type(1, "Monstera", "Monstera deliciosa")];
+
+        let nothing = match_catalog(vec![candidate("Unknown", 1.0)], &catalog);
+        assert!(nothing.candidates.is_empty());
+        assert!(nothing.unknown);
+
+        let unsure = match_catalog(
+            vec![
+                candidate("Monstera deliciosa", 0.1),
+                candidate("Rosa canina", 2.0),
+            ],
+            &catalog,
+        );
+        assert_eq!(unsure.candidates[0].confidence, 1.0);
+        assert!(unsure.unknown);
+    }
+
+    #[tokio::test]
+    async fn stub_is_deterministic() {
+        let stub = StubIdentifier::new(vec!["A a".to_string(), "B b".to_string()]);
+        let first = stub.identify(png(), "image/png").await.expect("identify");
+        let second = stub.identify(png(), "image/png").await.expect("identify");
+
+        assert_eq!(first, second);
+        assert!(first.len() <= 2);
+        assert!(first.windows(2).all(|w| w[0].confidence > w[1].confidence));
+    }
+
+    #[sqlx::test]
+    async fn identifies_catalog_species(pool: PgPool) {
+        plant_type::seed_catalog(&pool).await.expect("seed");
+        let identifier = FixedIdentifier(vec![candidate("Monstera deliciosa", 0.8)]);
+
+        let identification = identify_plant(&pool, &identifier, png())
+            .await
+            .expect("identify");
+        let plant_type = identification.candidates[0]
+            .plant_type
+            .as_ref()
+            .expect("catalog match");
+        assert_eq!(plant_type.common_name, "Monstera");
+        assert!(!identification.unknown);
+
+        assert!(matches!(
+            identify_plant(&pool, &identifier, Bytes::from_static(b"not an image")).await,
+            Err(ServiceError::Validation(_))
+        ));
+        assert!(matches!(
+            identify_plant(&pool, &DisabledIdentifier, png()).await,
+            Err(ServiceError::Unavailable(_))
+        ));
+    }
+}
@@ -1,93 +1,864 @@
-use anyhow::Result;
-use sqlx::{Pool, Postgres};
+use std::collections::HashMap;
 
-pub async fn link_plant_to_pot(
-    pool: &Pool<Postgres>,
+use sqlx::{
+    PgConnection, Pool, Postgres,
+    types::chrono::{DateTime, Utc},
+};
+
+use crate::{
+    entities::{Plant, PlantDb, PlantPotAssignment, PlantPotAssignmentDb},
+    services::{
+        self,
+        error::ServiceError,
+        events::{self, LiveEvent},
+    },
+};
+
+fn pot_busy() -> ServiceError {
+    ServiceError::conflict("POT_ALREADY_LINKED", "Pot is already linked to a plant")
+}
+
+fn plant_busy() -> ServiceError {
+    ServiceError::conflict("PLANT_ALREADY_LINKED", "Plant is already linked to a pot")
+}
+
+/// Maps a violated assignment constraint onto the conflict it stands for.
+/// The checks before each write give the same answers, the constraints only
+/// catch what slips past them.
+fn link_error(e: sqlx::Error) -> ServiceError {
+    if let sqlx::Error::Database(db_err) = &e {
+        match db_err.constraint() {
+            Some(
+                "plant_pot_assignment_active_pot_key" | "plant_pot_assignment_pot_period_excl",
+            ) => {
+                return pot_busy();
+            }
+            Some(
+                "plant_pot_assignment_active_plant_key" | "plant_pot_assignment_plant_period_excl",
+            ) => return plant_busy(),
+            _ => {}
+        }
+    }
+    ServiceError::from(e)
+}
+
+/// Serializes the link changes of a user, whose plants and pots are the
+/// only ones they can touch, and returns the time the changes take effect.
+/// The time is taken after the lock, so it is never before a period another
+/// transaction opened. The user row is locked without its key, so rows
+/// referencing the user, like new plants and pots, are not held up.
+async fn lock_links(conn: &mut PgConnection, user_id: i32) -> Result<DateTime<Utc>, ServiceError> {
+    sqlx::query_scalar!(
+        r#"SELECT id FROM "user" WHERE id = $1 FOR NO KEY UPDATE"#,
+        user_id
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    let now = sqlx::query_scalar!(r#"SELECT clock_timestamp() AS "now!""#)
+        .fetch_one(conn)
+        .await?;
+
+    Ok(now)
+}
+
+async fn check_plant(
+    conn: &mut PgConnection,
     user_id: i32,
     plant_id: i32,
-    pot_id: i32,
-) -> Result<()> {
-    let _plant = sqlx::query!(
-        r#"SELECT * FROM plant
-        WHERE id = $1 AND owner_id = $2"#,
+) -> Result<(), ServiceError> {
+    sqlx::query_scalar!(
+        "SELECT id FROM plant WHERE id = $1 AND owner_id = $2",
         plant_id,
         user_id
     )
-    .fetch_optional(pool)
-    .await
-    .map_err(|e| anyhow::anyhow!("Failed to fetch plant: {}", e))?
-    .ok_or_else(|| anyhow::anyhow!("Plant not found"))?;
+    .fetch_optional(conn)
+    .await?
+    .ok_or(ServiceError::NotFound("Plant"))?;
 
-    let _pot = sqlx::query!(
-        r#"SELECT * FROM pot
-        WHERE id = $1 AND owner_id = $2"#,
+    Ok(())
+}
+
+/// Checks the pot can take a plant. The pot row stays locked so it cannot
+/// be archived in the meantime.
+async fn check_pot(conn: &mut PgConnection, user_id: i32, pot_id: i32) -> Result<(), ServiceError> {
+    let archived = sqlx::query_scalar!(
+        r#"SELECT archived_at IS NOT NULL AS "archived!" FROM pot
+        WHERE id = $1 AND owner_id = $2
+        FOR UPDATE"#,
         pot_id,
         user_id
     )
-    .fetch_optional(pool)
-    .await
-    .map_err(|e| anyhow::anyhow!("Failed to fetch pot: {}", e))?
-    .ok_or_else(|| anyhow::anyhow!("Pot not found"))?;
+    .fetch_optional(conn)
+    .await?
+    .ok_or(ServiceError::NotFound("Pot"))?;
+
+    if archived {
+        return Err(ServiceError::conflict(
+            "POT_ARCHIVED",
+            "Pot is unregistered and can no longer be linked",
+        ));
+    }
 
-    let pot_already_linked = sqlx::query!(
-        r#"SELECT * FROM plant_pot_assignment
-        WHERE pot_id = $1"#,
+    Ok(())
+}
+
+/// The plant currently in the pot.
+async fn plant_in_pot(conn: &mut PgConnection, pot_id: i32) -> Result<Option<i32>, ServiceError> {
+    let plant_id = sqlx::query_scalar!(
+        "SELECT plant_id FROM plant_pot_assignment WHERE pot_id = $1 AND unlinked_at IS NULL",
         pot_id
     )
-    .fetch_optional(pool)
-    .await
-    .map_err(|e| anyhow::anyhow!("Failed to check existing link: {}", e))?
-    .is_some();
+    .fetch_optional(conn)
+    .await?;
 
-    if pot_already_linked {
-        return Err(anyhow::anyhow!("Plant is already linked to a pot"));
-    }
+    Ok(plant_id)
+}
 
-    let plant_already_linked = sqlx::query!(
-        r#"SELECT * FROM plant_pot_assignment
-        WHERE plant_id = $1"#,
+/// The pot the plant is currently in.
+async fn pot_of_plant(conn: &mut PgConnection, plant_id: i32) -> Result<Option<i32>, ServiceError> {
+    let pot_id = sqlx::query_scalar!(
+        "SELECT pot_id FROM plant_pot_assignment WHERE plant_id = $1 AND unlinked_at IS NULL",
         plant_id
     )
-    .fetch_optional(pool)
-    .await
-    .map_err(|e| anyhow::anyhow!("Failed to check existing plant link: {}", e))?
-    .is_some();
+    .fetch_optional(conn)
+    .await?;
 
-    if plant_already_linked {
-        return Err(anyhow::anyhow!("Plant is already linked to a pot"));
-    }
+    Ok(pot_id)
+}
 
-    sqlx::query!(
-        r#"INSERT INTO plant_pot_assignment (plant_id, pot_id)
-        VALUES ($1, $2)"#,
+async fn open_period(
+    conn: &mut PgConnection,
+    plant_id: i32,
+    pot_id: i32,
+    notes: Option<&str>,
+    now: DateTime<Utc>,
+) -> Result<PlantPotAssignment, ServiceError> {
+    let assignment = sqlx::query_as!(
+        PlantPotAssignmentDb,
+        "INSERT INTO plant_pot_assignment (plant_id, pot_id, notes, linked_at)
+        VALUES ($1, $2, $3, $4)
+        RETURNING id, plant_id, pot_id, linked_at, unlinked_at, notes",
         plant_id,
         pot_id,
+        notes,
+        now,
     )
-    .execute(pool)
+    .fetch_one(&mut *conn)
     .await
-    .map_err(|e| anyhow::anyhow!("Failed to link plant to pot: {}", e))?;
+    .map_err(link_error)
+    .map(PlantPotAssignment::from)?;
+
+    events::publish_in_tx(conn, LiveEvent::Linked { pot_id, plant_id }).await?;
+
+    Ok(assignment)
+}
+
+pub async fn link_plant_to_pot(
+    pool: &Pool<Postgres>,
+    user_id: i32,
+    plant_id: i32,
+    pot_id: i32,
+) -> Result<(), ServiceError> {
+    create_link(pool, user_id, plant_id, pot_id, None).await?;
+
     Ok(())
 }
 
-pub async fn unlink_plant_from_pot(
+/// Puts the plant into the pot, both have to be free.
+pub async fn create_link(
     pool: &Pool<Postgres>,
     user_id: i32,
     plant_id: i32,
     pot_id: i32,
-) -> Result<()> {
+    notes: Option<&str>,
+) -> Result<PlantPotAssignment, ServiceError> {
+    let notes = clean_notes(notes);
+
+    let mut tx = pool.begin().await?;
+
+    let now = lock_links(&mut tx, user_id).await?;
+    check_plant(&mut tx, user_id, plant_id).await?;
+    check_pot(&mut tx, user_id, pot_id).await?;
+
+    if plant_in_pot(&mut tx, pot_id).await?.is_some() {
+        return Err(pot_busy());
+    }
+    if pot_of_plant(&mut tx, plant_id).await?.is_some() {
+        return Err(plant_busy());
+    }
+
+    let assignment = open_period(&mut tx, plant_id, pot_id, notes, now).await?;
+
+    tx.commit().await?;
+
+    Ok(assignment)
+}
+
+/// Moves the plant into the pot in one step, ending its period in its
+/// current pot. A pot holding another plant is refused unless `swap` is set,
+/// then that plant moves into the pot the plant came from. Returns the
+/// periods that were started.
+pub async fn switch_plant(
+    pool: &Pool<Postgres>,
+    user_id: i32,
+    plant_id: i32,
+    pot_id: i32,
+    swap: bool,
+) -> Result<Vec<PlantPotAssignment>, ServiceError> {
+    let mut tx = pool.begin().await?;
+
+    let now = lock_links(&mut tx, user_id).await?;
+    check_plant(&mut tx, user_id, plant_id).await?;
+    check_pot(&mut tx, user_id, pot_id).await?;
+
+    let from_pot_id = pot_of_plant(&mut tx, plant_id).await?;
+    if from_pot_id == Some(pot_id) {
+        return Err(plant_busy());
+    }
+
+    let other_plant_id = plant_in_pot(&mut tx, pot_id).await?;
+    let swapped = match (other_plant_id, from_pot_id) {
+        (None, _) => None,
+        (Some(_), _) if !swap => return Err(pot_busy()),
+        (Some(other_plant_id), Some(from_pot_id)) => Some((other_plant_id, from_pot_id)),
+        (Some(_), None) => {
+            return Err(ServiceError::validation(
+                "swap",
+                "the plant is not in a pot the other plant could move to",
+            ));
+        }
+    };
+
+    let closed = sqlx::query!(
+        "UPDATE plant_pot_assignment SET unlinked_at = $3
+        WHERE unlinked_at IS NULL AND (plant_id = $1 OR pot_id = $2)
+        RETURNING plant_id, pot_id",
+        plant_id,
+        pot_id,
+        now,
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+    for period in closed {
+        events::publish_in_tx(
+            &mut tx,
+            LiveEvent::Unlinked {
+                pot_id: period.pot_id,
+                plant_id: period.plant_id,
+            },
+        )
+        .await?;
+    }
+
+    let mut opened = vec![open_period(&mut tx, plant_id, pot_id, None, now).await?];
+    if let Some((other_plant_id, from_pot_id)) = swapped {
+        opened.push(open_period(&mut tx, other_plant_id, from_pot_id, None, now).await?);
+    }
+
+    tx.commit().await?;
+
+    Ok(opened)
+}
+
+/// Turns blank notes into missing ones.
+fn clean_notes(notes: Option<&str>) -> Option<&str> {
+    notes.map(str::trim).filter(|notes| !notes.is_empty())
+}
+
+fn link_ended() -> ServiceError {
+    ServiceError::conflict("LINK_ENDED", "The plant was already taken out of the pot")
+}
+
+/// Returns a link of the user, current or ended.
+pub async fn get_link(
+    pool: &Pool<Postgres>,
+    user_id: i32,
+    link_id: i32,
+) -> Result<PlantPotAssignment, ServiceError> {
+    sqlx::query_as!(
+        PlantPotAssignmentDb,
+        "SELECT a.id, a.plant_id, a.pot_id, a.linked_at, a.unlinked_at, a.notes
+        FROM plant_pot_assignment a
+        JOIN pot ON pot.id = a.pot_id
+        WHERE a.id = $1 AND pot.owner_id = $2",
+        link_id,
+        user_id
+    )
+    .fetch_optional(pool)
+    .await?
+    .map(PlantPotAssignment::from)
+    .ok_or(ServiceError::NotFound("Link"))
+}
+
+/// Returns the current link of a pot of the user.
+pub async fn get_current_link(
+    pool: &Pool<Postgres>,
+    user_id: i32,
+    pot_id: i32,
+) -> Result<PlantPotAssignment, ServiceError> {
+    services::pot::get_pot(pool, user_id, pot_id).await?;
+
+    sqlx::query_as!(
+        PlantPotAssignmentDb,
+        "SELECT id, plant_id, pot_id, linked_at, unlinked_at, notes
+        FROM plant_pot_assignment
+        WHERE pot_id = $1 AND unlinked_at IS NULL",
+        pot_id
+    )
+    .fetch_optional(pool)
+    .await?
+    .map(PlantPotAssignment::from)
+    .ok_or(ServiceError::NotFound("Link"))
+}
+
+/// Changes the notes of a link and, while it is current, the plant in the
+/// pot. Putting another plant in ends the link and starts a new one, which
+/// is returned.
+pub async fn update_link(
+    pool: &Pool<Postgres>,
+    user_id: i32,
+    link_id: i32,
+    plant_id: i32,
+    notes: Option<&str>,
+) -> Result<PlantPotAssignment, ServiceError> {
+    let notes = clean_notes(notes);
+
+    let mut tx = pool.begin().await?;
+
+    let now = lock_links(&mut tx, user_id).await?;
+    let link = sqlx::query_as!(
+        PlantPotAssignmentDb,
+        "SELECT a.id, a.plant_id, a.pot_id, a.linked_at, a.unlinked_at, a.notes
+        FROM plant_pot_assignment a
+        JOIN pot ON pot.id = a.pot_id
+        WHERE a.id = $1 AND pot.owner_id = $2",
+        link_id,
+        user_id
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or(ServiceError::NotFound("Link"))?;
+
+    if link.plant_id == plant_id {
+        let link = sqlx::query_as!(
+            PlantPotAssignmentDb,
+            "UPDATE plant_pot_assignment SET notes = $2
+            WHERE id = $1
+            RETURNING id, plant_id, pot_id, linked_at, unlinked_at, notes",
+            link_id,
+            notes
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map(PlantPotAssignment::from)?;
+
+        tx.commit().await?;
+
+        return Ok(link);
+    }
+
+    if link.unlinked_at.is_some() {
+        return Err(link_ended());
+    }
+    check_plant(&mut tx, user_id, plant_id).await?;
+    check_pot(&mut tx, user_id, link.pot_id).await?;
+    if pot_of_plant(&mut tx, plant_id).await?.is_some() {
+        return Err(plant_busy());
+    }
+
     sqlx::query!(
-        r#"DELETE FROM plant_pot_assignment
-        USING pot
+        "UPDATE plant_pot_assignment SET unlinked_at = $2 WHERE id = $1",
+        link_id,
+        now
+    )
+    .execute(&mut *tx)
+    .await?;
+    events::publish_in_tx(
+        &mut tx,
+        LiveEvent::Unlinked {
+            pot_id: link.pot_id,
+            plant_id: link.plant_id,
+        },
+    )
+    .await?;
+
+    let link = open_period(&mut tx, plant_id, link.pot_id, notes, now).await?;
+
+    tx.commit().await?;
+
+    Ok(link)
+}
+
+/// Takes the plant out of the pot, the link stays in the history.
+pub async fn delete_link(
+    pool: &Pool<Postgres>,
+    user_id: i32,
+    link_id: i32,
+) -> Result<(), ServiceError> {
+    let link = get_link(pool, user_id, link_id).await?;
+    if link.unlinked_at.is_some() {
+        return Err(link_ended());
+    }
+
+    unlink_plant_from_pot(pool, user_id, link.plant_id, link.pot_id).await
+}
+
+/// Returns the plants currently in the user's pots, by pot id.
+pub async fn get_current_plants(
+    pool: &Pool<Postgres>,
+    user_id: i32,
+) -> Result<HashMap<i32, Plant>, ServiceError> {
+    let links = sqlx::query!(
+        "SELECT a.pot_id, a.plant_id FROM plant_pot_assignment a
+        JOIN pot ON pot.id = a.pot_id
+        WHERE pot.owner_id = $1 AND a.unlinked_at IS NULL",
+        user_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let plant_ids: Vec<i32> = links.iter().map(|link| link.plant_id).collect();
+    let mut plants: HashMap<i32, Plant> = sqlx::query_as!(
+        PlantDb,
+        "SELECT * FROM plant WHERE id = ANY($1)",
+        &plant_ids
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|plant| (plant.id, Plant::from(plant)))
+    .collect();
+
+    Ok(links
+        .into_iter()
+        .filter_map(|link| Some((link.pot_id, plants.remove(&link.plant_id)?)))
+        .collect())
+}
+
+/// Returns the plant currently in the pot.
+pub async fn get_current_plant(
+    pool: &Pool<Postgres>,
+    pot_id: i32,
+) -> Result<Option<Plant>, ServiceError> {
+    let plant = sqlx::query_as!(
+        PlantDb,
+        "SELECT plant.* FROM plant
+        JOIN plant_pot_assignment a ON a.plant_id = plant.id
+        WHERE a.pot_id = $1 AND a.unlinked_at IS NULL",
+        pot_id
+    )
+    .fetch_optional(pool)
+    .await?
+    .map(Plant::from);
+
+    Ok(plant)
+}
+
+/// Ends the current period of the plant in the pot, it stays in the history.
+pub async fn unlink_plant_from_pot(
+    pool: &Pool<Postgres>,
+    user_id: i32,
+    plant_id: i32,
+    pot_id: i32,
+) -> Result<(), ServiceError> {
+    let result = sqlx::query!(
+        r#"UPDATE plant_pot_assignment SET unlinked_at = clock_timestamp()
+        FROM pot
         WHERE plant_pot_assignment.pot_id = pot.id
         AND plant_pot_assignment.plant_id = $1
         AND plant_pot_assignment.pot_id = $2
+        AND plant_pot_assignment.unlinked_at IS NULL
         AND pot.owner_id = $3"#,
         plant_id,
         pot_id,
         user_id
     )
     .execute(pool)
-    .await
-    .map_err(|e| anyhow::anyhow!("Failed to unlink plant from pot: {}", e))?;
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(ServiceError::NotFound("Link"));
+    }
+
+    events::publish(pool, LiveEvent::Unlinked { pot_id, plant_id }).await;
+
     Ok(())
 }
+
+/// Returns every pot the plant was in, most recent first.
+pub async fn get_plant_history(
+    pool: &Pool<Postgres>,
+    user_id: i32,
+    plant_id: i32,
+) -> Result<Vec<PlantPotAssignment>, ServiceError> {
+    sqlx::query_scalar!(
+        "SELECT id FROM plant WHERE id = $1 AND owner_id = $2",
+        plant_id,
+        user_id
+    )
+    .fetch_optional(pool)
+    .await?
+    .ok_or(ServiceError::NotFound("Plant"))?;
+
+    let history = sqlx::query_as!(
+        PlantPotAssignmentDb,
+        "SELECT id, plant_id, pot_id, linked_at, unlinked_at, notes FROM plant_pot_assignment
+        WHERE plant_id = $1
+        ORDER BY linked_at DESC, id DESC",
+        plant_id
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(PlantPotAssignment::from)
+    .collect();
+
+    Ok(history)
+}
+
+/// Returns every plant that was in the pot, most recent first.
+pub async fn get_pot_history(
+    pool: &Pool<Postgres>,
+    user_id: i32,
+    pot_id: i32,
+) -> Result<Vec<PlantPotAssignment>, ServiceError> {
+    sqlx::query_scalar!(
+        "SELECT id FROM pot WHERE id = $1 AND owner_id = $2",
+        pot_id,
+        user_id
+    )
+    .fetch_optional(pool)
+    .await?
+    .ok_or(ServiceError::NotFound("Pot"))?;
+
+    let history = sqlx::query_as!(
+        PlantPotAssignmentDb,
+        "SELECT id, plant_id, pot_id, linked_at, unlinked_at, notes FROM plant_pot_assignment
+        WHERE pot_id = $1
+        ORDER BY linked_at DESC, id DESC",
+        pot_id
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(PlantPotAssignment::from)
+    .collect();
+
+    Ok(history)
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::{PgPool, types::chrono::NaiveDateTime};
+
+    use super::*;
+    use crate::services::{
+        measurement::{self, MeasurementFilter},
+        plant::{self, PlantInput},
+        plausibility::PlausibilityLimits,
+        pot::{self, PotRegistration},
+    };
+
+    fn at(value: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M").expect("timestamp")
+    }
+
+    #[sqlx::test]
+    async fn unlinking_keeps_the_history(pool: PgPool) {
+        let user_id = sqlx::query_scalar!(
+            r#"INSERT INTO "user" (email, password_hash) VALUES ('a@example.com', 'x') RETURNING id"#
+        )
+        .fetch_one(&pool)
+        .await
+        .expect("user");
+        let monstera = plant::create_plant(&pool, &PlantInput::new("Monstera"), user_id)
+            .await
+            .expect("plant");
+        let ficus = plant::create_plant(&pool, &PlantInput::new("Ficus"), user_id)
+            .await
+            .expect("plant");
+        let (pot, _) = pot::create_pot(&pool, user_id, &PotRegistration::default())
+            .await
+            .expect("pot");
+
+        link_plant_to_pot(&pool, user_id, monstera.id, pot.id)
+            .await
+            .expect("link");
+        unlink_plant_from_pot(&pool, user_id, monstera.id, pot.id)
+            .await
+            .expect("unlink");
+        // Gone from the current links, so it can be unlinked only once.
+        assert!(matches!(
+            unlink_plant_from_pot(&pool, user_id, monstera.id, pot.id).await,
+            Err(ServiceError::NotFound("Link"))
+        ));
+        link_plant_to_pot(&pool, user_id, ficus.id, pot.id)
+            .await
+            .expect("link");
+
+        // Move the periods to known times: the monstera in January, the
+        // ficus from February on.
+        sqlx::query!(
+            "UPDATE plant_pot_assignment SET linked_at = '2026-01-01Z', unlinked_at = '2026-02-01Z'
+            WHERE plant_id = $1",
+            monstera.id
+        )
+        .execute(&pool)
+        .await
+        .expect("period");
+        sqlx::query!(
+            "UPDATE plant_pot_assignment SET linked_at = '2026-02-01Z' WHERE plant_id = $1",
+            ficus.id
+        )
+        .execute(&pool)
+        .await
+        .expect("period");
+
+        for timestamp in ["2026-01-15 12:00", "2026-02-15 12:00"] {
+            measurement::create_measurement(
+                &pool,
+                &PlausibilityLimits::without_age_limit(),
+                pot.id,
+                40.0,
+                20.0,
+                300.0,
+                50.0,
+                90,
+                at(timestamp),
+            )
+            .await
+            .expect("measurement");
+        }
+
+        let filter = MeasurementFilter {
+            plant_id: Some(monstera.id),
+            ..MeasurementFilter::default()
+        };
+        let page = measurement::get_measurements(&pool, user_id, &filter)
+            .await
+            .expect("measurements");
+        assert_eq!(page.total, 1);
+        assert_eq!(page.measurements[0].timestamp, at("2026-01-15 12:00"));
+        assert_eq!(page.measurements[0].plant_id, Some(monstera.id));
+
+        let history = get_plant_history(&pool, user_id, monstera.id)
+            .await
+            .expect("history");
+        assert_eq!(history.len(), 1);
+        assert!(history[0].unlinked_at.is_some());
+
+        let history = get_pot_history(&pool, user_id, pot.id)
+            .await
+            .expect("history");
+        let plants: Vec<i32> = history.iter().map(|a| a.plant_id).collect();
+        assert_eq!(plants, vec![ficus.id, monstera.id]);
+        assert_eq!(history[0].unlinked_at, None);
+    }
+
+    #[sqlx::test]
+    async fn switching_moves_or_swaps_plants(pool: PgPool) {
+        let user_id = sqlx::query_scalar!(
+            r#"INSERT INTO "user" (email, password_hash) VALUES ('a@example.com', 'x') RETURNING id"#
+        )
+        .fetch_one(&pool)
+        .await
+        .expect("user");
+        let monstera = plant::create_plant(&pool, &PlantInput::new("Monstera"), user_id)
+            .await
+            .expect("plant");
+        let ficus = plant::create_plant(&pool, &PlantInput::new("Ficus"), user_id)
+            .await
+            .expect("plant");
+        let mut pots = Vec::new();
+        for _ in 0..3 {
+            let (pot, _) = pot::create_pot(&pool, user_id, &PotRegistration::default())
+                .await
+                .expect("pot");
+            pots.push(pot.id);
+        }
+
+        link_plant_to_pot(&pool, user_id, monstera.id, pots[0])
+            .await
+            .expect("link");
+        link_plant_to_pot(&pool, user_id, ficus.id, pots[1])
+            .await
+            .expect("link");
+        assert!(matches!(
+            link_plant_to_pot(&pool, user_id, monstera.id, pots[2]).await,
+            Err(ServiceError::Conflict {
+                code: "PLANT_ALREADY_LINKED",
+                ..
+            })
+        ));
+        assert!(matches!(
+            link_plant_to_pot(&pool, user_id, monstera.id, pots[1]).await,
+            Err(ServiceError::Conflict {
+                code: "POT_ALREADY_LINKED",
+                ..
+            })
+        ));
+
+        // Into an empty pot.
+        let opened = switch_plant(&pool, user_id, monstera.id, pots[2], false)
+            .await
+            .expect("switch");
+        assert_eq!(opened.len(), 1);
+        assert_eq!(opened[0].pot_id, pots[2]);
+
+        // Into a taken pot.
+        assert!(matches!(
+            switch_plant(&pool, user_id, monstera.id, pots[1], false).await,
+            Err(ServiceError::Conflict {
+                code: "POT_ALREADY_LINKED",
+                ..
+            })
+        ));
+        let opened = switch_plant(&pool, user_id, monstera.id, pots[1], true)
+            .await
+            .expect("swap");
+        let links: Vec<(i32, i32)> = opened.iter().map(|a| (a.plant_id, a.pot_id)).collect();
+        assert_eq!(links, vec![(monstera.id, pots[1]), (ficus.id, pots[2])]);
+
+        let history = get_plant_history(&pool, user_id, monstera.id)
+            .await
+            .expect("history");
+        let pot_ids: Vec<i32> = history.iter().map(|a| a.pot_id).collect();
+        assert_eq!(pot_ids, vec![pots[1], pots[2], pots[0]]);
+        assert_eq!(history[1].unlinked_at, Some(history[0].linked_at));
+    }
+
+    #[sqlx::test]
+    async fn constraints_reject_double_links(pool: PgPool) {
+        let user_id = sqlx::query_scalar!(
+            r#"INSERT INTO "user" (email, password_hash) VALUES ('a@example.com', 'x') RETURNING id"#
+        )
+        .fetch_one(&pool)
+        .await
+        .expect("user");
+        let plant = plant::create_plant(&pool, &PlantInput::new("Monstera"), user_id)
+            .await
+            .expect("plant");
+        let other = plant::create_plant(&pool, &PlantInput::new("Ficus"), user_id)
+            .await
+            .expect("plant");
+        let (pot, _) = pot::create_pot(&pool, user_id, &PotRegistration::default())
+            .await
+            .expect("pot");
+        link_plant_to_pot(&pool, user_id, plant.id, pot.id)
+            .await
+            .expect("link");
+
+        // What a concurrent request that passed the checks would insert.
+        let result = sqlx::query!(
+            "INSERT INTO plant_pot_assignment (plant_id, pot_id) VALUES ($1, $2)",
+            other.id,
+            pot.id
+        )
+        .execute(&pool)
+        .await
+        .map_err(link_error);
+        assert!(matches!(
+            result,
+            Err(ServiceError::Conflict {
+                code: "POT_ALREADY_LINKED",
+                ..
+            })
+        ));
+
+        // Periods in the past must not overlap either.
+        unlink_plant_from_pot(&pool, user_id, plant.id, pot.id)
+            .await
+            .expect("unlink");
+        let result = sqlx::query!(
+            "INSERT INTO plant_pot_assignment (plant_id, pot_id, linked_at, unlinked_at)
+            VALUES ($1, $2, now() - interval '2 days', clock_timestamp())",
+            other.id,
+            pot.id
+        )
+        .execute(&pool)
+        .await
+        .map_err(link_error);
+        assert!(matches!(
+            result,
+            Err(ServiceError::Conflict {
+                code: "POT_ALREADY_LINKED",
+                ..
+            })
+        ));
+    }
+
+    #[sqlx::test]
+    async fn links_can_be_edited_until_they_end(pool: PgPool) {
+        let user_id = sqlx::query_scalar!(
+            r#"INSERT INTO "user" (email, password_hash) VALUES ('a@example.com', 'x') RETURNING id"#
+        )
+        .fetch_one(&pool)
+        .await
+        .expect("user");
+        let monstera = plant::create_plant(&pool, &PlantInput::new("Monstera"), user_id)
+            .await
+            .expect("plant");
+        let ficus = plant::create_plant(&pool, &PlantInput::new("Ficus"), user_id)
+            .await
+            .expect("plant");
+        let (pot, _) = pot::create_pot(&pool, user_id, &PotRegistration::default())
+            .await
+            .expect("pot");
+
+        let link = create_link(&pool, user_id, monstera.id, pot.id, Some(" Repotted "))
+            .await
+            .expect("link");
+        assert_eq!(link.notes.as_deref(), Some("Repotted"));
+
+        let same = update_link(&pool, user_id, link.id, monstera.id, Some("New soil"))
+            .await
+            .expect("notes");
+        assert_eq!(same.id, link.id);
+        assert_eq!(same.notes.as_deref(), Some("New soil"));
+
+        let replaced = update_link(&pool, user_id, link.id, ficus.id, None)
+            .await
+            .expect("plant");
+        assert_ne!(replaced.id, link.id);
+        assert_eq!(replaced.plant_id, ficus.id);
+        let current = get_current_plants(&pool, user_id).await.expect("current");
+        assert_eq!(current.get(&pot.id).map(|plant| plant.id), Some(ficus.id));
+
+        // The ended link keeps its notes editable but not its plant.
+        assert!(matches!(
+            update_link(&pool, user_id, link.id, ficus.id, None).await,
+            Err(ServiceError::Conflict {
+                code: "LINK_ENDED",
+                ..
+            })
+        ));
+        update_link(&pool, user_id, link.id, monstera.id, None)
+            .await
+            .expect("notes");
+        assert!(matches!(
+            delete_link(&pool, user_id, link.id).await,
+            Err(ServiceError::Conflict {
+                code: "LINK_ENDED",
+                ..
+            })
+        ));
+
+        delete_link(&pool, user_id, replaced.id)
+            .await
+            .expect("delete");
+        assert!(
+            get_current_plant(&pool, pot.id)
+                .await
+                .expect("current")
+                .is_none()
+        );
+        assert!(
+            get_link(&pool, user_id, replaced.id)
+                .await
+                .expect("link")
+                .unlinked_at
+                .is_some()
+        );
+    }
+}
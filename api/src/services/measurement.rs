@@ -1,10 +1,30 @@
-use crate::entities::{Measurement, MeasurementDb};
-use anyhow::{Result, anyhow};
-use log::error;
-use sqlx::{Pool, Postgres, types::chrono::NaiveDateTime};
+use crate::{
+    entities::{Measurement, MeasurementDb, QuarantinedMeasurement, QuarantinedMeasurementDb},
+    services::{
+        automation,
+        error::ServiceError,
+        events::{self, LiveEvent},
+        heartbeat::{self, Heartbeat},
+        plausibility::{PlausibilityLimits, Reading},
+    },
+};
+use log::{error, warn};
+use sqlx::{
+    Pool, Postgres,
+    types::chrono::{DateTime, NaiveDateTime},
+};
 
+pub const DEFAULT_LIMIT: i64 = 100;
+pub const MAX_LIMIT: i64 = 1000;
+
+/// Records a reading of a pot. Implausible readings are stored but
+/// quarantined: they are left out of every series and rollup, trigger no
+/// task rules and no live event. Returns the measurement and the reasons it
+/// was quarantined for, empty if it was not.
+#[allow(clippy::too_many_arguments)]
 pub async fn create_measurement(
     pool: &Pool<Postgres>,
+    limits: &PlausibilityLimits,
     pot: i32,
     moisture: f32,
     temperature: f32,
@@ -12,10 +32,30 @@ pub async fn create_measurement(
     humidity: f32,
     battery_level: i32,
     timestamp: NaiveDateTime,
-) -> Result<Measurement> {
-    sqlx::query_as!(
+) -> Result<(Measurement, Vec<String>), ServiceError> {
+    let reasons = limits.check(&Reading {
+        soil_moisture: moisture,
+        temperature,
+        light_level,
+        humidity,
+        battery_level,
+        timestamp,
+    })?;
+
+    let mut tx = pool.begin().await?;
+
+    let measurement = sqlx::query_as!(
         MeasurementDb,
-        "INSERT INTO measurement (pot_id, soil_moisture, temperature, light_level, humidity, battery_level, timestamp) VALUES ($1, $2, $3, $4, $5, $6, $7) RETURNING *",
+        r#"INSERT INTO measurement (pot_id, soil_moisture, temperature, light_level, humidity, battery_level, timestamp)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        RETURNING id, pot_id,
+            (SELECT plant_id FROM plant_pot_assignment a
+                WHERE a.pot_id = measurement.pot_id
+                AND a.linked_at <= measurement.timestamp AT TIME ZONE 'UTC'
+                AND (a.unlinked_at IS NULL OR a.unlinked_at > measurement.timestamp AT TIME ZONE 'UTC')
+                ORDER BY a.linked_at DESC
+                LIMIT 1) AS "plant_id?",
+            timestamp, soil_moisture, temperature, light_level, humidity, battery_level"#,
         pot,
         moisture,
         temperature,
@@ -24,32 +64,656 @@ pub async fn create_measurement(
         battery_level,
         timestamp
     )
-    .fetch_one(pool)
+    .fetch_one(&mut *tx)
     .await
-    .map_err(|e| {
-        error!("{e}");
-        anyhow!(e)
-    })
-    .map(Measurement::from)
+    .map(Measurement::from)?;
+
+    // In the same transaction, so the rollups never see the reading
+    // without its quarantine.
+    if !reasons.is_empty() {
+        sqlx::query!(
+            "INSERT INTO measurement_quarantine (measurement_id, reasons) VALUES ($1, $2)",
+            measurement.id,
+            &reasons
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+
+    // An implausible reading still shows that the pot is online.
+    let heartbeat = Heartbeat {
+        battery_level: Some(battery_level).filter(|level| (0..=100).contains(level)),
+        firmware_version: None,
+    };
+    if let Err(e) = heartbeat::record_heartbeat(pool, pot, &heartbeat).await {
+        error!("Failed to record heartbeat of pot {}: {}", pot, e);
+    }
+
+    if !reasons.is_empty() {
+        warn!(
+            "Quarantined measurement {} of pot {}: {}",
+            measurement.id,
+            pot,
+            reasons.join(", ")
+        );
+        return Ok((measurement, reasons));
+    }
+
+    events::publish(pool, LiveEvent::from(&measurement)).await;
+
+    // Like live events, the task rules never fail the ingestion.
+    if let Err(e) = automation::apply_rules(pool, &measurement).await {
+        error!(
+            "Failed to apply task rules to measurement {}: {}",
+            measurement.id, e
+        );
+    }
+
+    Ok((measurement, reasons))
 }
 
-pub async fn get_measurements(pool: &Pool<Postgres>, pot_id: String) -> Result<Vec<Measurement>> {
-    let pot_id = pot_id.parse::<i32>().map_err(|e| {
-        error!("Failed to parse pot_id: {}", e);
-        anyhow!(e)
-    })?;
+/// Returns the quarantined readings of a pot owned by the user, newest first.
+pub async fn get_quarantined(
+    pool: &Pool<Postgres>,
+    user_id: i32,
+    pot_id: i32,
+    limit: Option<i64>,
+) -> Result<Vec<QuarantinedMeasurement>, ServiceError> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT);
+    if !(1..=MAX_LIMIT).contains(&limit) {
+        return Err(ServiceError::validation(
+            "limit",
+            format!("must be between 1 and {}", MAX_LIMIT),
+        ));
+    }
+    resolve_pot_ids(pool, user_id, MeasurementSource::Pot(pot_id)).await?;
 
     let measurements = sqlx::query_as!(
+        QuarantinedMeasurementDb,
+        r#"SELECT m.id, m.pot_id, a.plant_id AS "plant_id?", m.timestamp, m.soil_moisture,
+            m.temperature, m.light_level, m.humidity, m.battery_level,
+            q.reasons, q.quarantined_at
+        FROM measurement m
+        JOIN measurement_quarantine q ON q.measurement_id = m.id
+        LEFT JOIN plant_pot_assignment a ON a.pot_id = m.pot_id
+            AND a.linked_at <= m.timestamp AT TIME ZONE 'UTC'
+            AND (a.unlinked_at IS NULL OR a.unlinked_at > m.timestamp AT TIME ZONE 'UTC')
+        WHERE m.pot_id = $1
+        ORDER BY m.timestamp DESC, m.id DESC
+        LIMIT $2"#,
+        pot_id,
+        limit,
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(QuarantinedMeasurement::from)
+    .collect();
+
+    Ok(measurements)
+}
+
+/// What a series of measurements is read from: one pot, or every pot a plant
+/// was in, for the periods it was in them.
+#[derive(Clone, Copy)]
+pub enum MeasurementSource {
+    Pot(i32),
+    Plant(i32),
+}
+
+impl MeasurementSource {
+    /// The plant whose assignment periods restrict the measurements.
+    pub fn plant_id(self) -> Option<i32> {
+        match self {
+            MeasurementSource::Pot(_) => None,
+            MeasurementSource::Plant(plant_id) => Some(plant_id),
+        }
+    }
+}
+
+/// Resolves a source owned by the user to the ids of its pots. For a plant
+/// these are all pots it was ever in, callers restrict the measurements to
+/// the assignment periods.
+pub async fn resolve_pot_ids(
+    pool: &Pool<Postgres>,
+    user_id: i32,
+    source: MeasurementSource,
+) -> Result<Vec<i32>, ServiceError> {
+    match source {
+        MeasurementSource::Pot(pot_id) => {
+            sqlx::query_scalar!(
+                "SELECT id FROM pot WHERE id = $1 AND owner_id = $2",
+                pot_id,
+                user_id
+            )
+            .fetch_optional(pool)
+            .await?
+            .ok_or(ServiceError::NotFound("Pot"))?;
+
+            Ok(vec![pot_id])
+        }
+        MeasurementSource::Plant(plant_id) => {
+            sqlx::query_scalar!(
+                "SELECT id FROM plant WHERE id = $1 AND owner_id = $2",
+                plant_id,
+                user_id
+            )
+            .fetch_optional(pool)
+            .await?
+            .ok_or(ServiceError::NotFound("Plant"))?;
+
+            let pot_ids = sqlx::query_scalar!(
+                "SELECT DISTINCT pot_id FROM plant_pot_assignment WHERE plant_id = $1",
+                plant_id
+            )
+            .fetch_all(pool)
+            .await?;
+
+            Ok(pot_ids)
+        }
+    }
+}
+
+/// Filters for [`get_measurements`]. Results are ordered newest first and
+/// paginated either by `offset` or, for deep pages, by a keyset `cursor`
+/// taken from a previous page.
+#[derive(Default)]
+pub struct MeasurementFilter {
+    pub pot_id: Option<i32>,
+    pub plant_id: Option<i32>,
+    pub start: Option<NaiveDateTime>,
+    pub end: Option<NaiveDateTime>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+    pub cursor: Option<String>,
+}
+
+pub struct MeasurementPage {
+    pub measurements: Vec<Measurement>,
+    pub total: i64,
+    pub limit: i64,
+    pub offset: i64,
+    pub has_more: bool,
+    /// Cursor for the next page, present when `has_more` is set.
+    pub next_cursor: Option<String>,
+}
+
+/// Position of the last row of a page: its timestamp and id, which together
+/// are unique and match the sort order.
+#[derive(Debug, PartialEq)]
+struct Cursor {
+    timestamp: NaiveDateTime,
+    id: i32,
+}
+
+impl Cursor {
+    fn encode(&self) -> String {
+        format!(
+            "{}_{}",
+            self.timestamp.and_utc().timestamp_micros(),
+            self.id
+        )
+    }
+
+    fn decode(cursor: &str) -> Option<Cursor> {
+        let (micros, id) = cursor.split_once('_')?;
+        let timestamp = DateTime::from_timestamp_micros(micros.parse().ok()?)?.naive_utc();
+        Some(Cursor {
+            timestamp,
+            id: id.parse().ok()?,
+        })
+    }
+}
+
+/// Returns a page of measurements from the user's pots matching the filter.
+/// Filtering by a pot or plant the user does not own is reported as not found.
+pub async fn get_measurements(
+    pool: &Pool<Postgres>,
+    user_id: i32,
+    filter: &MeasurementFilter,
+) -> Result<MeasurementPage, ServiceError> {
+    let limit = filter.limit.unwrap_or(DEFAULT_LIMIT);
+    if !(1..=MAX_LIMIT).contains(&limit) {
+        return Err(ServiceError::validation(
+            "limit",
+            format!("must be between 1 and {}", MAX_LIMIT),
+        ));
+    }
+    let offset = filter.offset.unwrap_or(0);
+    if offset < 0 {
+        return Err(ServiceError::validation("offset", "must not be negative"));
+    }
+    let cursor = match &filter.cursor {
+        Some(_) if offset > 0 => {
+            return Err(ServiceError::validation(
+                "cursor",
+                "cannot be combined with offset",
+            ));
+        }
+        Some(cursor) => {
+            Some(Cursor::decode(cursor).ok_or(ServiceError::validation("cursor", "is invalid"))?)
+        }
+        None => None,
+    };
+    if let (Some(start), Some(end)) = (filter.start, filter.end)
+        && start > end
+    {
+        return Err(ServiceError::validation(
+            "startDate",
+            "must not be after endDate",
+        ));
+    }
+
+    if let Some(pot_id) = filter.pot_id {
+        resolve_pot_ids(pool, user_id, MeasurementSource::Pot(pot_id)).await?;
+    }
+    if let Some(plant_id) = filter.plant_id {
+        resolve_pot_ids(pool, user_id, MeasurementSource::Plant(plant_id)).await?;
+    }
+
+    let total = sqlx::query_scalar!(
+        r#"SELECT COUNT(*) AS "count!"
+        FROM measurement m
+        JOIN pot ON pot.id = m.pot_id
+        LEFT JOIN plant_pot_assignment a ON a.pot_id = m.pot_id
+            AND a.linked_at <= m.timestamp AT TIME ZONE 'UTC'
+            AND (a.unlinked_at IS NULL OR a.unlinked_at > m.timestamp AT TIME ZONE 'UTC')
+        WHERE pot.owner_id = $1
+        AND NOT EXISTS (SELECT 1 FROM measurement_quarantine q WHERE q.measurement_id = m.id)
+        AND ($2::int IS NULL OR m.pot_id = $2)
+        AND ($3::int IS NULL OR a.plant_id = $3)
+        AND ($4::timestamp IS NULL OR m.timestamp >= $4)
+        AND ($5::timestamp IS NULL OR m.timestamp <= $5)"#,
+        user_id,
+        filter.pot_id,
+        filter.plant_id,
+        filter.start,
+        filter.end,
+    )
+    .fetch_one(pool)
+    .await?;
+
+    // One extra row tells whether there is a next page.
+    let mut measurements: Vec<Measurement> = sqlx::query_as!(
         MeasurementDb,
-        "SELECT * FROM measurement WHERE pot_id = $1 ORDER BY timestamp DESC",
-        pot_id
+        r#"SELECT m.id, m.pot_id, a.plant_id AS "plant_id?", m.timestamp, m.soil_moisture,
+            m.temperature, m.light_level, m.humidity, m.battery_level
+        FROM measurement m
+        JOIN pot ON pot.id = m.pot_id
+        LEFT JOIN plant_pot_assignment a ON a.pot_id = m.pot_id
+            AND a.linked_at <= m.timestamp AT TIME ZONE 'UTC'
+            AND (a.unlinked_at IS NULL OR a.unlinked_at > m.timestamp AT TIME ZONE 'UTC')
+        WHERE pot.owner_id = $1
+        AND NOT EXISTS (SELECT 1 FROM measurement_quarantine q WHERE q.measurement_id = m.id)
+        AND ($2::int IS NULL OR m.pot_id = $2)
+        AND ($3::int IS NULL OR a.plant_id = $3)
+        AND ($4::timestamp IS NULL OR m.timestamp >= $4)
+        AND ($5::timestamp IS NULL OR m.timestamp <= $5)
+        AND ($6::timestamp IS NULL OR (m.timestamp, m.id) < ($6, $7::int))
+        ORDER BY m.timestamp DESC, m.id DESC
+        LIMIT $8 OFFSET $9"#,
+        user_id,
+        filter.pot_id,
+        filter.plant_id,
+        filter.start,
+        filter.end,
+        cursor.as_ref().map(|c| c.timestamp),
+        cursor.as_ref().map(|c| c.id),
+        limit + 1,
+        offset,
     )
     .fetch_all(pool)
-    .await
-    .map_err(|e| {
-        error!("{e}");
-        anyhow!(e)
-    })?;
+    .await?
+    .into_iter()
+    .map(Measurement::from)
+    .collect();
+
+    let has_more = measurements.len() as i64 > limit;
+    measurements.truncate(limit as usize);
+    let next_cursor = measurements.last().filter(|_| has_more).map(|last| {
+        Cursor {
+            timestamp: last.timestamp,
+            id: last.id,
+        }
+        .encode()
+    });
+
+    Ok(MeasurementPage {
+        measurements,
+        total,
+        limit,
+        offset,
+        has_more,
+        next_cursor,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::services::{
+        downsample::{self, Metric},
+        rollup::{self, Bucket},
+    };
+
+    /// Creates a user with a pot holding one measurement per minute, starting
+    /// at 2026-01-01 00:00.
+    async fn pot_with_measurements(pool: &PgPool, count: i64) -> (i32, i32) {
+        let user_id = sqlx::query_scalar!(
+            r#"INSERT INTO "user" (email, password_hash) VALUES ('a@example.com', 'unused') RETURNING id"#
+        )
+        .fetch_one(pool)
+        .await
+        .expect("user");
+        let pot_id = sqlx::query_scalar!(
+            "INSERT INTO pot (owner_id) VALUES ($1) RETURNING id",
+            user_id
+        )
+        .fetch_one(pool)
+        .await
+        .expect("pot");
+        sqlx::query!(
+            "INSERT INTO measurement (pot_id, soil_moisture, temperature, light_level, humidity, battery_level, timestamp)
+            SELECT $1, 40, 21, 300, 55, 90, TIMESTAMP '2026-01-01' + n * INTERVAL '1 minute'
+            FROM generate_series(0, $2::bigint - 1) AS n",
+            pot_id,
+            count
+        )
+        .execute(pool)
+        .await
+        .expect("measurements");
+
+        (user_id, pot_id)
+    }
+
+    fn at_minute(minute: i64) -> NaiveDateTime {
+        DateTime::from_timestamp(1_767_225_600 + minute * 60, 0)
+            .expect("timestamp")
+            .naive_utc()
+    }
+
+    #[sqlx::test]
+    async fn paginates_newest_first(pool: PgPool) {
+        let (user_id, pot_id) = pot_with_measurements(&pool, 5).await;
+        let filter = MeasurementFilter {
+            pot_id: Some(pot_id),
+            limit: Some(2),
+            offset: Some(2),
+            ..Default::default()
+        };
+
+        let page = get_measurements(&pool, user_id, &filter)
+            .await
+            .expect("page");
+
+        assert_eq!(page.total, 5);
+        assert!(page.has_more);
+        let timestamps: Vec<_> = page.measurements.iter().map(|m| m.timestamp).collect();
+        assert_eq!(timestamps, vec![at_minute(2), at_minute(1)]);
+    }
+
+    #[sqlx::test]
+    async fn follows_cursor_to_last_page(pool: PgPool) {
+        let (user_id, pot_id) = pot_with_measurements(&pool, 5).await;
+        let mut filter = MeasurementFilter {
+            pot_id: Some(pot_id),
+            limit: Some(2),
+            ..Default::default()
+        };
+
+        let mut seen = Vec::new();
+        loop {
+            let page = get_measurements(&pool, user_id, &filter)
+                .await
+                .expect("page");
+            seen.extend(page.measurements.iter().map(|m| m.timestamp));
+            match page.next_cursor {
+                Some(cursor) => filter.cursor = Some(cursor),
+                None => {
+                    assert!(!page.has_more);
+                    break;
+                }
+            }
+        }
+
+        assert_eq!(seen, (0..5).rev().map(at_minute).collect::<Vec<_>>());
+    }
+
+    #[sqlx::test]
+    async fn filters_by_time_range(pool: PgPool) {
+        let (user_id, pot_id) = pot_with_measurements(&pool, 10).await;
+        let filter = MeasurementFilter {
+            pot_id: Some(pot_id),
+            start: Some(at_minute(3)),
+            end: Some(at_minute(5)),
+            ..Default::default()
+        };
+
+        let page = get_measurements(&pool, user_id, &filter)
+            .await
+            .expect("page");
+
+        assert_eq!(page.total, 3);
+        assert!(!page.has_more);
+    }
+
+    #[sqlx::test]
+    async fn rejects_invalid_pagination(pool: PgPool) {
+        let (user_id, _) = pot_with_measurements(&pool, 1).await;
+
+        for filter in [
+            MeasurementFilter {
+                limit: Some(0),
+                ..Default::default()
+            },
+            MeasurementFilter {
+                limit: Some(MAX_LIMIT + 1),
+                ..Default::default()
+            },
+            MeasurementFilter {
+                offset: Some(1),
+                cursor: Some("0_1".to_string()),
+                ..Default::default()
+            },
+            MeasurementFilter {
+                start: Some(at_minute(1)),
+                end: Some(at_minute(0)),
+                ..Default::default()
+            },
+        ] {
+            assert!(matches!(
+                get_measurements(&pool, user_id, &filter).await,
+                Err(ServiceError::Validation(_))
+            ));
+        }
+    }
+
+    #[sqlx::test]
+    async fn plant_series_follows_its_pots(pool: PgPool) {
+        let (user_id, first_pot) = pot_with_measurements(&pool, 60).await;
+        let second_pot = sqlx::query_scalar!(
+            "INSERT INTO pot (owner_id) VALUES ($1) RETURNING id",
+            user_id
+        )
+        .fetch_one(&pool)
+        .await
+        .expect("pot");
+        sqlx::query!(
+            "INSERT INTO measurement (pot_id, soil_moisture, temperature, light_level, humidity, battery_level, timestamp)
+            SELECT $1, 60, 21, 300, 55, 90, TIMESTAMP '2026-01-01' + n * INTERVAL '1 minute'
+            FROM generate_series(0, 59) AS n",
+            second_pot
+        )
+        .execute(&pool)
+        .await
+        .expect("measurements");
+        let plant_id = sqlx::query_scalar!(
+            "INSERT INTO plant (owner_id, name) VALUES ($1, 'Monstera') RETURNING id",
+            user_id
+        )
+        .fetch_one(&pool)
+        .await
+        .expect("plant");
+        // Moved from the first pot to the second one half an hour in.
+        sqlx::query!(
+            "INSERT INTO plant_pot_assignment (plant_id, pot_id, linked_at, unlinked_at)
+            VALUES ($1, $2, '2026-01-01 00:00Z', '2026-01-01 00:30Z'),
+                ($1, $3, '2026-01-01 00:30Z', NULL)",
+            plant_id,
+            first_pot,
+            second_pot
+        )
+        .execute(&pool)
+        .await
+        .expect("assignments");
+
+        let filter = MeasurementFilter {
+            plant_id: Some(plant_id),
+            limit: Some(100),
+            ..Default::default()
+        };
+        let page = get_measurements(&pool, user_id, &filter)
+            .await
+            .expect("measurements");
+        assert_eq!(page.total, 60);
+        let pot_at = |minute| {
+            page.measurements
+                .iter()
+                .find(|m| m.timestamp == at_minute(minute))
+                .map(|m| m.pot_id)
+        };
+        assert_eq!(pot_at(29), Some(first_pot));
+        assert_eq!(pot_at(30), Some(second_pot));
+
+        let series = downsample::get_downsampled(
+            &pool,
+            user_id,
+            MeasurementSource::Plant(plant_id),
+            Metric::default(),
+            1000,
+            at_minute(0),
+            at_minute(59),
+        )
+        .await
+        .expect("downsampled");
+        assert_eq!(series.len(), 60);
+        assert!(series.iter().all(|m| m.plant_id == Some(plant_id)));
+    }
+
+    #[sqlx::test]
+    async fn implausible_readings_are_quarantined(pool: PgPool) {
+        let (user_id, pot_id) = pot_with_measurements(&pool, 0).await;
+        let limits = PlausibilityLimits::without_age_limit();
+
+        let (_, reasons) = create_measurement(
+            &pool,
+            &limits,
+            pot_id,
+            40.0,
+            21.0,
+            300.0,
+            55.0,
+            90,
+            at_minute(0),
+        )
+        .await
+        .expect("measurement");
+        assert!(reasons.is_empty());
+        let (quarantined, reasons) = create_measurement(
+            &pool,
+            &limits,
+            pot_id,
+            40.0,
+            21.0,
+            300.0,
+            -5.0,
+            9000,
+            at_minute(1),
+        )
+        .await
+        .expect("measurement");
+        assert_eq!(reasons.len(), 2);
+        assert!(matches!(
+            create_measurement(
+                &pool,
+                &limits,
+                pot_id,
+                f32::NAN,
+                21.0,
+                300.0,
+                55.0,
+                90,
+                at_minute(2)
+            )
+            .await,
+            Err(ServiceError::Validation(_))
+        ));
+
+        let filter = MeasurementFilter {
+            pot_id: Some(pot_id),
+            ..Default::default()
+        };
+        let page = get_measurements(&pool, user_id, &filter)
+            .await
+            .expect("page");
+        assert_eq!(page.total, 1);
+        assert_eq!(page.measurements[0].timestamp, at_minute(0));
+
+        let series = downsample::get_downsampled(
+            &pool,
+            user_id,
+            MeasurementSource::Pot(pot_id),
+            Metric::default(),
+            1000,
+            at_minute(0),
+            at_minute(59),
+        )
+        .await
+        .expect("downsampled");
+        assert_eq!(series.len(), 1);
+
+        rollup::refresh_rollups(&pool).await.expect("refresh");
+        let hours = rollup::get_rollups(
+            &pool,
+            user_id,
+            MeasurementSource::Pot(pot_id),
+            Bucket::Hour,
+            None,
+            None,
+        )
+        .await
+        .expect("rollups");
+        assert_eq!(hours[0].count, 1);
+
+        let stored = get_quarantined(&pool, user_id, pot_id, None)
+            .await
+            .expect("quarantine");
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored[0].measurement.id, quarantined.id);
+        assert_eq!(stored[0].reasons, reasons);
+    }
+
+    #[test]
+    fn cursor_round_trips() {
+        let cursor = Cursor {
+            timestamp: DateTime::from_timestamp_micros(1_767_268_800_123_456)
+                .expect("timestamp")
+                .naive_utc(),
+            id: 42,
+        };
+
+        assert_eq!(Cursor::decode(&cursor.encode()), Some(cursor));
+    }
 
-    Ok(measurements.into_iter().map(Measurement::from).collect())
+    #[test]
+    fn rejects_malformed_cursors() {
+        assert_eq!(Cursor::decode(""), None);
+        assert_eq!(Cursor::decode("123"), None);
+        assert_eq!(Cursor::decode("abc_1"), None);
+        assert_eq!(Cursor::decode("123_abc"), None);
+    }
 }
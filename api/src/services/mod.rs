@@ -1,7 +1,29 @@
 pub mod auth;
+pub mod automation;
+pub mod device;
+pub mod downsample;
+pub mod error;
+pub mod events;
+pub mod heartbeat;
+pub mod identify;
 pub mod jwt;
 pub mod link;
 pub mod measurement;
+pub mod pairing;
+pub mod photo;
 pub mod plant;
+pub mod plant_type;
+pub mod plausibility;
 pub mod pot;
+pub mod pot_config;
+pub mod rollup;
+pub mod schedule;
+pub mod session;
+pub mod storage;
+pub mod task;
+pub mod timelapse;
+pub mod token;
 pub mod user;
+
+#[cfg(test)]
+mod tests;
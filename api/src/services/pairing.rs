@@ -0,0 +1,620 @@
+//! Claiming pots with one-time codes. A device announces itself by serial
+//! number and gets a short pairing code to show its user, and a token it
+//! keeps. The user claims the pot with the code, after which the device
+//! collects its credential with the token.
+
+use std::time::Duration;
+
+use rand::Rng;
+use sqlx::{
+    Pool, Postgres,
+    types::chrono::{DateTime, Utc},
+};
+
+use crate::{
+    entities::{Pot, PotDb},
+    services::{
+        device::{self, IssuedCredential},
+        error::ServiceError,
+        pot,
+        token::{generate_token, hash_token},
+    },
+};
+
+/// Codes are read off a device, so easily confused characters are left out.
+const CODE_ALPHABET: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+const CODE_LENGTH: usize = 8;
+
+pub const CODE_TTL: Duration = Duration::from_secs(15 * 60);
+
+/// Failed claims a user may make within `CLAIM_WINDOW`.
+pub const MAX_FAILED_CLAIMS: i64 = 5;
+pub const CLAIM_WINDOW: Duration = Duration::from_secs(15 * 60);
+
+/// Codes that may be requested for one serial number, and by one client,
+/// within `PAIRING_REQUEST_WINDOW`. Asking for codes needs no account.
+pub const MAX_REQUESTS_PER_SERIAL: i64 = 5;
+pub const MAX_REQUESTS_PER_CLIENT: i64 = 20;
+pub const PAIRING_REQUEST_WINDOW: Duration = Duration::from_secs(15 * 60);
+
+/// Pending codes kept per serial number, the oldest is replaced beyond that.
+pub const MAX_PENDING_CODES: i64 = 3;
+
+/// What a device gets back when it asks to be paired. Both secrets are only
+/// returned once, the database keeps hashes of them.
+pub struct PairingCode {
+    /// Shown to the user, e.g. `K7PX-3M9Q`.
+    pub code: String,
+    /// Kept by the device to collect its credential.
+    pub token: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+pub enum PairingStatus {
+    /// Nobody has claimed the pot yet.
+    Pending { expires_at: DateTime<Utc> },
+    Claimed {
+        pot_id: i32,
+        credential: IssuedCredential,
+    },
+}
+
+/// What a device reports about itself when it asks to be paired.
+#[derive(Clone, Default)]
+pub struct PairingRequest {
+    pub serial_number: String,
+    pub model: Option<String>,
+    pub firmware_version: Option<String>,
+}
+
+fn generate_code() -> String {
+    let mut rng = rand::rng();
+    (0..CODE_LENGTH)
+        .map(|_| CODE_ALPHABET[rng.random_range(0..CODE_ALPHABET.len())] as char)
+        .collect()
+}
+
+/// Upper case without separators, so `k7px-3m9q` matches `K7PX3M9Q`.
+fn normalize_code(code: &str) -> String {
+    code.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_uppercase())
+        .collect()
+}
+
+fn format_code(code: &str) -> String {
+    let (head, tail) = code.split_at(CODE_LENGTH / 2);
+    format!("{head}-{tail}")
+}
+
+/// Issues a pairing code for the device asking from `client`, its network
+/// address. Anyone can ask for a code for any serial number, so earlier codes
+/// for the same serial number keep working until they expire or, beyond
+/// [`MAX_PENDING_CODES`], are replaced.
+pub async fn start_pairing(
+    pool: &Pool<Postgres>,
+    request: &PairingRequest,
+    client: &str,
+) -> Result<PairingCode, ServiceError> {
+    let serial_number = pot::clean("serialNumber", &Some(request.serial_number.clone()))?
+        .ok_or_else(|| ServiceError::validation("serialNumber", "must not be empty"))?;
+    let model = pot::clean("model", &request.model)?;
+    let firmware_version = pot::clean("firmwareVersion", &request.firmware_version)?;
+
+    let mut tx = pool.begin().await?;
+
+    sqlx::query!("DELETE FROM pot_pairing WHERE claimed_at IS NULL AND expires_at < now()")
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query!(
+        "DELETE FROM pairing_request WHERE requested_at < $1",
+        Utc::now() - PAIRING_REQUEST_WINDOW
+    )
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query!(
+        "DELETE FROM pairing_attempt WHERE attempted_at < $1",
+        Utc::now() - CLAIM_WINDOW
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    // Concurrent requests for the same serial number or from the same client
+    // must not all pass the limits. The serial number is always locked
+    // first, so the locks cannot deadlock.
+    sqlx::query!(
+        "SELECT pg_advisory_xact_lock(hashtext('pairing_serial'), hashtext($1)),
+            pg_advisory_xact_lock(hashtext('pairing_client'), hashtext($2))",
+        serial_number,
+        client
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    let requests = sqlx::query!(
+        r#"SELECT
+            count(*) FILTER (WHERE serial_number = $1) AS "serial!",
+            count(*) FILTER (WHERE client_addr = $2) AS "client!"
+        FROM pairing_request
+        WHERE (serial_number = $1 OR client_addr = $2) AND requested_at > $3"#,
+        serial_number,
+        client,
+        Utc::now() - PAIRING_REQUEST_WINDOW,
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+    if requests.serial >= MAX_REQUESTS_PER_SERIAL || requests.client >= MAX_REQUESTS_PER_CLIENT {
+        return Err(ServiceError::RateLimited(
+            "Too many pairing codes requested, try again later".to_string(),
+        ));
+    }
+    sqlx::query!(
+        "INSERT INTO pairing_request (serial_number, client_addr) VALUES ($1, $2)",
+        serial_number,
+        client
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    // Makes room for the new code.
+    sqlx::query!(
+        "DELETE FROM pot_pairing WHERE id IN (
+            SELECT id FROM pot_pairing
+            WHERE serial_number = $1 AND claimed_at IS NULL
+            ORDER BY created_at DESC, id DESC
+            OFFSET $2
+        )",
+        serial_number,
+        MAX_PENDING_CODES - 1,
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    // Live codes are unique. A collision is unlikely, but possible.
+    let code = loop {
+        let code = generate_code();
+        let taken = sqlx::query_scalar!(
+            r#"SELECT EXISTS (
+                SELECT 1 FROM pot_pairing WHERE code_hash = $1 AND claimed_at IS NULL
+            ) AS "exists!""#,
+            hash_token(&code)
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        if !taken {
+            break code;
+        }
+    };
+    let token = generate_token();
+
+    let expires_at = sqlx::query_scalar!(
+        "INSERT INTO pot_pairing
+            (serial_number, model, firmware_version, code_hash, token_hash, expires_at)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING expires_at",
+        serial_number,
+        model,
+        firmware_version,
+        hash_token(&code),
+        hash_token(&token),
+        Utc::now() + CODE_TTL,
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(PairingCode {
+        code: format_code(&code),
+        token,
+        expires_at,
+    })
+}
+
+fn already_claimed() -> ServiceError {
+    ServiceError::conflict(
+        "POT_ALREADY_CLAIMED",
+        "Pot is registered to another account, which has to unregister it first",
+    )
+}
+
+/// Binds the pot that showed `code` to the user. A pot registered to another
+/// user has to be unregistered by them first; claiming a pot the user
+/// already owns pairs the device with it again.
+pub async fn claim_pot(
+    pool: &Pool<Postgres>,
+    user_id: i32,
+    code: &str,
+) -> Result<Pot, ServiceError> {
+    let mut tx = pool.begin().await?;
+
+    // Serializes the claims of the user, so concurrent guesses cannot all
+    // pass the limit.
+    sqlx::query!(
+        "SELECT pg_advisory_xact_lock(hashtext('pairing_attempt'), $1)",
+        user_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    let failed_claims = sqlx::query_scalar!(
+        r#"SELECT count(*) AS "count!" FROM pairing_attempt
+        WHERE user_id = $1 AND attempted_at > $2"#,
+        user_id,
+        Utc::now() - CLAIM_WINDOW,
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+    if failed_claims >= MAX_FAILED_CLAIMS {
+        return Err(ServiceError::RateLimited(
+            "Too many invalid pairing codes, try again later".to_string(),
+        ));
+    }
+
+    let pairing = sqlx::query!(
+        "SELECT id, serial_number, model, firmware_version FROM pot_pairing
+        WHERE code_hash = $1 AND claimed_at IS NULL AND expires_at > now()
+        FOR UPDATE",
+        hash_token(&normalize_code(code))
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let Some(pairing) = pairing else {
+        sqlx::query!("INSERT INTO pairing_attempt (user_id) VALUES ($1)", user_id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        return Err(ServiceError::NotFound("Pairing code"));
+    };
+
+    let owner = sqlx::query!(
+        "SELECT id, owner_id FROM pot
+        WHERE serial_number = $1 AND archived_at IS NULL
+        FOR UPDATE",
+        pairing.serial_number
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let pot = match owner {
+        Some(owner) if owner.owner_id != user_id => return Err(already_claimed()),
+        Some(owner) => {
+            sqlx::query_as!(
+                PotDb,
+                "UPDATE pot SET model = COALESCE($2, model),
+                    firmware_version = COALESCE($3, firmware_version), updated_at = now()
+                WHERE id = $1
+                RETURNING *",
+                owner.id,
+                pairing.model,
+                pairing.firmware_version,
+            )
+            .fetch_one(&mut *tx)
+            .await?
+        }
+        None => {
+            sqlx::query_as!(
+                PotDb,
+                "INSERT INTO pot (owner_id, serial_number, model, firmware_version)
+                VALUES ($1, $2, $3, $4)
+                RETURNING *",
+                user_id,
+                pairing.serial_number,
+                pairing.model,
+                pairing.firmware_version,
+            )
+            .fetch_one(&mut *tx)
+            .await
+            // Another user claimed the same device at the same time.
+            .map_err(|e| match e {
+                sqlx::Error::Database(db_err) if db_err.is_unique_violation() => already_claimed(),
+                e => ServiceError::from(e),
+            })?
+        }
+    };
+
+    sqlx::query!(
+        "UPDATE pot_pairing SET claimed_at = now(), pot_id = $2 WHERE id = $1",
+        pairing.id,
+        pot.id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(Pot::from(pot))
+}
+
+/// Called by the device with its pairing token. Once the pot is claimed, a
+/// credential is issued and returned exactly once.
+pub async fn get_pairing_status(
+    pool: &Pool<Postgres>,
+    token: &str,
+) -> Result<PairingStatus, ServiceError> {
+    let mut tx = pool.begin().await?;
+
+    let pairing = sqlx::query!(
+        "SELECT id, pot_id, expires_at, claimed_at, delivered_at FROM pot_pairing
+        WHERE token_hash = $1
+        FOR UPDATE",
+        hash_token(token)
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or(ServiceError::NotFound("Pairing"))?;
+
+    let pot_id = match (pairing.pot_id, pairing.claimed_at) {
+        (Some(pot_id), Some(_)) => pot_id,
+        _ if pairing.expires_at < Utc::now() => {
+            return Err(ServiceError::conflict(
+                "PAIRING_EXPIRED",
+                "The pairing code expired, request a new one",
+            ));
+        }
+        _ => {
+            return Ok(PairingStatus::Pending {
+                expires_at: pairing.expires_at,
+            });
+        }
+    };
+
+    if pairing.delivered_at.is_some() {
+        return Err(ServiceError::conflict(
+            "PAIRING_COMPLETED",
+            "The device credential was already collected",
+        ));
+    }
+
+    let credential = device::issue_credential(&mut tx, pot_id).await?;
+    sqlx::query!(
+        "UPDATE pot_pairing SET delivered_at = now() WHERE id = $1",
+        pairing.id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(PairingStatus::Claimed { pot_id, credential })
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::PgPool;
+
+    use super::*;
+
+    async fn create_user(pool: &PgPool, email: &str) -> i32 {
+        sqlx::query_scalar!(
+            r#"INSERT INTO "user" (email, password_hash) VALUES ($1, 'x') RETURNING id"#,
+            email
+        )
+        .fetch_one(pool)
+        .await
+        .expect("user")
+    }
+
+    const CLIENT: &str = "192.0.2.1";
+
+    fn request(serial_number: &str) -> PairingRequest {
+        PairingRequest {
+            serial_number: serial_number.to_string(),
+            model: Some("PT-2".to_string()),
+            firmware_version: None,
+        }
+    }
+
+    #[test]
+    fn codes_are_readable() {
+        let code = format_code(&generate_code());
+
+        assert_eq!(code.len(), CODE_LENGTH + 1);
+        assert!(!code.contains(['0', 'O', '1', 'I']));
+        assert_eq!(normalize_code(&code.to_lowercase()), code.replace('-', ""));
+    }
+
+    #[sqlx::test]
+    async fn claims_are_single_use(pool: PgPool) {
+        let user_id = create_user(&pool, "a@example.com").await;
+        let pairing = start_pairing(&pool, &request("SN-1"), CLIENT)
+            .await
+            .expect("pairing");
+
+        assert!(matches!(
+            get_pairing_status(&pool, &pairing.token).await,
+            Ok(PairingStatus::Pending { .. })
+        ));
+
+        let pot = claim_pot(&pool, user_id, &pairing.code.to_lowercase())
+            .await
+            .expect("claim");
+        assert_eq!(pot.serial_number.as_deref(), Some("SN-1"));
+        assert!(matches!(
+            claim_pot(&pool, user_id, &pairing.code).await,
+            Err(ServiceError::NotFound(_))
+        ));
+
+        let claimed = match get_pairing_status(&pool, &pairing.token)
+            .await
+            .expect("status")
+        {
+            PairingStatus::Claimed { pot_id, credential } => Some((pot_id, credential)),
+            PairingStatus::Pending { .. } => None,
+        };
+        let (pot_id, credential) = claimed.expect("claimed");
+        assert_eq!(pot_id, pot.id);
+        assert_eq!(
+            device::verify_credential(&pool, &credential.credential())
+                .await
+                .expect("verify"),
+            Some(pot.id)
+        );
+        assert!(matches!(
+            get_pairing_status(&pool, &pairing.token).await,
+            Err(ServiceError::Conflict {
+                code: "PAIRING_COMPLETED",
+                ..
+            })
+        ));
+    }
+
+    #[sqlx::test]
+    async fn new_codes_do_not_cancel_pending_ones(pool: PgPool) {
+        let user_id = create_user(&pool, "a@example.com").await;
+        let first = start_pairing(&pool, &request("SN-1"), CLIENT)
+            .await
+            .expect("pairing");
+        let second = start_pairing(&pool, &request("SN-1"), CLIENT)
+            .await
+            .expect("pairing");
+
+        let pot = claim_pot(&pool, user_id, &first.code).await.expect("claim");
+        assert_eq!(pot.serial_number.as_deref(), Some("SN-1"));
+        assert!(matches!(
+            get_pairing_status(&pool, &first.token).await,
+            Ok(PairingStatus::Claimed { .. })
+        ));
+        assert!(matches!(
+            get_pairing_status(&pool, &second.token).await,
+            Ok(PairingStatus::Pending { .. })
+        ));
+    }
+
+    #[sqlx::test]
+    async fn owned_pots_have_to_be_released(pool: PgPool) {
+        let user_a = create_user(&pool, "a@example.com").await;
+        let user_b = create_user(&pool, "b@example.com").await;
+        let first = start_pairing(&pool, &request("SN-1"), CLIENT)
+            .await
+            .expect("pairing");
+        let pot = claim_pot(&pool, user_a, &first.code).await.expect("claim");
+
+        let second = start_pairing(&pool, &request("SN-1"), CLIENT)
+            .await
+            .expect("pairing");
+        assert!(matches!(
+            claim_pot(&pool, user_b, &second.code).await,
+            Err(ServiceError::Conflict {
+                code: "POT_ALREADY_CLAIMED",
+                ..
+            })
+        ));
+
+        // The owner pairing the device again keeps the same pot.
+        let again = claim_pot(&pool, user_a, &second.code).await.expect("claim");
+        assert_eq!(again.id, pot.id);
+
+        pot::delete_pot(&pool, user_a, pot.id, false)
+            .await
+            .expect("release");
+        let third = start_pairing(&pool, &request("SN-1"), CLIENT)
+            .await
+            .expect("pairing");
+        let claimed = claim_pot(&pool, user_b, &third.code).await.expect("claim");
+        assert_ne!(claimed.id, pot.id);
+    }
+
+    #[sqlx::test]
+    async fn pending_codes_per_serial_are_capped(pool: PgPool) {
+        let mut pairings = Vec::new();
+        for _ in 0..=MAX_PENDING_CODES {
+            pairings.push(
+                start_pairing(&pool, &request("SN-1"), CLIENT)
+                    .await
+                    .expect("pairing"),
+            );
+        }
+
+        assert!(matches!(
+            get_pairing_status(&pool, &pairings[0].token).await,
+            Err(ServiceError::NotFound(_))
+        ));
+        for pairing in &pairings[1..] {
+            assert!(matches!(
+                get_pairing_status(&pool, &pairing.token).await,
+                Ok(PairingStatus::Pending { .. })
+            ));
+        }
+    }
+
+    #[sqlx::test]
+    async fn code_requests_are_rate_limited(pool: PgPool) {
+        for _ in 0..MAX_REQUESTS_PER_SERIAL {
+            start_pairing(&pool, &request("SN-1"), CLIENT)
+                .await
+                .expect("pairing");
+        }
+        assert!(matches!(
+            start_pairing(&pool, &request("SN-1"), "192.0.2.2").await,
+            Err(ServiceError::RateLimited(_))
+        ));
+
+        for i in MAX_REQUESTS_PER_SERIAL..MAX_REQUESTS_PER_CLIENT {
+            start_pairing(&pool, &request(&format!("SN-{i}")), CLIENT)
+                .await
+                .expect("pairing");
+        }
+        assert!(matches!(
+            start_pairing(&pool, &request("SN-NEW"), CLIENT).await,
+            Err(ServiceError::RateLimited(_))
+        ));
+        start_pairing(&pool, &request("SN-NEW"), "192.0.2.2")
+            .await
+            .expect("other client");
+    }
+
+    #[sqlx::test]
+    async fn concurrent_claims_of_a_device_conflict(pool: PgPool) {
+        let user_a = create_user(&pool, "a@example.com").await;
+        let user_b = create_user(&pool, "b@example.com").await;
+        let pairing = start_pairing(&pool, &request("SN-1"), CLIENT)
+            .await
+            .expect("pairing");
+
+        // The other claim registers the device but has not committed yet.
+        let mut other = pool.begin().await.expect("transaction");
+        sqlx::query!(
+            "INSERT INTO pot (owner_id, serial_number) VALUES ($1, 'SN-1')",
+            user_a
+        )
+        .execute(&mut *other)
+        .await
+        .expect("pot");
+
+        let claim = tokio::spawn({
+            let pool = pool.clone();
+            async move { claim_pot(&pool, user_b, &pairing.code).await }
+        });
+        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        other.commit().await.expect("commit");
+
+        assert!(matches!(
+            claim.await.expect("join"),
+            Err(ServiceError::Conflict {
+                code: "POT_ALREADY_CLAIMED",
+                ..
+            })
+        ));
+    }
+
+    #[sqlx::test]
+    async fn guessing_is_rate_limited(pool: PgPool) {
+        let user_id = create_user(&pool, "a@example.com").await;
+        let pairing = start_pairing(&pool, &request("SN-1"), CLIENT)
+            .await
+            .expect("pairing");
+
+        for _ in 0..MAX_FAILED_CLAIMS {
+            assert!(matches!(
+                claim_pot(&pool, user_id, "AAAA-AAAA").await,
+                Err(ServiceError::NotFound(_))
+            ));
+        }
+        assert!(matches!(
+            claim_pot(&pool, user_id, &pairing.code).await,
+            Err(ServiceError::RateLimited(_))
+        ));
+    }
+}
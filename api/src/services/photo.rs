@@ -0,0 +1,568 @@
+use std::io::Cursor;
+
+use bytes::Bytes;
+use image::{DynamicImage, ImageDecoder, ImageFormat, ImageReader, Limits};
+use log::warn;
+use sqlx::{
+    Pool, Postgres,
+    types::chrono::{DateTime, FixedOffset, NaiveDate, Utc},
+};
+
+use crate::{
+    entities::{CaptureSource, PlantPhoto, PlantPhotoDb},
+    services::{error::ServiceError, storage::Storage},
+};
+
+/// Largest accepted upload.
+pub const MAX_PHOTO_BYTES: usize = 15 * 1024 * 1024;
+
+/// Images wider or taller than this are rejected before decoding them.
+const MAX_DIMENSION: u32 = 12_000;
+
+/// Thumbnails fit into a square of this size, keeping the aspect ratio.
+pub const THUMBNAIL_SIZE: u32 = 320;
+
+pub const DEFAULT_LIMIT: i64 = 50;
+pub const MAX_LIMIT: i64 = 200;
+
+const SUPPORTED_FORMATS: [ImageFormat; 3] =
+    [ImageFormat::Jpeg, ImageFormat::Png, ImageFormat::WebP];
+
+pub struct PhotoUpload {
+    pub data: Bytes,
+    /// Capture time given by the client. Takes precedence over the EXIF tags.
+    pub captured_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Clone, Copy)]
+pub enum PhotoVariant {
+    Original,
+    Thumbnail,
+}
+
+pub struct PhotoPage {
+    pub photos: Vec<PlantPhoto>,
+    pub total: i64,
+    pub limit: i64,
+    pub offset: i64,
+    pub has_more: bool,
+}
+
+/// What [`process`] learned from an upload.
+struct ProcessedPhoto {
+    format: ImageFormat,
+    width: u32,
+    height: u32,
+    thumbnail: Vec<u8>,
+    taken_at: Option<DateTime<Utc>>,
+}
+
+/// Decodes a JPEG, PNG or WebP image, turned upright according to its EXIF
+/// orientation.
+pub fn decode(data: &[u8]) -> Result<(ImageFormat, DynamicImage), ServiceError> {
+    let unsupported = || ServiceError::validation("photo", "must be a JPEG, PNG or WebP image");
+
+    let mut reader = ImageReader::new(Cursor::new(data))
+        .with_guessed_format()
+        .map_err(|_| unsupported())?;
+    let format = reader
+        .format()
+        .filter(|format| SUPPORTED_FORMATS.contains(format))
+        .ok_or_else(unsupported)?;
+
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_DIMENSION);
+    limits.max_image_height = Some(MAX_DIMENSION);
+    reader.limits(limits);
+
+    let mut decoder = reader.into_decoder().map_err(|_| unsupported())?;
+    let orientation = decoder.orientation().map_err(|_| unsupported())?;
+    let mut image = DynamicImage::from_decoder(decoder).map_err(|_| unsupported())?;
+    image.apply_orientation(orientation);
+
+    Ok((format, image))
+}
+
+/// Decodes the image, reads its capture time and renders a JPEG thumbnail.
+fn process(data: &[u8]) -> Result<ProcessedPhoto, ServiceError> {
+    let (format, image) = decode(data)?;
+
+    // JPEG has no alpha channel.
+    let thumbnail =
+        DynamicImage::ImageRgb8(image.thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE).to_rgb8());
+    let mut encoded = Cursor::new(Vec::new());
+    thumbnail
+        .write_to(&mut encoded, ImageFormat::Jpeg)
+        .map_err(|e| ServiceError::Internal(e.into()))?;
+
+    Ok(ProcessedPhoto {
+        format,
+        width: image.width(),
+        height: image.height(),
+        thumbnail: encoded.into_inner(),
+        taken_at: exif_taken_at(data),
+    })
+}
+
+/// Reads `DateTimeOriginal` from the EXIF data. Cameras store local time,
+/// which is taken as UTC unless `OffsetTimeOriginal` says otherwise.
+fn exif_taken_at(data: &[u8]) -> Option<DateTime<Utc>> {
+    let exif = exif::Reader::new()
+        .read_from_container(&mut Cursor::new(data))
+        .ok()?;
+
+    let ascii = |tag| match exif.get_field(tag, exif::In::PRIMARY).map(|f| &f.value) {
+        Some(exif::Value::Ascii(values)) => values.first().cloned(),
+        _ => None,
+    };
+
+    let mut taken_at = exif::DateTime::from_ascii(&ascii(exif::Tag::DateTimeOriginal)?).ok()?;
+    if let Some(offset) = ascii(exif::Tag::OffsetTimeOriginal) {
+        let _ = taken_at.parse_offset(&offset);
+    }
+
+    let local = NaiveDate::from_ymd_opt(
+        taken_at.year.into(),
+        taken_at.month.into(),
+        taken_at.day.into(),
+    )?
+    .and_hms_opt(
+        taken_at.hour.into(),
+        taken_at.minute.into(),
+        taken_at.second.into(),
+    )?;
+    let offset = FixedOffset::east_opt(i32::from(taken_at.offset.unwrap_or(0)) * 60)?;
+
+    Some(
+        local
+            .and_local_timezone(offset)
+            .single()?
+            .with_timezone(&Utc),
+    )
+}
+
+async fn check_plant(
+    pool: &Pool<Postgres>,
+    user_id: i32,
+    plant_id: i32,
+) -> Result<(), ServiceError> {
+    sqlx::query_scalar!(
+        "SELECT id FROM plant WHERE id = $1 AND owner_id = $2",
+        plant_id,
+        user_id
+    )
+    .fetch_optional(pool)
+    .await?
+    .ok_or(ServiceError::NotFound("Plant"))?;
+
+    Ok(())
+}
+
+/// Stores a photo of the plant with its thumbnail. The capture time is the
+/// one given, else the EXIF one, else now.
+pub async fn upload_photo(
+    pool: &Pool<Postgres>,
+    storage: &dyn Storage,
+    user_id: i32,
+    plant_id: i32,
+    upload: PhotoUpload,
+) -> Result<PlantPhoto, ServiceError> {
+    check_plant(pool, user_id, plant_id).await?;
+
+    if upload.data.len() > MAX_PHOTO_BYTES {
+        return Err(ServiceError::validation(
+            "photo",
+            format!(
+                "must not be larger than {} MiB",
+                MAX_PHOTO_BYTES / 1024 / 1024
+            ),
+        ));
+    }
+    if let Some(captured_at) = upload.captured_at
+        && captured_at > Utc::now()
+    {
+        return Err(ServiceError::validation(
+            "capturedAt",
+            "must not be in the future",
+        ));
+    }
+
+    // Decoding is CPU bound, keep it off the async workers.
+    let data = upload.data.clone();
+    let processed = tokio::task::spawn_blocking(move || process(&data))
+        .await
+        .map_err(|e| ServiceError::Internal(e.into()))??;
+
+    let (captured_at, capture_source) = match (upload.captured_at, processed.taken_at) {
+        (Some(captured_at), _) => (captured_at, CaptureSource::Explicit),
+        (None, Some(taken_at)) => (taken_at, CaptureSource::Exif),
+        (None, None) => (Utc::now(), CaptureSource::Upload),
+    };
+
+    let name = uuid::Uuid::new_v4();
+    let extension = processed.format.extensions_str()[0];
+    let image_key = format!("plants/{plant_id}/{name}.{extension}");
+    let thumbnail_key = format!("plants/{plant_id}/{name}-thumbnail.jpg");
+
+    storage.put(&image_key, upload.data).await?;
+    if let Err(e) = storage
+        .put(&thumbnail_key, Bytes::from(processed.thumbnail))
+        .await
+    {
+        remove_blobs(storage, &[&image_key]).await;
+        return Err(e.into());
+    }
+
+    let photo = sqlx::query_as!(
+        PlantPhotoDb,
+        "INSERT INTO plant_photo (plant_id, image_key, thumbnail_key, content_type, width, height,
+            captured_at, capture_source)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        RETURNING *",
+        plant_id,
+        image_key,
+        thumbnail_key,
+        processed.format.to_mime_type(),
+        processed.width as i32,
+        processed.height as i32,
+        captured_at,
+        capture_source.as_str()
+    )
+    .fetch_one(pool)
+    .await;
+
+    match photo {
+        Ok(photo) => Ok(PlantPhoto::from(photo)),
+        Err(e) => {
+            remove_blobs(storage, &[&image_key, &thumbnail_key]).await;
+            Err(e.into())
+        }
+    }
+}
+
+/// Returns a page of the plant's photos, oldest capture first.
+pub async fn get_photos(
+    pool: &Pool<Postgres>,
+    user_id: i32,
+    plant_id: i32,
+    limit: Option<i64>,
+    offset: Option<i64>,
+) -> Result<PhotoPage, ServiceError> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT);
+    if !(1..=MAX_LIMIT).contains(&limit) {
+        return Err(ServiceError::validation(
+            "limit",
+            format!("must be between 1 and {}", MAX_LIMIT),
+        ));
+    }
+    let offset = offset.unwrap_or(0);
+    if offset < 0 {
+        return Err(ServiceError::validation("offset", "must not be negative"));
+    }
+
+    check_plant(pool, user_id, plant_id).await?;
+
+    let total = sqlx::query_scalar!(
+        r#"SELECT COUNT(*) AS "count!" FROM plant_photo WHERE plant_id = $1"#,
+        plant_id
+    )
+    .fetch_one(pool)
+    .await?;
+
+    // One extra row tells whether there is a next page.
+    let mut photos: Vec<PlantPhoto> = sqlx::query_as!(
+        PlantPhotoDb,
+        "SELECT * FROM plant_photo
+        WHERE plant_id = $1
+        ORDER BY captured_at, id
+        LIMIT $2 OFFSET $3",
+        plant_id,
+        limit + 1,
+        offset
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(PlantPhoto::from)
+    .collect();
+
+    let has_more = photos.len() as i64 > limit;
+    photos.truncate(limit as usize);
+
+    Ok(PhotoPage {
+        photos,
+        total,
+        limit,
+        offset,
+        has_more,
+    })
+}
+
+pub async fn get_photo(
+    pool: &Pool<Postgres>,
+    user_id: i32,
+    plant_id: i32,
+    photo_id: i32,
+) -> Result<PlantPhoto, ServiceError> {
+    sqlx::query_as!(
+        PlantPhotoDb,
+        "SELECT plant_photo.* FROM plant_photo
+        JOIN plant ON plant.id = plant_photo.plant_id
+        WHERE plant_photo.id = $1 AND plant_photo.plant_id = $2 AND plant.owner_id = $3",
+        photo_id,
+        plant_id,
+        user_id
+    )
+    .fetch_optional(pool)
+    .await?
+    .map(PlantPhoto::from)
+    .ok_or(ServiceError::NotFound("Photo"))
+}
+
+/// Loads the image or its thumbnail, returning the data and its content type.
+pub async fn read_photo(
+    storage: &dyn Storage,
+    photo: &PlantPhoto,
+    variant: PhotoVariant,
+) -> Result<(Bytes, String), ServiceError> {
+    match variant {
+        PhotoVariant::Original => Ok((
+            storage.get(&photo.image_key).await?,
+            photo.content_type.clone(),
+        )),
+        PhotoVariant::Thumbnail => Ok((
+            storage.get(&photo.thumbnail_key).await?,
+            ImageFormat::Jpeg.to_mime_type().to_string(),
+        )),
+    }
+}
+
+pub async fn delete_photo(
+    pool: &Pool<Postgres>,
+    storage: &dyn Storage,
+    user_id: i32,
+    plant_id: i32,
+    photo_id: i32,
+) -> Result<(), ServiceError> {
+    let photo = get_photo(pool, user_id, plant_id, photo_id).await?;
+
+    sqlx::query!("DELETE FROM plant_photo WHERE id = $1", photo.id)
+        .execute(pool)
+        .await?;
+
+    remove_blobs(storage, &[&photo.image_key, &photo.thumbnail_key]).await;
+
+    Ok(())
+}
+
+/// Deletes every photo of the plant, ahead of deleting the plant itself.
+pub async fn delete_plant_photos(
+    pool: &Pool<Postgres>,
+    storage: &dyn Storage,
+    user_id: i32,
+    plant_id: i32,
+) -> Result<(), ServiceError> {
+    let keys = sqlx::query!(
+        "DELETE FROM plant_photo
+        USING plant
+        WHERE plant_photo.plant_id = plant.id
+        AND plant.id = $1
+        AND plant.owner_id = $2
+        RETURNING plant_photo.image_key, plant_photo.thumbnail_key",
+        plant_id,
+        user_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    for row in keys {
+        remove_blobs(storage, &[&row.image_key, &row.thumbnail_key]).await;
+    }
+
+    Ok(())
+}
+
+/// Best effort: the rows are gone already, a leftover blob is only wasted
+/// space.
+async fn remove_blobs(storage: &dyn Storage, keys: &[&str]) {
+    for key in keys {
+        if let Err(e) = storage.delete(key).await {
+            warn!("Could not delete photo blob {key}: {e:#}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{ImageBuffer, Rgb};
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::services::{
+        plant::{self, PlantInput},
+        storage::LocalStorage,
+    };
+
+    fn temp_storage() -> LocalStorage {
+        LocalStorage::new(std::env::temp_dir().join(format!("photos-{}", uuid::Uuid::new_v4())))
+    }
+
+    fn encode(width: u32, height: u32, format: ImageFormat) -> Vec<u8> {
+        let image = ImageBuffer::from_pixel(width, height, Rgb([40u8, 160, 60]));
+        let mut data = Cursor::new(Vec::new());
+        image.write_to(&mut data, format).expect("encode");
+        data.into_inner()
+    }
+
+    /// A JPEG with an EXIF segment holding only `DateTimeOriginal`.
+    fn jpeg_with_exif(taken_at: &str) -> Vec<u8> {
+        let mut tiff = b"II\x2a\x00\x08\x00\x00\x00".to_vec();
+        // IFD0 with a pointer to the Exif IFD at offset 26.
+        tiff.extend([1, 0, 0x69, 0x87, 4, 0, 1, 0, 0, 0, 26, 0, 0, 0, 0, 0, 0, 0]);
+        // Exif IFD with DateTimeOriginal stored at offset 44.
+        tiff.extend([1, 0, 0x03, 0x90, 2, 0, 20, 0, 0, 0, 44, 0, 0, 0, 0, 0, 0, 0]);
+        tiff.extend(taken_at.as_bytes());
+        tiff.push(0);
+
+        let mut segment = vec![0xff, 0xe1];
+        segment.extend(((tiff.len() + 8) as u16).to_be_bytes());
+        segment.extend(b"Exif\0\0");
+        segment.extend(tiff);
+
+        let mut jpeg = encode(64, 48, ImageFormat::Jpeg);
+        jpeg.splice(2..2, segment);
+        jpeg
+    }
+
+    async fn create_plant(pool: &PgPool) -> (i32, i32) {
+        let user_id = sqlx::query_scalar!(
+            r#"INSERT INTO "user" (email, password_hash) VALUES ('a@example.com', 'unused') RETURNING id"#
+        )
+        .fetch_one(pool)
+        .await
+        .expect("user");
+        let plant = plant::create_plant(pool, &PlantInput::new("Monstera"), user_id)
+            .await
+            .expect("plant");
+
+        (user_id, plant.id)
+    }
+
+    #[test]
+    fn process_renders_a_bounded_thumbnail() {
+        let processed = process(&encode(1280, 640, ImageFormat::Png)).expect("process");
+
+        assert_eq!(processed.format, ImageFormat::Png);
+        assert_eq!((processed.width, processed.height), (1280, 640));
+        assert_eq!(processed.taken_at, None);
+
+        let thumbnail = image::load_from_memory(&processed.thumbnail).expect("thumbnail");
+        assert_eq!(
+            (thumbnail.width(), thumbnail.height()),
+            (THUMBNAIL_SIZE, 160)
+        );
+    }
+
+    #[test]
+    fn process_reads_the_exif_capture_time() {
+        let processed = process(&jpeg_with_exif("2025:06:01 08:30:00")).expect("process");
+
+        assert_eq!(
+            processed.taken_at.map(|taken_at| taken_at.to_rfc3339()),
+            Some("2025-06-01T08:30:00+00:00".to_string())
+        );
+    }
+
+    #[test]
+    fn process_rejects_other_files() {
+        assert!(matches!(
+            process(b"definitely not an image"),
+            Err(ServiceError::Validation(_))
+        ));
+        // A BMP header, a format the decoder knows but uploads may not use.
+        assert!(matches!(
+            process(b"BM\x3a\x00\x00\x00\x00\x00\x00\x00\x36\x00\x00\x00"),
+            Err(ServiceError::Validation(_))
+        ));
+    }
+
+    #[sqlx::test]
+    async fn photos_are_listed_by_capture_time(pool: PgPool) {
+        let (user_id, plant_id) = create_plant(&pool).await;
+        let storage = temp_storage();
+        let explicit = DateTime::parse_from_rfc3339("2024-01-01T12:00:00Z")
+            .expect("date")
+            .with_timezone(&Utc);
+
+        let uploaded = upload_photo(
+            &pool,
+            &storage,
+            user_id,
+            plant_id,
+            PhotoUpload {
+                data: Bytes::from(encode(32, 32, ImageFormat::Png)),
+                captured_at: None,
+            },
+        )
+        .await
+        .expect("upload");
+        let exif = upload_photo(
+            &pool,
+            &storage,
+            user_id,
+            plant_id,
+            PhotoUpload {
+                data: Bytes::from(jpeg_with_exif("2025:06:01 08:30:00")),
+                captured_at: None,
+            },
+        )
+        .await
+        .expect("upload");
+        let given = upload_photo(
+            &pool,
+            &storage,
+            user_id,
+            plant_id,
+            PhotoUpload {
+                data: Bytes::from(jpeg_with_exif("2025:06:01 08:30:00")),
+                captured_at: Some(explicit),
+            },
+        )
+        .await
+        .expect("upload");
+
+        assert_eq!(uploaded.capture_source, CaptureSource::Upload);
+        assert_eq!(exif.capture_source, CaptureSource::Exif);
+        assert_eq!(given.capture_source, CaptureSource::Explicit);
+        assert_eq!(given.captured_at, explicit);
+
+        let page = get_photos(&pool, user_id, plant_id, Some(2), None)
+            .await
+            .expect("photos");
+        assert_eq!(page.total, 3);
+        assert!(page.has_more);
+        let ids: Vec<i32> = page.photos.iter().map(|photo| photo.id).collect();
+        assert_eq!(ids, vec![given.id, exif.id]);
+
+        let (thumbnail, content_type) = read_photo(&storage, &exif, PhotoVariant::Thumbnail)
+            .await
+            .expect("thumbnail");
+        assert_eq!(content_type, "image/jpeg");
+        assert!(image::load_from_memory(&thumbnail).is_ok());
+
+        delete_photo(&pool, &storage, user_id, plant_id, exif.id)
+            .await
+            .expect("delete");
+        assert!(storage.get(&exif.image_key).await.is_err());
+        assert!(storage.get(&exif.thumbnail_key).await.is_err());
+
+        delete_plant_photos(&pool, &storage, user_id, plant_id)
+            .await
+            .expect("delete all");
+        let page = get_photos(&pool, user_id, plant_id, None, None)
+            .await
+            .expect("photos");
+        assert_eq!(page.total, 0);
+        assert!(storage.get(&uploaded.image_key).await.is_err());
+    }
+}
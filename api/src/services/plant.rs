@@ -1,69 +1,491 @@
-use anyhow::{Result, anyhow};
-use sqlx::{Pool, Postgres};
+use sqlx::{
+    Pool, Postgres,
+    types::chrono::{NaiveDate, Utc},
+};
 
-use crate::entities::{Plant, PlantDb};
+use crate::{
+    entities::{Plant, PlantDb},
+    services::{
+        error::ServiceError,
+        events::{self, LiveEvent},
+    },
+};
 
-pub async fn create_plant(pool: &Pool<Postgres>, name: &str, user_id: i32) -> Result<Plant> {
-    sqlx::query_as!(
+// Every query is scoped by `owner_id`. Plants of other users are reported as
+// missing so callers cannot tell them apart from ids that do not exist.
+
+/// The editable fields of a plant. Blank strings are stored as missing.
+#[derive(Clone, Default)]
+pub struct PlantInput {
+    pub name: String,
+    pub plant_type_id: Option<i32>,
+    pub species: Option<String>,
+    pub location: Option<String>,
+    pub notes: Option<String>,
+    pub image_url: Option<String>,
+    pub acquired_on: Option<NaiveDate>,
+    pub archived: bool,
+}
+
+#[cfg(test)]
+impl PlantInput {
+    pub fn new(name: impl Into<String>) -> Self {
+        PlantInput {
+            name: name.into(),
+            ..Default::default()
+        }
+    }
+}
+
+impl From<Plant> for PlantInput {
+    fn from(plant: Plant) -> Self {
+        PlantInput {
+            name: plant.name,
+            plant_type_id: plant.plant_type_id,
+            species: plant.species,
+            location: plant.location,
+            notes: plant.notes,
+            image_url: plant.image_url,
+            acquired_on: plant.acquired_on,
+            archived: plant.archived,
+        }
+    }
+}
+
+/// A partial update of a plant. `None` leaves a field unchanged, `Some(None)`
+/// clears it.
+#[derive(Default)]
+pub struct PlantPatch {
+    pub name: Option<String>,
+    pub plant_type_id: Option<Option<i32>>,
+    pub species: Option<Option<String>>,
+    pub location: Option<Option<String>>,
+    pub notes: Option<Option<String>>,
+    pub image_url: Option<Option<String>>,
+    pub acquired_on: Option<Option<NaiveDate>>,
+    pub archived: Option<bool>,
+}
+
+impl PlantPatch {
+    fn apply(self, input: &mut PlantInput) {
+        fn set<T>(field: &mut T, value: Option<T>) {
+            if let Some(value) = value {
+                *field = value;
+            }
+        }
+
+        set(&mut input.name, self.name);
+        set(&mut input.plant_type_id, self.plant_type_id);
+        set(&mut input.species, self.species);
+        set(&mut input.location, self.location);
+        set(&mut input.notes, self.notes);
+        set(&mut input.image_url, self.image_url);
+        set(&mut input.acquired_on, self.acquired_on);
+        set(&mut input.archived, self.archived);
+    }
+}
+
+pub async fn create_plant(
+    pool: &Pool<Postgres>,
+    input: &PlantInput,
+    user_id: i32,
+) -> Result<Plant, ServiceError> {
+    let input = validate(pool, input).await?;
+
+    let plant = sqlx::query_as!(
         PlantDb,
-        "INSERT INTO plant (name, owner_id) VALUES ($1, $2) RETURNING *",
-        name,
+        "INSERT INTO plant (name, plant_type_id, species, location, notes, image_url,
+            acquired_on, archived, owner_id)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+        RETURNING *",
+        input.name,
+        input.plant_type_id,
+        input.species,
+        input.location,
+        input.notes,
+        input.image_url,
+        input.acquired_on,
+        input.archived,
         user_id
     )
     .fetch_one(pool)
-    .await
-    .map_err(|e| anyhow!(e))
+    .await?;
+
+    Ok(Plant::from(plant))
+}
+
+pub async fn get_plant(
+    pool: &Pool<Postgres>,
+    user_id: i32,
+    plant_id: i32,
+) -> Result<Plant, ServiceError> {
+    sqlx::query_as!(
+        PlantDb,
+        "SELECT * FROM plant WHERE id = $1 AND owner_id = $2",
+        plant_id,
+        user_id
+    )
+    .fetch_optional(pool)
+    .await?
     .map(Plant::from)
+    .ok_or(ServiceError::NotFound("Plant"))
 }
 
-pub async fn find_plant_by_id(pool: &Pool<Postgres>, plant_id: i32) -> Result<Option<Plant>> {
-    let mut tx = pool.begin().await.map_err(|e| anyhow!(e))?;
+/// Returns the plants of the user, optionally only the archived or the
+/// active ones.
+pub async fn get_plants(
+    pool: &Pool<Postgres>,
+    user_id: i32,
+    archived: Option<bool>,
+) -> Result<Vec<Plant>, ServiceError> {
+    let plants = sqlx::query_as!(
+        PlantDb,
+        "SELECT * FROM plant
+        WHERE owner_id = $1 AND ($2::boolean IS NULL OR archived = $2)
+        ORDER BY id",
+        user_id,
+        archived
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(Plant::from)
+    .collect();
 
-    let plant = sqlx::query_as!(PlantDb, "SELECT * FROM plant WHERE id = $1", plant_id)
-        .fetch_optional(&mut *tx)
-        .await
-        .map_err(|e| anyhow!(e))?
-        .map(Plant::from);
+    Ok(plants)
+}
 
-    tx.commit().await.map_err(|e| anyhow!(e))?;
+/// Replaces every editable field of a plant.
+pub async fn update_plant(
+    pool: &Pool<Postgres>,
+    user_id: i32,
+    plant_id: i32,
+    input: &PlantInput,
+) -> Result<Plant, ServiceError> {
+    let input = validate(pool, input).await?;
 
-    Ok(plant)
+    sqlx::query_as!(
+        PlantDb,
+        "UPDATE plant SET name = $1, plant_type_id = $2, species = $3, location = $4,
+            notes = $5, image_url = $6, acquired_on = $7, archived = $8
+        WHERE id = $9 AND owner_id = $10
+        RETURNING *",
+        input.name,
+        input.plant_type_id,
+        input.species,
+        input.location,
+        input.notes,
+        input.image_url,
+        input.acquired_on,
+        input.archived,
+        plant_id,
+        user_id
+    )
+    .fetch_optional(pool)
+    .await?
+    .map(Plant::from)
+    .ok_or(ServiceError::NotFound("Plant"))
 }
 
-pub async fn get_plants(pool: &Pool<Postgres>) -> Result<Vec<Plant>> {
-    let mut tx = pool.begin().await.map_err(|e| anyhow!(e))?;
+/// Updates only the fields set in `patch`.
+pub async fn patch_plant(
+    pool: &Pool<Postgres>,
+    user_id: i32,
+    plant_id: i32,
+    patch: PlantPatch,
+) -> Result<Plant, ServiceError> {
+    let mut tx = pool.begin().await?;
 
-    let plants = sqlx::query_as!(PlantDb, "SELECT * FROM plant")
-        .fetch_all(&mut *tx)
-        .await
-        .map_err(|e| anyhow!(e))?
-        .into_iter()
-        .map(Plant::from)
-        .collect();
+    let plant = sqlx::query_as!(
+        PlantDb,
+        "SELECT * FROM plant WHERE id = $1 AND owner_id = $2 FOR UPDATE",
+        plant_id,
+        user_id
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    .map(Plant::from)
+    .ok_or(ServiceError::NotFound("Plant"))?;
 
-    tx.commit().await.map_err(|e| anyhow!(e))?;
+    let mut input = PlantInput::from(plant);
+    patch.apply(&mut input);
+    let input = validate(pool, &input).await?;
 
-    Ok(plants)
+    let plant = sqlx::query_as!(
+        PlantDb,
+        "UPDATE plant SET name = $1, plant_type_id = $2, species = $3, location = $4,
+            notes = $5, image_url = $6, acquired_on = $7, archived = $8
+        WHERE id = $9
+        RETURNING *",
+        input.name,
+        input.plant_type_id,
+        input.species,
+        input.location,
+        input.notes,
+        input.image_url,
+        input.acquired_on,
+        input.archived,
+        plant_id
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(Plant::from(plant))
 }
 
-pub async fn update_plant(pool: &Pool<Postgres>, plant_id: i32, name: &str) -> Result<Plant> {
-    sqlx::query_as!(
-        PlantDb,
-        "UPDATE plant SET name = $1 WHERE id = $2 RETURNING *",
-        name,
+/// Deletes a plant. A plant that has been in a pot is archived instead: its
+/// open period is closed and the pot history keeps pointing at it.
+pub async fn delete_plant(
+    pool: &Pool<Postgres>,
+    user_id: i32,
+    plant_id: i32,
+) -> Result<(), ServiceError> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query_scalar!(
+        "SELECT id FROM plant WHERE id = $1 AND owner_id = $2 FOR UPDATE",
+        plant_id,
+        user_id
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or(ServiceError::NotFound("Plant"))?;
+
+    let pot_ids = sqlx::query_scalar!(
+        "UPDATE plant_pot_assignment SET unlinked_at = clock_timestamp()
+        WHERE plant_id = $1 AND unlinked_at IS NULL
+        RETURNING pot_id",
         plant_id
     )
-    .fetch_one(pool)
-    .await
-    .map_err(|e| anyhow!(e))
-    .map(Plant::from)
+    .fetch_all(&mut *tx)
+    .await?;
+    for pot_id in pot_ids {
+        events::publish_in_tx(&mut tx, LiveEvent::Unlinked { pot_id, plant_id }).await?;
+    }
+
+    let has_history = sqlx::query_scalar!(
+        r#"SELECT EXISTS (
+            SELECT 1 FROM plant_pot_assignment WHERE plant_id = $1
+        ) AS "exists!""#,
+        plant_id
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    if has_history {
+        sqlx::query!("UPDATE plant SET archived = true WHERE id = $1", plant_id)
+            .execute(&mut *tx)
+            .await?;
+    } else {
+        sqlx::query!("DELETE FROM plant WHERE id = $1", plant_id)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// Checks the input and returns it with blank strings turned into missing
+/// values.
+async fn validate(pool: &Pool<Postgres>, input: &PlantInput) -> Result<PlantInput, ServiceError> {
+    fn clean(value: &Option<String>) -> Option<String> {
+        value
+            .as_deref()
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map(str::to_string)
+    }
+
+    let input = PlantInput {
+        name: input.name.trim().to_string(),
+        species: clean(&input.species),
+        location: clean(&input.location),
+        notes: clean(&input.notes),
+        image_url: clean(&input.image_url),
+        ..input.clone()
+    };
+
+    if input.name.is_empty() {
+        return Err(ServiceError::validation("name", "must not be empty"));
+    }
+    if let Some(image_url) = &input.image_url
+        && !(image_url.starts_with("https://") || image_url.starts_with("http://"))
+    {
+        return Err(ServiceError::validation(
+            "imageUrl",
+            "must be an http or https URL",
+        ));
+    }
+    if let Some(acquired_on) = input.acquired_on
+        && acquired_on > Utc::now().date_naive()
+    {
+        return Err(ServiceError::validation(
+            "acquiredOn",
+            "must not be in the future",
+        ));
+    }
+    if let Some(plant_type_id) = input.plant_type_id {
+        sqlx::query_scalar!("SELECT id FROM plant_type WHERE id = $1", plant_type_id)
+            .fetch_optional(pool)
+            .await?
+            .ok_or(ServiceError::NotFound("Plant type"))?;
+    }
+
+    Ok(input)
 }
 
-pub async fn delete_plant(pool: &Pool<Postgres>, plant_id: i32) -> Result<()> {
-    sqlx::query!("DELETE FROM plant WHERE id = $1", plant_id)
-        .execute(pool)
+#[cfg(test)]
+mod tests {
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::services::{
+        link,
+        pot::{self, PotRegistration},
+    };
+
+    async fn create_user(pool: &PgPool) -> i32 {
+        sqlx::query_scalar!(
+            r#"INSERT INTO "user" (email, password_hash) VALUES ('a@example.com', 'unused') RETURNING id"#
+        )
+        .fetch_one(pool)
         .await
-        .map_err(|e| anyhow!(e))?;
+        .expect("user")
+    }
 
-    Ok(())
+    #[sqlx::test]
+    async fn patch_changes_only_the_given_fields(pool: PgPool) {
+        let user_id = create_user(&pool).await;
+        let plant = create_plant(
+            &pool,
+            &PlantInput {
+                species: Some("Monstera deliciosa".to_string()),
+                location: Some("Living room".to_string()),
+                notes: Some("  ".to_string()),
+                ..PlantInput::new("Monstera")
+            },
+            user_id,
+        )
+        .await
+        .expect("plant");
+        assert_eq!(plant.notes, None);
+
+        let patched = patch_plant(
+            &pool,
+            user_id,
+            plant.id,
+            PlantPatch {
+                location: Some(None),
+                archived: Some(true),
+                ..Default::default()
+            },
+        )
+        .await
+        .expect("patch");
+
+        assert_eq!(patched.name, "Monstera");
+        assert_eq!(patched.species.as_deref(), Some("Monstera deliciosa"));
+        assert_eq!(patched.location, None);
+        assert!(patched.archived);
+
+        let active = get_plants(&pool, user_id, Some(false))
+            .await
+            .expect("plants");
+        assert!(active.is_empty());
+        let archived = get_plants(&pool, user_id, Some(true))
+            .await
+            .expect("plants");
+        assert_eq!(archived.len(), 1);
+    }
+
+    #[sqlx::test]
+    async fn invalid_profile_fields_are_rejected(pool: PgPool) {
+        let user_id = create_user(&pool).await;
+        let plant = create_plant(&pool, &PlantInput::new("Monstera"), user_id)
+            .await
+            .expect("plant");
+
+        let tomorrow = Utc::now().date_naive().succ_opt().expect("date");
+        assert!(matches!(
+            patch_plant(
+                &pool,
+                user_id,
+                plant.id,
+                PlantPatch {
+                    acquired_on: Some(Some(tomorrow)),
+                    ..Default::default()
+                },
+            )
+            .await,
+            Err(ServiceError::Validation(_))
+        ));
+        assert!(matches!(
+            patch_plant(
+                &pool,
+                user_id,
+                plant.id,
+                PlantPatch {
+                    image_url: Some(Some("file:///etc/passwd".to_string())),
+                    ..Default::default()
+                },
+            )
+            .await,
+            Err(ServiceError::Validation(_))
+        ));
+        assert!(matches!(
+            patch_plant(
+                &pool,
+                user_id,
+                plant.id,
+                PlantPatch {
+                    name: Some(" ".to_string()),
+                    ..Default::default()
+                },
+            )
+            .await,
+            Err(ServiceError::Validation(_))
+        ));
+    }
+
+    #[sqlx::test]
+    async fn deleting_a_potted_plant_keeps_its_history(pool: PgPool) {
+        let user_id = create_user(&pool).await;
+        let potted = create_plant(&pool, &PlantInput::new("Monstera"), user_id)
+            .await
+            .expect("plant");
+        let unpotted = create_plant(&pool, &PlantInput::new("Ficus"), user_id)
+            .await
+            .expect("plant");
+        let (pot, _) = pot::create_pot(&pool, user_id, &PotRegistration::default())
+            .await
+            .expect("pot");
+        link::link_plant_to_pot(&pool, user_id, potted.id, pot.id)
+            .await
+            .expect("link");
+
+        delete_plant(&pool, user_id, potted.id)
+            .await
+            .expect("delete");
+        delete_plant(&pool, user_id, unpotted.id)
+            .await
+            .expect("delete");
+
+        let archived = get_plant(&pool, user_id, potted.id)
+            .await
+            .expect("plant is kept");
+        assert!(archived.archived);
+        let history = link::get_pot_history(&pool, user_id, pot.id)
+            .await
+            .expect("history");
+        assert_eq!(history.len(), 1);
+        assert!(history[0].unlinked_at.is_some());
+        assert!(matches!(
+            get_plant(&pool, user_id, unpotted.id).await,
+            Err(ServiceError::NotFound(_))
+        ));
+    }
 }
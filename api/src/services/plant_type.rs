@@ -0,0 +1,193 @@
+use anyhow::Context;
+use serde::Deserialize;
+use sqlx::{Pool, Postgres};
+
+use crate::{
+    entities::{PlantType, PlantTypeDb},
+    services::error::ServiceError,
+};
+
+/// The bundled species catalog.
+const CATALOG: &str = include_str!("../../data/plant_types.json");
+
+pub const SEARCH_LIMIT: i64 = 50;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CatalogEntry {
+    common_name: String,
+    scientific_name: String,
+    watering_interval_days: i32,
+    fertilization_interval_days: i32,
+    moisture_range: (f32, f32),
+    light_range: (f32, f32),
+    temperature_range: (f32, f32),
+}
+
+fn parse_catalog() -> Result<Vec<CatalogEntry>, serde_json::Error> {
+    serde_json::from_str(CATALOG)
+}
+
+/// Scientific names of the bundled catalog, in catalog order.
+pub fn catalog_names() -> anyhow::Result<Vec<String>> {
+    let entries = parse_catalog().context("Invalid plant type catalog")?;
+    Ok(entries.into_iter().map(|e| e.scientific_name).collect())
+}
+
+/// Inserts the bundled catalog, updating species that already exist by
+/// scientific name. Returns the number of species in the catalog.
+pub async fn seed_catalog(pool: &Pool<Postgres>) -> anyhow::Result<usize> {
+    let entries = parse_catalog().context("Invalid plant type catalog")?;
+
+    let mut tx = pool.begin().await?;
+    for entry in &entries {
+        sqlx::query!(
+            "INSERT INTO plant_type (common_name, scientific_name, watering_interval_days,
+                fertilization_interval_days, moisture_min, moisture_max, light_min, light_max,
+                temperature_min, temperature_max)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            ON CONFLICT (scientific_name) DO UPDATE SET
+                common_name = EXCLUDED.common_name,
+                watering_interval_days = EXCLUDED.watering_interval_days,
+                fertilization_interval_days = EXCLUDED.fertilization_interval_days,
+                moisture_min = EXCLUDED.moisture_min,
+                moisture_max = EXCLUDED.moisture_max,
+                light_min = EXCLUDED.light_min,
+                light_max = EXCLUDED.light_max,
+                temperature_min = EXCLUDED.temperature_min,
+                temperature_max = EXCLUDED.temperature_max",
+            entry.common_name,
+            entry.scientific_name,
+            entry.watering_interval_days,
+            entry.fertilization_interval_days,
+            entry.moisture_range.0,
+            entry.moisture_range.1,
+            entry.light_range.0,
+            entry.light_range.1,
+            entry.temperature_range.0,
+            entry.temperature_range.1,
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await?;
+
+    Ok(entries.len())
+}
+
+pub async fn get_plant_type(
+    pool: &Pool<Postgres>,
+    plant_type_id: i32,
+) -> Result<PlantType, ServiceError> {
+    sqlx::query_as!(
+        PlantTypeDb,
+        "SELECT * FROM plant_type WHERE id = $1",
+        plant_type_id
+    )
+    .fetch_optional(pool)
+    .await?
+    .map(PlantType::from)
+    .ok_or(ServiceError::NotFound("Plant type"))
+}
+
+pub async fn get_plant_types(pool: &Pool<Postgres>) -> Result<Vec<PlantType>, ServiceError> {
+    let plant_types = sqlx::query_as!(PlantTypeDb, "SELECT * FROM plant_type ORDER BY id")
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .map(PlantType::from)
+        .collect();
+
+    Ok(plant_types)
+}
+
+/// Finds species whose common or scientific name contains `query`, names
+/// starting with it first. Without a query the whole catalog is listed.
+pub async fn search_plant_types(
+    pool: &Pool<Postgres>,
+    query: Option<&str>,
+) -> Result<Vec<PlantType>, ServiceError> {
+    let query = query.map(str::trim).unwrap_or_default();
+    let pattern = query
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+
+    let plant_types = sqlx::query_as!(
+        PlantTypeDb,
+        "SELECT * FROM plant_type
+        WHERE common_name ILIKE '%' || $1 || '%' OR scientific_name ILIKE '%' || $1 || '%'
+        ORDER BY (common_name ILIKE $1 || '%' OR scientific_name ILIKE $1 || '%') DESC,
+            common_name, id
+        LIMIT $2",
+        pattern,
+        SEARCH_LIMIT,
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(PlantType::from)
+    .collect();
+
+    Ok(plant_types)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use sqlx::PgPool;
+
+    use super::*;
+
+    #[test]
+    fn bundled_catalog_is_valid() {
+        let entries = parse_catalog().expect("catalog");
+
+        assert!(!entries.is_empty());
+        let names: HashSet<&str> = entries.iter().map(|e| e.scientific_name.as_str()).collect();
+        assert_eq!(names.len(), entries.len(), "duplicate scientific name");
+        for entry in &entries {
+            assert!(
+                entry.watering_interval_days > 0,
+                "{}",
+                entry.scientific_name
+            );
+            assert!(
+                entry.fertilization_interval_days > 0,
+                "{}",
+                entry.scientific_name
+            );
+            assert!(entry.moisture_range.0 <= entry.moisture_range.1);
+            assert!(entry.light_range.0 <= entry.light_range.1);
+            assert!(entry.temperature_range.0 <= entry.temperature_range.1);
+        }
+    }
+
+    #[sqlx::test]
+    async fn searches_common_and_scientific_names(pool: PgPool) {
+        let count = seed_catalog(&pool).await.expect("seed");
+        assert_eq!(seed_catalog(&pool).await.expect("reseed"), count);
+
+        let by_common = search_plant_types(&pool, Some("monst"))
+            .await
+            .expect("search");
+        assert_eq!(by_common[0].scientific_name, "Monstera deliciosa");
+
+        let by_scientific = search_plant_types(&pool, Some("FICUS"))
+            .await
+            .expect("search");
+        assert_eq!(by_scientific.len(), 2);
+
+        assert!(
+            search_plant_types(&pool, Some("%"))
+                .await
+                .expect("search")
+                .is_empty()
+        );
+        assert_eq!(
+            search_plant_types(&pool, None).await.expect("list").len(),
+            count
+        );
+    }
+}
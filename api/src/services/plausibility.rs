@@ -0,0 +1,266 @@
+//! Plausibility checks for readings reported by pots. Devices are not
+//! trusted: a broken sensor or a reset clock still produces well-formed
+//! requests. Values that cannot be stored at all are rejected, readings that
+//! are merely implausible are stored but quarantined, see
+//! `services::measurement::create_measurement`.
+//!
+//! The limits default to the ranges of the sensors the pots ship with and can
+//! be overridden with a JSON file named by `MEASUREMENT_LIMITS_FILE`, e.g.
+//! `{"temperature": {"min": -10, "max": 60}, "maxClockSkewSeconds": 60}`.
+//! Missing entries keep their defaults.
+
+use std::fmt::Display;
+
+use anyhow::{anyhow, bail};
+use serde::Deserialize;
+use sqlx::types::chrono::{NaiveDateTime, Utc};
+
+use crate::services::error::ServiceError;
+
+/// An inclusive range of plausible values.
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub struct Bounds<T> {
+    pub min: T,
+    pub max: T,
+}
+
+impl<T: PartialOrd + Display> Bounds<T> {
+    /// Also false for NaN bounds.
+    fn is_ordered(&self) -> bool {
+        self.min <= self.max
+    }
+
+    fn check(&self, field: &str, value: T, reasons: &mut Vec<String>) {
+        if value < self.min || value > self.max {
+            reasons.push(format!(
+                "{} {} is outside {} to {}",
+                field, value, self.min, self.max
+            ));
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct PlausibilityLimits {
+    /// Percent.
+    #[serde(rename = "soilMoisture")]
+    pub soil_moisture: Bounds<f32>,
+    /// Degrees Celsius.
+    pub temperature: Bounds<f32>,
+    /// Lux.
+    #[serde(rename = "lightLevel")]
+    pub light_level: Bounds<f32>,
+    /// Percent relative humidity.
+    pub humidity: Bounds<f32>,
+    /// Percent.
+    #[serde(rename = "batteryLevel")]
+    pub battery_level: Bounds<i32>,
+    /// How far a timestamp may lie ahead of the server clock.
+    #[serde(rename = "maxClockSkewSeconds")]
+    pub max_clock_skew_seconds: i64,
+    /// How far a timestamp may lie behind the server clock. Pots buffer
+    /// readings while offline, so this is generous. `None` disables the check.
+    #[serde(rename = "maxAgeSeconds")]
+    pub max_age_seconds: Option<i64>,
+}
+
+impl Default for PlausibilityLimits {
+    fn default() -> Self {
+        PlausibilityLimits {
+            soil_moisture: Bounds {
+                min: 0.0,
+                max: 100.0,
+            },
+            temperature: Bounds {
+                min: -40.0,
+                max: 80.0,
+            },
+            light_level: Bounds {
+                min: 0.0,
+                max: 150_000.0,
+            },
+            humidity: Bounds {
+                min: 0.0,
+                max: 100.0,
+            },
+            battery_level: Bounds { min: 0, max: 100 },
+            max_clock_skew_seconds: 5 * 60,
+            max_age_seconds: Some(30 * 24 * 60 * 60),
+        }
+    }
+}
+
+#[cfg(test)]
+impl PlausibilityLimits {
+    /// The default limits without the age check, for fixtures with fixed
+    /// timestamps.
+    pub fn without_age_limit() -> Self {
+        PlausibilityLimits {
+            max_age_seconds: None,
+            ..Default::default()
+        }
+    }
+}
+
+/// A reading as reported by a pot.
+pub struct Reading {
+    pub soil_moisture: f32,
+    pub temperature: f32,
+    pub light_level: f32,
+    pub humidity: f32,
+    pub battery_level: i32,
+    pub timestamp: NaiveDateTime,
+}
+
+impl PlausibilityLimits {
+    pub fn from_env() -> anyhow::Result<Self> {
+        let Ok(path) = std::env::var("MEASUREMENT_LIMITS_FILE") else {
+            return Ok(PlausibilityLimits::default());
+        };
+        let contents = std::fs::read_to_string(&path)
+            .map_err(|e| anyhow!("Failed to read measurement limits file {path}: {e}"))?;
+        let limits: PlausibilityLimits = serde_json::from_str(&contents)
+            .map_err(|e| anyhow!("Failed to parse measurement limits file {path}: {e}"))?;
+
+        let ranges = [
+            limits.soil_moisture,
+            limits.temperature,
+            limits.light_level,
+            limits.humidity,
+        ];
+        if !ranges.iter().all(Bounds::is_ordered) || !limits.battery_level.is_ordered() {
+            bail!("Measurement limits must not have a min above their max");
+        }
+        if limits.max_clock_skew_seconds < 0 || limits.max_age_seconds.is_some_and(|age| age < 0) {
+            bail!("Measurement clock limits must not be negative");
+        }
+
+        Ok(limits)
+    }
+
+    /// Rejects readings that cannot be stored and returns why the others are
+    /// implausible, if they are.
+    pub fn check(&self, reading: &Reading) -> Result<Vec<String>, ServiceError> {
+        for (field, value) in [
+            ("soilMoisture", reading.soil_moisture),
+            ("temperature", reading.temperature),
+            ("lightLevel", reading.light_level),
+            ("humidity", reading.humidity),
+        ] {
+            if !value.is_finite() {
+                return Err(ServiceError::validation(field, "must be a finite number"));
+            }
+        }
+
+        let mut reasons = Vec::new();
+        self.soil_moisture
+            .check("soilMoisture", reading.soil_moisture, &mut reasons);
+        self.temperature
+            .check("temperature", reading.temperature, &mut reasons);
+        self.light_level
+            .check("lightLevel", reading.light_level, &mut reasons);
+        self.humidity
+            .check("humidity", reading.humidity, &mut reasons);
+        self.battery_level
+            .check("batteryLevel", reading.battery_level, &mut reasons);
+
+        let offset = (reading.timestamp - Utc::now().naive_utc()).num_seconds();
+        if offset > self.max_clock_skew_seconds {
+            reasons.push(format!("timestamp is {} seconds in the future", offset));
+        }
+        if let Some(max_age) = self.max_age_seconds
+            && -offset > max_age
+        {
+            reasons.push(format!("timestamp is {} seconds in the past", -offset));
+        }
+
+        Ok(reasons)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn reading() -> Reading {
+        Reading {
+            soil_moisture: 40.0,
+            temperature: 21.0,
+            light_level: 300.0,
+            humidity: 55.0,
+            battery_level: 90,
+            timestamp: Utc::now().naive_utc(),
+        }
+    }
+
+    #[test]
+    fn accepts_plausible_readings() {
+        let limits = PlausibilityLimits::default();
+
+        assert!(limits.check(&reading()).expect("check").is_empty());
+    }
+
+    #[test]
+    fn rejects_non_finite_values() {
+        let limits = PlausibilityLimits::default();
+
+        for reading in [
+            Reading {
+                humidity: f32::NAN,
+                ..reading()
+            },
+            Reading {
+                light_level: f32::INFINITY,
+                ..reading()
+            },
+        ] {
+            assert!(matches!(
+                limits.check(&reading),
+                Err(ServiceError::Validation(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn reports_every_implausible_value() {
+        let limits = PlausibilityLimits::default();
+        let reading = Reading {
+            humidity: -5.0,
+            battery_level: 9000,
+            timestamp: Utc::now().naive_utc() + Duration::from_secs(3600),
+            ..reading()
+        };
+
+        let reasons = limits.check(&reading).expect("check");
+
+        assert_eq!(reasons.len(), 3);
+        assert_eq!(reasons[0], "humidity -5 is outside 0 to 100");
+        assert_eq!(reasons[1], "batteryLevel 9000 is outside 0 to 100");
+        assert!(reasons[2].starts_with("timestamp is"));
+    }
+
+    #[test]
+    fn age_limit_can_be_disabled() {
+        let old = Reading {
+            timestamp: Utc::now().naive_utc() - Duration::from_secs(365 * 24 * 3600),
+            ..reading()
+        };
+
+        assert_eq!(
+            PlausibilityLimits::default()
+                .check(&old)
+                .expect("check")
+                .len(),
+            1
+        );
+        assert!(
+            PlausibilityLimits::without_age_limit()
+                .check(&old)
+                .expect("check")
+                .is_empty()
+        );
+    }
+}
@@ -1,50 +1,455 @@
-use crate::entities::{Pot, PotDb};
-use anyhow::{Result, anyhow};
-use log::error;
+use crate::{
+    entities::{Pot, PotDb, PotStatus},
+    services::{
+        device::{self, IssuedCredential},
+        error::ServiceError,
+        events::{self, LiveEvent},
+    },
+};
 use sqlx::{Pool, Postgres};
 
-pub async fn create_pot(pool: &Pool<Postgres>, user_id: i32) -> Result<Pot> {
-    sqlx::query_as!(
+/// Longest accepted value for the free-form pot fields.
+const MAX_FIELD_LENGTH: usize = 100;
+
+/// What a device reports about itself when it is registered.
+#[derive(Clone, Default)]
+pub struct PotRegistration {
+    pub serial_number: Option<String>,
+    pub name: Option<String>,
+    pub model: Option<String>,
+    pub firmware_version: Option<String>,
+}
+
+/// The fields of a registered pot its owner can change.
+#[derive(Clone, Default)]
+pub struct PotUpdate {
+    pub name: Option<String>,
+    pub status: PotStatus,
+    pub firmware_version: Option<String>,
+}
+
+/// How `delete_pot` got rid of a pot.
+#[derive(Debug, PartialEq, Eq)]
+pub enum PotRemoval {
+    /// The pot had measurements, it was archived to keep them.
+    Archived,
+    Deleted,
+}
+
+/// Trims the value, turns blank values into missing ones and checks the
+/// length.
+pub fn clean(field: &'static str, value: &Option<String>) -> Result<Option<String>, ServiceError> {
+    let value = value
+        .as_deref()
+        .map(str::trim)
+        .filter(|value| !value.is_empty());
+
+    match value {
+        Some(value) if value.chars().count() > MAX_FIELD_LENGTH => Err(ServiceError::validation(
+            field,
+            format!("must not be longer than {MAX_FIELD_LENGTH} characters"),
+        )),
+        value => Ok(value.map(str::to_string)),
+    }
+}
+
+/// Registers a pot together with the first credential its device uses to
+/// report measurements.
+pub async fn create_pot(
+    pool: &Pool<Postgres>,
+    user_id: i32,
+    registration: &PotRegistration,
+) -> Result<(Pot, IssuedCredential), ServiceError> {
+    let registration = PotRegistration {
+        serial_number: clean("serialNumber", &registration.serial_number)?,
+        name: clean("name", &registration.name)?,
+        model: clean("model", &registration.model)?,
+        firmware_version: clean("firmwareVersion", &registration.firmware_version)?,
+    };
+
+    let mut tx = pool.begin().await?;
+
+    let pot = sqlx::query_as!(
         PotDb,
-        "INSERT INTO pot (owner_id) VALUES ($1) RETURNING *",
-        user_id
+        "INSERT INTO pot (owner_id, serial_number, name, model, firmware_version)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING *",
+        user_id,
+        registration.serial_number,
+        registration.name,
+        registration.model,
+        registration.firmware_version,
     )
-    .fetch_one(pool)
+    .fetch_one(&mut *tx)
     .await
-    .map_err(|e| {
-        error!("{e}");
-        anyhow!(e)
+    .map_err(|e| match e {
+        sqlx::Error::Database(db_err) if db_err.is_unique_violation() => ServiceError::conflict(
+            "SERIAL_NUMBER_TAKEN",
+            "A pot with this serial number is already registered",
+        ),
+        e => ServiceError::from(e),
     })
-    .map(Pot::from)
+    .map(Pot::from)?;
+
+    let credential = device::issue_credential(&mut tx, pot.id).await?;
+
+    tx.commit().await?;
+
+    Ok((pot, credential))
 }
 
-pub async fn get_all_pots(pool: &Pool<Postgres>, user_id: i32) -> Result<Vec<Pot>> {
-    let pots = sqlx::query_as!(PotDb, "SELECT * FROM pot where owner_id = $1", user_id)
-        .fetch_all(pool)
-        .await
-        .map_err(|e| {
-            error!("{e}");
-            anyhow!(e)
-        })?
-        .into_iter()
-        .map(Pot::from)
-        .collect();
+/// Returns the registered pots of the user, or the archived ones.
+pub async fn get_all_pots(
+    pool: &Pool<Postgres>,
+    user_id: i32,
+    archived: bool,
+) -> Result<Vec<Pot>, ServiceError> {
+    let pots = sqlx::query_as!(
+        PotDb,
+        "SELECT * FROM pot
+        WHERE owner_id = $1 AND (archived_at IS NOT NULL) = $2
+        ORDER BY id",
+        user_id,
+        archived
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(Pot::from)
+    .collect();
     Ok(pots)
 }
 
-pub async fn get_pot(pool: &Pool<Postgres>, user_id: i32, pot_id: i32) -> Result<Pot> {
-    let pot = sqlx::query_as!(
+/// Returns the pot, archived or not.
+pub async fn get_pot(
+    pool: &Pool<Postgres>,
+    user_id: i32,
+    pot_id: i32,
+) -> Result<Pot, ServiceError> {
+    sqlx::query_as!(
         PotDb,
         "SELECT * FROM pot WHERE id = $1 AND owner_id = $2",
         pot_id,
         user_id
     )
-    .fetch_one(pool)
+    .fetch_optional(pool)
+    .await?
+    .map(Pot::from)
+    .ok_or(ServiceError::NotFound("Pot"))
+}
+
+pub async fn update_pot(
+    pool: &Pool<Postgres>,
+    user_id: i32,
+    pot_id: i32,
+    update: &PotUpdate,
+) -> Result<Pot, ServiceError> {
+    let name = clean("name", &update.name)?;
+    let firmware_version = clean("firmwareVersion", &update.firmware_version)?;
+
+    let mut tx = pool.begin().await?;
+
+    let pot = sqlx::query_as!(
+        PotDb,
+        "SELECT * FROM pot WHERE id = $1 AND owner_id = $2 FOR UPDATE",
+        pot_id,
+        user_id
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or(ServiceError::NotFound("Pot"))?;
+
+    if pot.archived_at.is_some() {
+        return Err(ServiceError::conflict(
+            "POT_ARCHIVED",
+            "Pot is unregistered and can no longer be changed",
+        ));
+    }
+
+    let pot = sqlx::query_as!(
+        PotDb,
+        "UPDATE pot SET name = $2, status = $3, firmware_version = $4, updated_at = now()
+        WHERE id = $1
+        RETURNING *",
+        pot_id,
+        name,
+        update.status.as_str(),
+        firmware_version,
+    )
+    .fetch_one(&mut *tx)
     .await
-    .map_err(|e| {
-        error!("{e}");
-        anyhow!(e)
-    })
     .map(Pot::from)?;
+
+    tx.commit().await?;
+
     Ok(pot)
 }
+
+/// Unregisters a pot and unlinks its plant. A pot with measurements is
+/// archived unless `purge` is set: its credentials are revoked and the
+/// history, including which plants it held, stays readable. Otherwise the
+/// pot is deleted with everything recorded for it.
+pub async fn delete_pot(
+    pool: &Pool<Postgres>,
+    user_id: i32,
+    pot_id: i32,
+    purge: bool,
+) -> Result<PotRemoval, ServiceError> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query_scalar!(
+        "SELECT id FROM pot WHERE id = $1 AND owner_id = $2 FOR UPDATE",
+        pot_id,
+        user_id
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or(ServiceError::NotFound("Pot"))?;
+
+    let plant_ids = sqlx::query_scalar!(
+        "UPDATE plant_pot_assignment SET unlinked_at = clock_timestamp()
+        WHERE pot_id = $1 AND unlinked_at IS NULL
+        RETURNING plant_id",
+        pot_id
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+    // Sent on commit. The owner is looked up from the pot, so this has to
+    // happen before it is deleted.
+    for plant_id in plant_ids {
+        events::publish_in_tx(&mut tx, LiveEvent::Unlinked { pot_id, plant_id }).await?;
+    }
+
+    let has_measurements = sqlx::query_scalar!(
+        r#"SELECT EXISTS (SELECT 1 FROM measurement WHERE pot_id = $1) AS "exists!""#,
+        pot_id
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let removal = if has_measurements && !purge {
+        sqlx::query!(
+            "UPDATE pot SET archived_at = COALESCE(archived_at, now()), updated_at = now()
+            WHERE id = $1",
+            pot_id
+        )
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query!(
+            "UPDATE device_credential SET revoked_at = now()
+            WHERE pot_id = $1 AND revoked_at IS NULL",
+            pot_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        PotRemoval::Archived
+    } else {
+        // The automation log outlives the readings that triggered it, only
+        // the references to them are dropped.
+        sqlx::query!(
+            "UPDATE task_automation SET measurement_ids = ARRAY(
+                SELECT id FROM unnest(measurement_ids) AS id
+                WHERE id NOT IN (SELECT id FROM measurement WHERE pot_id = $1)
+            )
+            WHERE measurement_ids && ARRAY(SELECT id FROM measurement WHERE pot_id = $1)",
+            pot_id
+        )
+        .execute(&mut *tx)
+        .await?;
+        // Credentials and rollups cascade.
+        sqlx::query!("DELETE FROM measurement WHERE pot_id = $1", pot_id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query!("DELETE FROM plant_pot_assignment WHERE pot_id = $1", pot_id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query!("DELETE FROM pot WHERE id = $1", pot_id)
+            .execute(&mut *tx)
+            .await?;
+
+        PotRemoval::Deleted
+    };
+
+    tx.commit().await?;
+
+    Ok(removal)
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::{
+        PgPool,
+        types::chrono::{NaiveDate, NaiveDateTime},
+    };
+
+    use super::*;
+    use crate::services::{
+        link, measurement,
+        plant::{self, PlantInput},
+        plausibility::PlausibilityLimits,
+    };
+
+    async fn create_user(pool: &PgPool) -> i32 {
+        sqlx::query_scalar!(
+            r#"INSERT INTO "user" (email, password_hash) VALUES ('pots@example.com', 'x') RETURNING id"#
+        )
+        .fetch_one(pool)
+        .await
+        .expect("user")
+    }
+
+    fn registration(serial_number: &str) -> PotRegistration {
+        PotRegistration {
+            serial_number: Some(serial_number.to_string()),
+            name: Some("  Window sill ".to_string()),
+            model: Some("PT-2".to_string()),
+            firmware_version: Some("".to_string()),
+        }
+    }
+
+    fn timestamp() -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2026, 5, 1)
+            .and_then(|d| d.and_hms_opt(12, 0, 0))
+            .expect("timestamp")
+    }
+
+    #[sqlx::test]
+    async fn serial_numbers_are_unique_among_registered_pots(pool: PgPool) {
+        let user_id = create_user(&pool).await;
+
+        let (pot, _) = create_pot(&pool, user_id, &registration("SN-1"))
+            .await
+            .expect("pot");
+        assert_eq!(pot.name.as_deref(), Some("Window sill"));
+        assert_eq!(pot.firmware_version, None);
+        assert_eq!(pot.status, PotStatus::Active);
+
+        assert!(matches!(
+            create_pot(&pool, user_id, &registration("SN-1")).await,
+            Err(ServiceError::Conflict {
+                code: "SERIAL_NUMBER_TAKEN",
+                ..
+            })
+        ));
+        create_pot(&pool, user_id, &PotRegistration::default())
+            .await
+            .expect("pot without serial number");
+        create_pot(&pool, user_id, &PotRegistration::default())
+            .await
+            .expect("pot without serial number");
+
+        let update = PotUpdate {
+            name: Some("Kitchen".to_string()),
+            status: PotStatus::Maintenance,
+            firmware_version: Some("1.4.0".to_string()),
+        };
+        let updated = update_pot(&pool, user_id, pot.id, &update)
+            .await
+            .expect("update");
+        assert_eq!(updated.status, PotStatus::Maintenance);
+        assert_eq!(updated.serial_number.as_deref(), Some("SN-1"));
+
+        // Without measurements the pot is gone for good, freeing the serial.
+        assert_eq!(
+            delete_pot(&pool, user_id, pot.id, false)
+                .await
+                .expect("delete"),
+            PotRemoval::Deleted
+        );
+        create_pot(&pool, user_id, &registration("SN-1"))
+            .await
+            .expect("re-register");
+    }
+
+    #[sqlx::test]
+    async fn pots_with_history_are_archived(pool: PgPool) {
+        let user_id = create_user(&pool).await;
+        let (pot, credential) = create_pot(&pool, user_id, &registration("SN-1"))
+            .await
+            .expect("pot");
+        let plant = plant::create_plant(&pool, &PlantInput::new("Fern"), user_id)
+            .await
+            .expect("plant");
+        link::link_plant_to_pot(&pool, user_id, plant.id, pot.id)
+            .await
+            .expect("link");
+        measurement::create_measurement(
+            &pool,
+            &PlausibilityLimits::without_age_limit(),
+            pot.id,
+            40.0,
+            21.0,
+            300.0,
+            55.0,
+            90,
+            timestamp(),
+        )
+        .await
+        .expect("measurement");
+
+        assert_eq!(
+            delete_pot(&pool, user_id, pot.id, false)
+                .await
+                .expect("delete"),
+            PotRemoval::Archived
+        );
+
+        let archived = get_pot(&pool, user_id, pot.id).await.expect("pot");
+        assert!(archived.archived_at.is_some());
+        assert!(
+            get_all_pots(&pool, user_id, false)
+                .await
+                .expect("pots")
+                .is_empty()
+        );
+        assert_eq!(
+            get_all_pots(&pool, user_id, true)
+                .await
+                .expect("pots")
+                .len(),
+            1
+        );
+        assert!(
+            device::verify_credential(&pool, &credential.credential())
+                .await
+                .expect("verify")
+                .is_none()
+        );
+        assert!(matches!(
+            update_pot(&pool, user_id, pot.id, &PotUpdate::default()).await,
+            Err(ServiceError::Conflict {
+                code: "POT_ARCHIVED",
+                ..
+            })
+        ));
+        assert!(matches!(
+            link::link_plant_to_pot(&pool, user_id, plant.id, pot.id).await,
+            Err(ServiceError::Conflict {
+                code: "POT_ARCHIVED",
+                ..
+            })
+        ));
+        // The plant survives, unlinked, and the device can be registered anew.
+        plant::get_plant(&pool, user_id, plant.id)
+            .await
+            .expect("plant");
+        let (replacement, _) = create_pot(&pool, user_id, &registration("SN-1"))
+            .await
+            .expect("re-register");
+        link::link_plant_to_pot(&pool, user_id, plant.id, replacement.id)
+            .await
+            .expect("relink");
+
+        assert_eq!(
+            delete_pot(&pool, user_id, pot.id, true)
+                .await
+                .expect("purge"),
+            PotRemoval::Deleted
+        );
+        assert!(matches!(
+            get_pot(&pool, user_id, pot.id).await,
+            Err(ServiceError::NotFound(_))
+        ));
+    }
+}
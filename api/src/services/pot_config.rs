@@ -0,0 +1,339 @@
+//! Settings for the device of a pot. Every change is stored as a new version,
+//! devices learn the current version with each measurement they report and
+//! fetch the configuration again once it changed.
+
+use sqlx::{Pool, Postgres};
+
+use crate::{
+    entities::{PotConfig, PotConfigDb},
+    services::error::ServiceError,
+};
+
+pub const DEFAULT_SAMPLING_INTERVAL_SECONDS: i32 = 300;
+/// Matches the default report interval of a pot.
+pub const DEFAULT_UPLOAD_INTERVAL_SECONDS: i32 = 900;
+
+const MIN_INTERVAL_SECONDS: i32 = 10;
+const MAX_INTERVAL_SECONDS: i32 = 24 * 60 * 60;
+
+/// A new configuration, replacing the current one.
+#[derive(Clone)]
+pub struct PotConfigUpdate {
+    pub sampling_interval_seconds: i32,
+    pub upload_interval_seconds: i32,
+    pub moisture_dry: Option<i32>,
+    pub moisture_wet: Option<i32>,
+    /// The version the change was based on. When set, the update is refused
+    /// if someone else changed the configuration in the meantime.
+    pub expected_version: Option<i32>,
+}
+
+impl PotConfigUpdate {
+    fn validate(&self) -> Result<(), ServiceError> {
+        for (field, value) in [
+            ("samplingIntervalSeconds", self.sampling_interval_seconds),
+            ("uploadIntervalSeconds", self.upload_interval_seconds),
+        ] {
+            if !(MIN_INTERVAL_SECONDS..=MAX_INTERVAL_SECONDS).contains(&value) {
+                return Err(ServiceError::validation(
+                    field,
+                    format!(
+                        "must be between {MIN_INTERVAL_SECONDS} and {MAX_INTERVAL_SECONDS} seconds"
+                    ),
+                ));
+            }
+        }
+        if self.upload_interval_seconds < self.sampling_interval_seconds {
+            return Err(ServiceError::validation(
+                "uploadIntervalSeconds",
+                "must not be shorter than the sampling interval",
+            ));
+        }
+
+        match (self.moisture_dry, self.moisture_wet) {
+            (None, None) => Ok(()),
+            (Some(dry), Some(wet)) if dry < 0 || wet < 0 => Err(ServiceError::validation(
+                "moistureCalibration",
+                "readings must not be negative",
+            )),
+            (Some(dry), Some(wet)) if dry == wet => Err(ServiceError::validation(
+                "moistureCalibration",
+                "dry and wet readings must differ",
+            )),
+            (Some(_), Some(_)) => Ok(()),
+            _ => Err(ServiceError::validation(
+                "moistureCalibration",
+                "needs both the dry and the wet reading",
+            )),
+        }
+    }
+}
+
+/// The configuration of a pot that was never configured.
+pub fn default_config(pot_id: i32) -> PotConfig {
+    PotConfig {
+        pot_id,
+        version: 0,
+        sampling_interval_seconds: DEFAULT_SAMPLING_INTERVAL_SECONDS,
+        upload_interval_seconds: DEFAULT_UPLOAD_INTERVAL_SECONDS,
+        moisture_dry: None,
+        moisture_wet: None,
+        changed_by: None,
+        changed_by_email: None,
+        changed_at: None,
+    }
+}
+
+/// Returns the current configuration of the pot. Does not check who asks,
+/// callers have to make sure the pot is the caller's.
+pub async fn get_config(pool: &Pool<Postgres>, pot_id: i32) -> Result<PotConfig, ServiceError> {
+    let config = sqlx::query_as!(
+        PotConfigDb,
+        r#"SELECT c.pot_id, c.version, c.sampling_interval_seconds, c.upload_interval_seconds,
+            c.moisture_dry, c.moisture_wet, c.changed_by, u.email AS "changed_by_email?",
+            c.changed_at
+        FROM pot_config c
+        LEFT JOIN "user" u ON u.id = c.changed_by
+        WHERE c.pot_id = $1
+        ORDER BY c.version DESC
+        LIMIT 1"#,
+        pot_id
+    )
+    .fetch_optional(pool)
+    .await?
+    .map(PotConfig::from)
+    .unwrap_or_else(|| default_config(pot_id));
+
+    Ok(config)
+}
+
+/// The current configuration version of the pot, 0 while it uses the
+/// defaults.
+pub async fn get_config_version(pool: &Pool<Postgres>, pot_id: i32) -> Result<i32, ServiceError> {
+    let version = sqlx::query_scalar!(
+        r#"SELECT COALESCE(MAX(version), 0) AS "version!" FROM pot_config WHERE pot_id = $1"#,
+        pot_id
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(version)
+}
+
+/// Stores a new version of the configuration. The upload interval also
+/// becomes the interval the pot is expected to report in.
+pub async fn update_config(
+    pool: &Pool<Postgres>,
+    user_id: i32,
+    pot_id: i32,
+    update: &PotConfigUpdate,
+) -> Result<PotConfig, ServiceError> {
+    update.validate()?;
+
+    let mut tx = pool.begin().await?;
+
+    let archived = sqlx::query_scalar!(
+        r#"SELECT archived_at IS NOT NULL AS "archived!" FROM pot
+        WHERE id = $1 AND owner_id = $2
+        FOR UPDATE"#,
+        pot_id,
+        user_id
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or(ServiceError::NotFound("Pot"))?;
+
+    if archived {
+        return Err(ServiceError::conflict(
+            "POT_ARCHIVED",
+            "Pot is unregistered and can no longer be changed",
+        ));
+    }
+
+    // The pot row is locked, so the version cannot move until commit.
+    let current_version = sqlx::query_scalar!(
+        r#"SELECT COALESCE(MAX(version), 0) AS "version!" FROM pot_config WHERE pot_id = $1"#,
+        pot_id
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    if let Some(expected_version) = update.expected_version
+        && expected_version != current_version
+    {
+        return Err(ServiceError::conflict(
+            "CONFIG_VERSION_MISMATCH",
+            format!(
+                "Configuration was changed in the meantime, the current version is {current_version}"
+            ),
+        ));
+    }
+
+    sqlx::query!(
+        "INSERT INTO pot_config (pot_id, version, sampling_interval_seconds,
+            upload_interval_seconds, moisture_dry, moisture_wet, changed_by)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)",
+        pot_id,
+        current_version + 1,
+        update.sampling_interval_seconds,
+        update.upload_interval_seconds,
+        update.moisture_dry,
+        update.moisture_wet,
+        user_id,
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        "UPDATE pot SET report_interval_seconds = $2, updated_at = now() WHERE id = $1",
+        pot_id,
+        update.upload_interval_seconds,
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    get_config(pool, pot_id).await
+}
+
+/// Returns every version of the pot's configuration, newest first.
+pub async fn get_config_history(
+    pool: &Pool<Postgres>,
+    user_id: i32,
+    pot_id: i32,
+) -> Result<Vec<PotConfig>, ServiceError> {
+    sqlx::query_scalar!(
+        "SELECT id FROM pot WHERE id = $1 AND owner_id = $2",
+        pot_id,
+        user_id
+    )
+    .fetch_optional(pool)
+    .await?
+    .ok_or(ServiceError::NotFound("Pot"))?;
+
+    let history = sqlx::query_as!(
+        PotConfigDb,
+        r#"SELECT c.pot_id, c.version, c.sampling_interval_seconds, c.upload_interval_seconds,
+            c.moisture_dry, c.moisture_wet, c.changed_by, u.email AS "changed_by_email?",
+            c.changed_at
+        FROM pot_config c
+        LEFT JOIN "user" u ON u.id = c.changed_by
+        WHERE c.pot_id = $1
+        ORDER BY c.version DESC"#,
+        pot_id
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(PotConfig::from)
+    .collect();
+
+    Ok(history)
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::services::pot::{self, PotRegistration};
+
+    fn update(sampling: i32, upload: i32) -> PotConfigUpdate {
+        PotConfigUpdate {
+            sampling_interval_seconds: sampling,
+            upload_interval_seconds: upload,
+            moisture_dry: None,
+            moisture_wet: None,
+            expected_version: None,
+        }
+    }
+
+    #[sqlx::test]
+    async fn changes_are_versioned(pool: PgPool) {
+        let user_id = sqlx::query_scalar!(
+            r#"INSERT INTO "user" (email, password_hash) VALUES ('a@example.com', 'x') RETURNING id"#
+        )
+        .fetch_one(&pool)
+        .await
+        .expect("user");
+        let (pot, _) = pot::create_pot(&pool, user_id, &PotRegistration::default())
+            .await
+            .expect("pot");
+
+        let config = get_config(&pool, pot.id).await.expect("config");
+        assert_eq!(config.version, 0);
+        assert_eq!(
+            config.upload_interval_seconds, pot.report_interval_seconds,
+            "defaults match the pot"
+        );
+
+        let config = update_config(&pool, user_id, pot.id, &update(60, 600))
+            .await
+            .expect("update");
+        assert_eq!(config.version, 1);
+        assert_eq!(config.changed_by_email.as_deref(), Some("a@example.com"));
+        let calibrated = PotConfigUpdate {
+            moisture_dry: Some(2800),
+            moisture_wet: Some(1200),
+            expected_version: Some(1),
+            ..update(60, 1200)
+        };
+        update_config(&pool, user_id, pot.id, &calibrated)
+            .await
+            .expect("update");
+
+        assert_eq!(get_config_version(&pool, pot.id).await.expect("version"), 2);
+        let pot = pot::get_pot(&pool, user_id, pot.id).await.expect("pot");
+        assert_eq!(pot.report_interval_seconds, 1200);
+
+        // Based on a version that is no longer current.
+        assert!(matches!(
+            update_config(&pool, user_id, pot.id, &calibrated).await,
+            Err(ServiceError::Conflict {
+                code: "CONFIG_VERSION_MISMATCH",
+                ..
+            })
+        ));
+
+        let history = get_config_history(&pool, user_id, pot.id)
+            .await
+            .expect("history");
+        let versions: Vec<i32> = history.iter().map(|config| config.version).collect();
+        assert_eq!(versions, vec![2, 1]);
+        assert_eq!(history[0].moisture_dry, Some(2800));
+    }
+
+    #[sqlx::test]
+    async fn rejects_invalid_settings(pool: PgPool) {
+        let user_id = sqlx::query_scalar!(
+            r#"INSERT INTO "user" (email, password_hash) VALUES ('a@example.com', 'x') RETURNING id"#
+        )
+        .fetch_one(&pool)
+        .await
+        .expect("user");
+        let (pot, _) = pot::create_pot(&pool, user_id, &PotRegistration::default())
+            .await
+            .expect("pot");
+
+        for invalid in [
+            update(1, 600),
+            update(600, 60),
+            PotConfigUpdate {
+                moisture_dry: Some(2800),
+                ..update(60, 600)
+            },
+            PotConfigUpdate {
+                moisture_dry: Some(2000),
+                moisture_wet: Some(2000),
+                ..update(60, 600)
+            },
+        ] {
+            assert!(matches!(
+                update_config(&pool, user_id, pot.id, &invalid).await,
+                Err(ServiceError::Validation(_))
+            ));
+        }
+        assert_eq!(get_config_version(&pool, pot.id).await.expect("version"), 0);
+    }
+}
@@ -0,0 +1,331 @@
+use std::time::Duration;
+
+use log::{error, info};
+use serde::Deserialize;
+use sqlx::{Pool, Postgres, types::chrono::NaiveDateTime};
+
+use crate::{
+    entities::{MeasurementRollup, MeasurementRollupDb},
+    services::{
+        error::ServiceError,
+        measurement::{MeasurementSource, resolve_pot_ids},
+    },
+};
+
+pub const DEFAULT_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Bucket {
+    Hour,
+    Day,
+}
+
+impl Bucket {
+    pub const ALL: [Bucket; 2] = [Bucket::Hour, Bucket::Day];
+
+    /// The name used both in the `measurement_rollup.bucket` column and as
+    /// the `date_trunc` field.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Bucket::Hour => "hour",
+            Bucket::Day => "day",
+        }
+    }
+}
+
+/// Folds measurements recorded since the last refresh into the rollup table.
+/// Every bucket touched by a new measurement is recomputed from scratch, so
+/// late measurements for old buckets are picked up as well. Quarantined
+/// measurements are left out. Returns the number of new measurements.
+///
+/// New measurements are queued by a trigger, so a measurement whose
+/// transaction commits after a refresh has started is rolled up by the next
+/// one.
+pub async fn refresh_rollups(pool: &Pool<Postgres>) -> Result<i64, ServiceError> {
+    let mut tx = pool.begin().await?;
+
+    // Locking the state row keeps concurrent refreshes from interleaving.
+    sqlx::query!("SELECT refreshed_at FROM measurement_rollup_state FOR UPDATE")
+        .fetch_one(&mut *tx)
+        .await?;
+    let measurement_ids =
+        sqlx::query_scalar!("DELETE FROM measurement_rollup_queue RETURNING measurement_id")
+            .fetch_all(&mut *tx)
+            .await?;
+    if measurement_ids.is_empty() {
+        return Ok(0);
+    }
+
+    for bucket in Bucket::ALL {
+        sqlx::query!(
+            r#"WITH touched AS (
+                SELECT DISTINCT pot_id, date_trunc($2, timestamp) AS bucket_start
+                FROM measurement
+                WHERE id = ANY($1)
+            )
+            INSERT INTO measurement_rollup (
+                pot_id, bucket, bucket_start, count,
+                soil_moisture_min, soil_moisture_max, soil_moisture_avg,
+                temperature_min, temperature_max, temperature_avg,
+                light_level_min, light_level_max, light_level_avg,
+                humidity_min, humidity_max, humidity_avg
+            )
+            SELECT m.pot_id, $2, t.bucket_start, COUNT(*),
+                MIN(m.soil_moisture), MAX(m.soil_moisture), AVG(m.soil_moisture),
+                MIN(m.temperature), MAX(m.temperature), AVG(m.temperature),
+                MIN(m.light_level), MAX(m.light_level), AVG(m.light_level),
+                MIN(m.humidity), MAX(m.humidity), AVG(m.humidity)
+            FROM touched t
+            JOIN measurement m ON m.pot_id = t.pot_id
+                AND m.timestamp >= t.bucket_start
+                AND m.timestamp < t.bucket_start + ('1 ' || $2)::interval
+            WHERE NOT EXISTS (
+                SELECT 1 FROM measurement_quarantine q WHERE q.measurement_id = m.id
+            )
+            GROUP BY m.pot_id, t.bucket_start
+            ON CONFLICT (pot_id, bucket, bucket_start) DO UPDATE SET
+                count = EXCLUDED.count,
+                soil_moisture_min = EXCLUDED.soil_moisture_min,
+                soil_moisture_max = EXCLUDED.soil_moisture_max,
+                soil_moisture_avg = EXCLUDED.soil_moisture_avg,
+                temperature_min = EXCLUDED.temperature_min,
+                temperature_max = EXCLUDED.temperature_max,
+                temperature_avg = EXCLUDED.temperature_avg,
+                light_level_min = EXCLUDED.light_level_min,
+                light_level_max = EXCLUDED.light_level_max,
+                light_level_avg = EXCLUDED.light_level_avg,
+                humidity_min = EXCLUDED.humidity_min,
+                humidity_max = EXCLUDED.humidity_max,
+                humidity_avg = EXCLUDED.humidity_avg"#,
+            &measurement_ids,
+            bucket.as_str(),
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    sqlx::query!("UPDATE measurement_rollup_state SET refreshed_at = now()")
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    Ok(measurement_ids.len() as i64)
+}
+
+/// Refreshes the rollups every `interval` for as long as the server runs.
+pub fn spawn_refresh_task(pool: Pool<Postgres>, interval: Duration) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            match refresh_rollups(&pool).await {
+                Ok(0) => {}
+                Ok(count) => info!("Rolled up {} new measurements", count),
+                Err(e) => error!("Failed to refresh measurement rollups: {}", e),
+            }
+        }
+    });
+}
+
+/// Returns per-bucket aggregates for a pot or plant owned by the user, oldest
+/// first. Buckets of several pots are merged, weighting averages by count.
+/// For a plant, the buckets of a pot during which the plant was in it are
+/// included whole, even when it moved in or out halfway through. The most
+/// recent bucket lags behind by up to one refresh interval.
+pub async fn get_rollups(
+    pool: &Pool<Postgres>,
+    user_id: i32,
+    source: MeasurementSource,
+    bucket: Bucket,
+    start: Option<NaiveDateTime>,
+    end: Option<NaiveDateTime>,
+) -> Result<Vec<MeasurementRollup>, ServiceError> {
+    let pot_ids = resolve_pot_ids(pool, user_id, source).await?;
+
+    let rollups = sqlx::query_as!(
+        MeasurementRollupDb,
+        r#"SELECT bucket_start, SUM(count)::bigint AS "count!",
+            MIN(soil_moisture_min) AS "soil_moisture_min!",
+            MAX(soil_moisture_max) AS "soil_moisture_max!",
+            (SUM(soil_moisture_avg * count) / SUM(count))::real AS "soil_moisture_avg!",
+            MIN(temperature_min) AS "temperature_min!",
+            MAX(temperature_max) AS "temperature_max!",
+            (SUM(temperature_avg * count) / SUM(count))::real AS "temperature_avg!",
+            MIN(light_level_min) AS "light_level_min!",
+            MAX(light_level_max) AS "light_level_max!",
+            (SUM(light_level_avg * count) / SUM(count))::real AS "light_level_avg!",
+            MIN(humidity_min) AS "humidity_min!",
+            MAX(humidity_max) AS "humidity_max!",
+            (SUM(humidity_avg * count) / SUM(count))::real AS "humidity_avg!"
+        FROM measurement_rollup
+        WHERE pot_id = ANY($1)
+        AND bucket = $2
+        AND ($3::timestamp IS NULL OR bucket_start >= date_trunc($2, $3::timestamp))
+        AND ($4::timestamp IS NULL OR bucket_start <= $4)
+        AND ($5::int IS NULL OR EXISTS (
+            SELECT 1 FROM plant_pot_assignment a
+            WHERE a.plant_id = $5
+            AND a.pot_id = measurement_rollup.pot_id
+            AND a.linked_at < (bucket_start + ('1 ' || $2)::interval) AT TIME ZONE 'UTC'
+            AND (a.unlinked_at IS NULL OR a.unlinked_at > bucket_start AT TIME ZONE 'UTC')
+        ))
+        GROUP BY bucket_start
+        ORDER BY bucket_start"#,
+        &pot_ids,
+        bucket.as_str(),
+        start,
+        end,
+        source.plant_id(),
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(MeasurementRollup::from)
+    .collect();
+
+    Ok(rollups)
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::PgPool;
+
+    use super::*;
+
+    async fn create_pot(pool: &PgPool) -> (i32, i32) {
+        let user_id = sqlx::query_scalar!(
+            r#"INSERT INTO "user" (email, password_hash) VALUES ('a@example.com', 'unused') RETURNING id"#
+        )
+        .fetch_one(pool)
+        .await
+        .expect("user");
+        let pot_id = sqlx::query_scalar!(
+            "INSERT INTO pot (owner_id) VALUES ($1) RETURNING id",
+            user_id
+        )
+        .fetch_one(pool)
+        .await
+        .expect("pot");
+
+        (user_id, pot_id)
+    }
+
+    async fn insert(pool: &PgPool, pot_id: i32, timestamp: &str, soil_moisture: f32) {
+        sqlx::query!(
+            "INSERT INTO measurement (pot_id, soil_moisture, temperature, light_level, humidity, battery_level, timestamp)
+            VALUES ($1, $2, 20, 300, 50, 90, $3::text::timestamp)",
+            pot_id,
+            soil_moisture,
+            timestamp
+        )
+        .execute(pool)
+        .await
+        .expect("measurement");
+    }
+
+    #[sqlx::test]
+    async fn rolls_up_new_and_late_measurements(pool: PgPool) {
+        let (user_id, pot_id) = create_pot(&pool).await;
+        insert(&pool, pot_id, "2026-01-01 10:05", 10.0).await;
+        insert(&pool, pot_id, "2026-01-01 10:55", 30.0).await;
+        insert(&pool, pot_id, "2026-01-01 11:30", 50.0).await;
+
+        assert_eq!(refresh_rollups(&pool).await.expect("refresh"), 3);
+        assert_eq!(refresh_rollups(&pool).await.expect("refresh"), 0);
+
+        let hours = get_rollups(
+            &pool,
+            user_id,
+            MeasurementSource::Pot(pot_id),
+            Bucket::Hour,
+            None,
+            None,
+        )
+        .await
+        .expect("rollups");
+        assert_eq!(hours.len(), 2);
+        assert_eq!(hours[0].count, 2);
+        assert_eq!(hours[0].soil_moisture.min, 10.0);
+        assert_eq!(hours[0].soil_moisture.max, 30.0);
+        assert_eq!(hours[0].soil_moisture.avg, 20.0);
+
+        // A measurement arriving late for an already rolled up hour.
+        insert(&pool, pot_id, "2026-01-01 10:30", 80.0).await;
+        assert_eq!(refresh_rollups(&pool).await.expect("refresh"), 1);
+
+        let days = get_rollups(
+            &pool,
+            user_id,
+            MeasurementSource::Pot(pot_id),
+            Bucket::Day,
+            None,
+            None,
+        )
+        .await
+        .expect("rollups");
+        assert_eq!(days.len(), 1);
+        assert_eq!(days[0].count, 4);
+        assert_eq!(days[0].soil_moisture.max, 80.0);
+        assert_eq!(days[0].soil_moisture.avg, 42.5);
+    }
+
+    #[sqlx::test]
+    async fn rolls_up_measurements_committed_out_of_order(pool: PgPool) {
+        let (user_id, pot_id) = create_pot(&pool).await;
+
+        // Takes the lower id, but commits after the higher one was rolled up.
+        let mut slow = pool.begin().await.expect("transaction");
+        sqlx::query!(
+            "INSERT INTO measurement (pot_id, soil_moisture, temperature, light_level, humidity, battery_level, timestamp)
+            VALUES ($1, 10, 20, 300, 50, 90, '2026-01-01 10:05')",
+            pot_id,
+        )
+        .execute(&mut *slow)
+        .await
+        .expect("measurement");
+        insert(&pool, pot_id, "2026-01-01 10:10", 30.0).await;
+
+        assert_eq!(refresh_rollups(&pool).await.expect("refresh"), 1);
+        slow.commit().await.expect("commit");
+        assert_eq!(refresh_rollups(&pool).await.expect("refresh"), 1);
+
+        let hours = get_rollups(
+            &pool,
+            user_id,
+            MeasurementSource::Pot(pot_id),
+            Bucket::Hour,
+            None,
+            None,
+        )
+        .await
+        .expect("rollups");
+        assert_eq!(hours.len(), 1);
+        assert_eq!(hours[0].count, 2);
+    }
+
+    #[sqlx::test]
+    async fn rollups_of_foreign_pots_are_not_found(pool: PgPool) {
+        let (_, pot_id) = create_pot(&pool).await;
+        let other_user = sqlx::query_scalar!(
+            r#"INSERT INTO "user" (email, password_hash) VALUES ('b@example.com', 'unused') RETURNING id"#
+        )
+        .fetch_one(&pool)
+        .await
+        .expect("user");
+
+        assert!(matches!(
+            get_rollups(
+                &pool,
+                other_user,
+                MeasurementSource::Pot(pot_id),
+                Bucket::Hour,
+                None,
+                None,
+            )
+            .await,
+            Err(ServiceError::NotFound("Pot"))
+        ));
+    }
+}
@@ -0,0 +1,572 @@
+use std::collections::HashMap;
+
+use sqlx::{
+    Pool, Postgres,
+    types::chrono::{DateTime, Utc},
+};
+
+use crate::{
+    entities::{
+        PlantType, PlantTypeDb, Task, TaskCompletion, TaskCompletionDb, TaskDb, TaskSnooze,
+        TaskSnoozeDb, TaskType,
+    },
+    services::{
+        self,
+        error::ServiceError,
+        task::{MAX_INTERVAL_DAYS, days},
+    },
+};
+
+/// Number of intervals between completions that must agree before the
+/// interval follows the observed rhythm.
+pub const RHYTHM_WINDOW: usize = 3;
+
+/// Snoozes since the last completion after which the interval is lengthened.
+pub const SNOOZE_THRESHOLD: usize = 3;
+
+/// Adjusted intervals stay between half and this multiple of the base interval.
+pub const MAX_FACTOR: i32 = 2;
+
+/// Default intervals per task type, used for tasks without an interval of
+/// their own.
+#[derive(Clone, Debug)]
+pub struct CareProfile {
+    pub watering_days: i32,
+    pub fertilization_days: i32,
+    pub repotting_days: i32,
+}
+
+impl Default for CareProfile {
+    fn default() -> Self {
+        CareProfile {
+            watering_days: 7,
+            fertilization_days: 30,
+            repotting_days: 365,
+        }
+    }
+}
+
+impl From<&PlantType> for CareProfile {
+    fn from(plant_type: &PlantType) -> Self {
+        CareProfile {
+            watering_days: plant_type.watering_interval_days,
+            fertilization_days: plant_type.fertilization_interval_days,
+            ..CareProfile::default()
+        }
+    }
+}
+
+impl CareProfile {
+    /// The profile of the species of a plant, or the defaults for plants
+    /// without one.
+    pub async fn for_plant(
+        pool: &Pool<Postgres>,
+        plant_id: Option<i32>,
+    ) -> Result<CareProfile, ServiceError> {
+        let plant_type = sqlx::query_as!(
+            PlantTypeDb,
+            "SELECT t.* FROM plant_type t JOIN plant p ON p.plant_type_id = t.id WHERE p.id = $1",
+            plant_id
+        )
+        .fetch_optional(pool)
+        .await?
+        .map(PlantType::from);
+
+        Ok(plant_type
+            .as_ref()
+            .map(CareProfile::from)
+            .unwrap_or_default())
+    }
+
+    pub fn interval_days(&self, task_type: TaskType) -> Option<i32> {
+        match task_type {
+            TaskType::Watering => Some(self.watering_days),
+            TaskType::Fertilization => Some(self.fertilization_days),
+            TaskType::Repotting => Some(self.repotting_days),
+            TaskType::Custom => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Schedule {
+    /// The interval of the task, or the profile default if it has none.
+    pub base_interval_days: i32,
+    pub interval_days: i32,
+    pub next_due_at: DateTime<Utc>,
+    pub explanation: String,
+}
+
+impl Schedule {
+    pub fn is_change(&self) -> bool {
+        self.interval_days != self.base_interval_days
+    }
+}
+
+pub struct Suggestion {
+    pub task: Task,
+    pub schedule: Schedule,
+}
+
+/// Computes the schedule of a task from its completion and snooze history.
+/// The result only depends on the arguments, not on the current time or the
+/// order of the history. Returns `None` for tasks without any interval.
+///
+/// Starting from the base interval:
+/// 1. if the last [`RHYTHM_WINDOW`] intervals between completions were all
+///    longer or all shorter than the interval, it becomes their average;
+/// 2. after [`SNOOZE_THRESHOLD`] snoozes since the last completion, it is
+///    lengthened by the total time they postponed the task;
+/// 3. the result is kept between half and [`MAX_FACTOR`] times the base, and
+///    below [`MAX_INTERVAL_DAYS`].
+///
+/// The next due date is the interval after the last completion (or the
+/// creation of the task), but never before a pending snooze.
+pub fn plan(
+    task: &Task,
+    profile: &CareProfile,
+    completions: &[TaskCompletion],
+    snoozes: &[TaskSnooze],
+) -> Option<Schedule> {
+    let mut reasons = Vec::new();
+    let base = match task.interval_days {
+        Some(interval) => interval,
+        None => {
+            let interval = profile.interval_days(task.task_type)?;
+            reasons.push(format!(
+                "no interval set, using the {}-day {} default",
+                interval, task.task_type
+            ));
+            interval
+        }
+    };
+    // Task intervals are validated against the same bound, the profiles of
+    // plant types are not.
+    let base = base.clamp(1, MAX_INTERVAL_DAYS);
+
+    let mut completed: Vec<DateTime<Utc>> = completions.iter().map(|c| c.completed_at).collect();
+    completed.sort();
+    let last_completed = completed.last().copied();
+    let mut interval = base;
+
+    if completed.len() > RHYTHM_WINDOW {
+        let gaps: Vec<f64> = completed[completed.len() - RHYTHM_WINDOW - 1..]
+            .windows(2)
+            .map(|w| days_between(w[0], w[1]))
+            .collect();
+        let average = (gaps.iter().sum::<f64>() / gaps.len() as f64).round() as i32;
+        let base_days = f64::from(base);
+        let consistent =
+            gaps.iter().all(|&gap| gap > base_days) || gaps.iter().all(|&gap| gap < base_days);
+        if consistent && average != base {
+            reasons.push(format!(
+                "interval {} from {} to {} days: the last {} completions came {} days apart on average",
+                change(interval, average),
+                interval,
+                average,
+                RHYTHM_WINDOW + 1,
+                average
+            ));
+            interval = average;
+        }
+    }
+
+    let mut pending: Vec<&TaskSnooze> = snoozes
+        .iter()
+        .filter(|snooze| last_completed.is_none_or(|last| snooze.snoozed_at > last))
+        .collect();
+    pending.sort_by_key(|snooze| (snooze.snoozed_at, snooze.id));
+
+    if let (Some(first), Some(last)) = (pending.first(), pending.last())
+        && pending.len() >= SNOOZE_THRESHOLD
+    {
+        let postponed = days_between(first.from_due_at, last.to_due_at).round() as i32;
+        if postponed > 0 {
+            reasons.push(format!(
+                "interval lengthened from {} to {} days after {} consecutive snoozes",
+                interval,
+                interval.saturating_add(postponed),
+                pending.len()
+            ));
+            interval = interval.saturating_add(postponed);
+        }
+    }
+
+    let clamped = interval.clamp(
+        (base + 1) / 2,
+        base.saturating_mul(MAX_FACTOR).min(MAX_INTERVAL_DAYS),
+    );
+    if clamped != interval {
+        reasons.push(format!("capped at {} days", clamped));
+        interval = clamped;
+    }
+    if interval == base {
+        reasons.push(format!("interval kept at {} days", base));
+    }
+
+    let mut next_due_at = last_completed.unwrap_or(task.created_at) + days(i64::from(interval));
+    if let Some(last) = pending.last()
+        && last.to_due_at > next_due_at
+    {
+        next_due_at = last.to_due_at;
+        reasons.push("the snoozed due date is kept".to_string());
+    }
+
+    Some(Schedule {
+        base_interval_days: base,
+        interval_days: interval,
+        next_due_at,
+        explanation: reasons.join("; "),
+    })
+}
+
+fn days_between(from: DateTime<Utc>, to: DateTime<Utc>) -> f64 {
+    (to - from).num_seconds() as f64 / (24.0 * 60.0 * 60.0)
+}
+
+fn change(from: i32, to: i32) -> &'static str {
+    if to > from { "lengthened" } else { "shortened" }
+}
+
+pub async fn get_schedule(
+    pool: &Pool<Postgres>,
+    user_id: i32,
+    task_id: i32,
+) -> Result<Schedule, ServiceError> {
+    let task = services::task::get_task(pool, user_id, task_id).await?;
+
+    let completions: Vec<TaskCompletion> = sqlx::query_as!(
+        TaskCompletionDb,
+        "SELECT * FROM task_completion WHERE task_id = $1",
+        task_id
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(TaskCompletion::from)
+    .collect();
+    let snoozes: Vec<TaskSnooze> = sqlx::query_as!(
+        TaskSnoozeDb,
+        "SELECT * FROM task_snooze WHERE task_id = $1",
+        task_id
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(TaskSnooze::from)
+    .collect();
+
+    let profile = CareProfile::for_plant(pool, task.plant_id).await?;
+    plan(&task, &profile, &completions, &snoozes).ok_or_else(|| {
+        ServiceError::conflict("TASK_NOT_SCHEDULABLE", "Task has no interval to schedule")
+    })
+}
+
+/// Returns the pending recurring tasks of the user whose interval should
+/// change, soonest due first.
+pub async fn get_suggestions(
+    pool: &Pool<Postgres>,
+    user_id: i32,
+) -> Result<Vec<Suggestion>, ServiceError> {
+    let tasks: Vec<Task> = sqlx::query_as!(
+        TaskDb,
+        "SELECT * FROM task
+        WHERE owner_id = $1 AND NOT done AND interval_days IS NOT NULL
+        ORDER BY due_at, id",
+        user_id
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(Task::from)
+    .collect();
+
+    let mut completions: HashMap<i32, Vec<TaskCompletion>> = HashMap::new();
+    for completion in sqlx::query_as!(
+        TaskCompletionDb,
+        "SELECT c.* FROM task_completion c
+        JOIN task t ON t.id = c.task_id
+        WHERE t.owner_id = $1 AND NOT t.done AND t.interval_days IS NOT NULL",
+        user_id
+    )
+    .fetch_all(pool)
+    .await?
+    {
+        completions
+            .entry(completion.task_id)
+            .or_default()
+            .push(TaskCompletion::from(completion));
+    }
+
+    let mut snoozes: HashMap<i32, Vec<TaskSnooze>> = HashMap::new();
+    for snooze in sqlx::query_as!(
+        TaskSnoozeDb,
+        "SELECT s.* FROM task_snooze s
+        JOIN task t ON t.id = s.task_id
+        WHERE t.owner_id = $1 AND NOT t.done AND t.interval_days IS NOT NULL",
+        user_id
+    )
+    .fetch_all(pool)
+    .await?
+    {
+        snoozes
+            .entry(snooze.task_id)
+            .or_default()
+            .push(TaskSnooze::from(snooze));
+    }
+
+    // Recurring tasks have an interval of their own, so the profile of their
+    // plant is never consulted.
+    let profile = CareProfile::default();
+    Ok(tasks
+        .into_iter()
+        .filter_map(|task| {
+            let schedule = plan(
+                &task,
+                &profile,
+                completions.get(&task.id).map_or(&[], Vec::as_slice),
+                snoozes.get(&task.id).map_or(&[], Vec::as_slice),
+            )?;
+            schedule
+                .is_change()
+                .then_some(Suggestion { task, schedule })
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::services::{
+        plant::{self, PlantInput},
+        plant_type,
+        task::{self, TaskInput},
+    };
+
+    fn start() -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2026-01-01T09:00:00Z")
+            .expect("date")
+            .with_timezone(&Utc)
+    }
+
+    fn at(day: i64) -> DateTime<Utc> {
+        start() + days(day)
+    }
+
+    fn task(task_type: TaskType, interval_days: Option<i32>) -> Task {
+        Task {
+            id: 1,
+            name: "Water".to_string(),
+            plant_id: None,
+            task_type,
+            due_at: at(interval_days.unwrap_or(0).into()),
+            interval_days,
+            done: false,
+            created_at: start(),
+            updated_at: start(),
+        }
+    }
+
+    fn completed(days: &[i64]) -> Vec<TaskCompletion> {
+        days.iter()
+            .enumerate()
+            .map(|(i, &day)| TaskCompletion {
+                id: i as i32,
+                due_at: at(day),
+                completed_at: at(day),
+            })
+            .collect()
+    }
+
+    /// One-day snoozes starting from `due`.
+    fn snoozed(due: i64, count: i64) -> Vec<TaskSnooze> {
+        (0..count)
+            .map(|i| TaskSnooze {
+                id: i as i32,
+                from_due_at: at(due + i),
+                to_due_at: at(due + i + 1),
+                snoozed_at: at(due + i),
+            })
+            .collect()
+    }
+
+    fn plan_watering(completions: &[TaskCompletion], snoozes: &[TaskSnooze]) -> Schedule {
+        plan(
+            &task(TaskType::Watering, Some(7)),
+            &CareProfile::default(),
+            completions,
+            snoozes,
+        )
+        .expect("schedule")
+    }
+
+    #[test]
+    fn keeps_the_interval_without_history() {
+        let schedule = plan_watering(&[], &[]);
+
+        assert_eq!(schedule.interval_days, 7);
+        assert_eq!(schedule.next_due_at, at(7));
+        assert_eq!(schedule.explanation, "interval kept at 7 days");
+        assert!(!schedule.is_change());
+    }
+
+    #[test]
+    fn huge_intervals_stay_within_bounds() {
+        let profile = CareProfile {
+            watering_days: i32::MAX,
+            ..CareProfile::default()
+        };
+        let schedule = plan(
+            &task(TaskType::Watering, None),
+            &profile,
+            &completed(&[0, 5000, 10000, 15000]),
+            &snoozed(20000, 3),
+        )
+        .expect("schedule");
+
+        assert_eq!(schedule.base_interval_days, MAX_INTERVAL_DAYS);
+        assert_eq!(schedule.interval_days, MAX_INTERVAL_DAYS);
+    }
+
+    #[test]
+    fn follows_a_consistent_rhythm() {
+        let schedule = plan_watering(&completed(&[0, 5, 10, 15]), &[]);
+
+        assert_eq!(schedule.interval_days, 5);
+        assert_eq!(schedule.next_due_at, at(20));
+        assert_eq!(
+            schedule.explanation,
+            "interval shortened from 7 to 5 days: the last 4 completions came 5 days apart on average"
+        );
+    }
+
+    #[test]
+    fn ignores_an_inconsistent_rhythm() {
+        let schedule = plan_watering(&completed(&[0, 5, 14, 21]), &[]);
+
+        assert_eq!(schedule.interval_days, 7);
+        assert_eq!(schedule.next_due_at, at(28));
+    }
+
+    #[test]
+    fn lengthens_after_consecutive_snoozes() {
+        let schedule = plan_watering(&completed(&[0]), &snoozed(7, 3));
+
+        assert_eq!(schedule.interval_days, 10);
+        assert_eq!(schedule.next_due_at, at(10));
+        assert_eq!(
+            schedule.explanation,
+            "interval lengthened from 7 to 10 days after 3 consecutive snoozes"
+        );
+        assert!(schedule.is_change());
+    }
+
+    #[test]
+    fn ignores_snoozes_before_the_last_completion() {
+        let mut history = completed(&[0]);
+        history.push(TaskCompletion {
+            id: 9,
+            due_at: at(10),
+            completed_at: at(10),
+        });
+
+        let schedule = plan_watering(&history, &snoozed(7, 3));
+
+        assert_eq!(schedule.interval_days, 7);
+        assert_eq!(schedule.next_due_at, at(17));
+    }
+
+    #[test]
+    fn keeps_a_snoozed_due_date() {
+        let schedule = plan_watering(&[], &snoozed(7, 2));
+
+        assert_eq!(schedule.interval_days, 7);
+        assert_eq!(schedule.next_due_at, at(9));
+    }
+
+    #[test]
+    fn caps_the_adjustment() {
+        let schedule = plan_watering(&[], &snoozed(7, 12));
+
+        assert_eq!(schedule.interval_days, 14);
+        assert_eq!(schedule.next_due_at, at(19));
+        assert!(schedule.explanation.contains("capped at 14 days"));
+    }
+
+    #[test]
+    fn falls_back_to_the_profile() {
+        let profile = CareProfile::default();
+
+        let schedule =
+            plan(&task(TaskType::Fertilization, None), &profile, &[], &[]).expect("schedule");
+        assert_eq!(schedule.base_interval_days, 30);
+        assert_eq!(
+            schedule.explanation,
+            "no interval set, using the 30-day fertilization default; interval kept at 30 days"
+        );
+
+        assert!(plan(&task(TaskType::Custom, None), &profile, &[], &[]).is_none());
+    }
+
+    #[test]
+    fn does_not_depend_on_history_order() {
+        let completions = completed(&[0, 5, 10, 15]);
+        let snoozes = snoozed(20, 3);
+        let mut shuffled_completions = completed(&[0, 5, 10, 15]);
+        shuffled_completions.reverse();
+        shuffled_completions.swap(0, 2);
+        let mut shuffled_snoozes = snoozed(20, 3);
+        shuffled_snoozes.reverse();
+
+        assert_eq!(
+            plan_watering(&completions, &snoozes),
+            plan_watering(&shuffled_completions, &shuffled_snoozes)
+        );
+    }
+
+    #[sqlx::test]
+    async fn uses_the_species_defaults(pool: PgPool) {
+        plant_type::seed_catalog(&pool).await.expect("seed");
+        let fern = plant_type::search_plant_types(&pool, Some("Nephrolepis"))
+            .await
+            .expect("search")
+            .remove(0);
+        let user_id = sqlx::query_scalar!(
+            r#"INSERT INTO "user" (email, password_hash) VALUES ('a@example.com', 'unused') RETURNING id"#
+        )
+        .fetch_one(&pool)
+        .await
+        .expect("user");
+        let plant = plant::create_plant(
+            &pool,
+            &PlantInput {
+                plant_type_id: Some(fern.id),
+                ..PlantInput::new("Fern")
+            },
+            user_id,
+        )
+        .await
+        .expect("plant");
+        let task = task::create_task(
+            &pool,
+            user_id,
+            &TaskInput {
+                name: "Water".to_string(),
+                task_type: TaskType::Watering,
+                plant_id: Some(plant.id),
+                due_at: start(),
+                interval_days: None,
+            },
+        )
+        .await
+        .expect("task");
+
+        let schedule = get_schedule(&pool, user_id, task.id)
+            .await
+            .expect("schedule");
+
+        assert_eq!(schedule.base_interval_days, fern.watering_interval_days);
+    }
+}
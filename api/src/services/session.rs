@@ -0,0 +1,336 @@
+use std::time::Duration;
+
+use anyhow::anyhow;
+use sqlx::{
+    PgPool, Postgres, Transaction,
+    types::{
+        Uuid,
+        chrono::{DateTime, Utc},
+    },
+};
+
+use crate::{
+    entities::{Session, SessionDb},
+    services::{
+        auth::AuthError,
+        token::{generate_token, hash_token},
+    },
+};
+
+const REFRESH_TOKEN_TTL: Duration = Duration::from_secs(30 * 24 * 3600);
+
+pub struct RefreshToken {
+    pub token: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// The outcome of exchanging a refresh token for a new one.
+pub struct RotatedSession {
+    pub session_id: Uuid,
+    pub email: String,
+    pub refresh_token: RefreshToken,
+}
+
+/// Starts a new session (one per device) and issues its first refresh token.
+pub async fn create_session(
+    pool: &PgPool,
+    user_id: i32,
+    device_name: Option<&str>,
+) -> Result<(Uuid, RefreshToken), AuthError> {
+    let mut tx = pool.begin().await.map_err(internal)?;
+
+    let session_id = sqlx::query_scalar!(
+        "INSERT INTO session (user_id, device_name) VALUES ($1, $2) RETURNING id",
+        user_id,
+        device_name,
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(internal)?;
+
+    let refresh_token = issue_refresh_token(&mut tx, session_id).await?;
+
+    tx.commit().await.map_err(internal)?;
+
+    Ok((session_id, refresh_token))
+}
+
+/// Exchanges a refresh token for a new one from the same session.
+///
+/// Every refresh token can be used exactly once. Presenting a token that has
+/// already been exchanged means it was leaked, so the whole session is revoked
+/// and both the attacker and the legitimate client have to log in again.
+pub async fn rotate_refresh_token(pool: &PgPool, token: &str) -> Result<RotatedSession, AuthError> {
+    let mut tx = pool.begin().await.map_err(internal)?;
+
+    let record = sqlx::query!(
+        r#"SELECT refresh_token.id, refresh_token.expires_at, refresh_token.used_at,
+            session.id AS session_id, session.revoked_at, "user".email
+        FROM refresh_token
+        JOIN session ON session.id = refresh_token.session_id
+        JOIN "user" ON "user".id = session.user_id
+        WHERE refresh_token.token_hash = $1
+        FOR UPDATE OF refresh_token, session"#,
+        hash_token(token),
+    )
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(internal)?
+    .ok_or(AuthError::InvalidToken)?;
+
+    if record.revoked_at.is_some() {
+        return Err(AuthError::InvalidToken);
+    }
+
+    if record.used_at.is_some() {
+        sqlx::query!(
+            "UPDATE session SET revoked_at = now() WHERE id = $1",
+            record.session_id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(internal)?;
+        tx.commit().await.map_err(internal)?;
+
+        log::warn!(
+            "Refresh token reuse detected, revoked session {}",
+            record.session_id
+        );
+        return Err(AuthError::TokenReused);
+    }
+
+    if record.expires_at <= Utc::now() {
+        return Err(AuthError::InvalidToken);
+    }
+
+    sqlx::query!(
+        "UPDATE refresh_token SET used_at = now() WHERE id = $1",
+        record.id
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(internal)?;
+
+    sqlx::query!(
+        "UPDATE session SET last_used_at = now() WHERE id = $1",
+        record.session_id
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(internal)?;
+
+    let refresh_token = issue_refresh_token(&mut tx, record.session_id).await?;
+
+    tx.commit().await.map_err(internal)?;
+
+    Ok(RotatedSession {
+        session_id: record.session_id,
+        email: record.email,
+        refresh_token,
+    })
+}
+
+/// Whether the session exists and has not been revoked. The access tokens of
+/// a revoked session are refused even before they expire.
+pub async fn is_active(pool: &PgPool, session_id: Uuid) -> Result<bool, AuthError> {
+    sqlx::query_scalar!(
+        r#"SELECT EXISTS (
+            SELECT 1 FROM session WHERE id = $1 AND revoked_at IS NULL
+        ) AS "active!""#,
+        session_id
+    )
+    .fetch_one(pool)
+    .await
+    .map_err(internal)
+}
+
+pub async fn get_sessions(pool: &PgPool, user_id: i32) -> Result<Vec<Session>, AuthError> {
+    let sessions = sqlx::query_as!(
+        SessionDb,
+        "SELECT * FROM session WHERE user_id = $1 AND revoked_at IS NULL ORDER BY last_used_at DESC",
+        user_id
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(internal)?
+    .into_iter()
+    .map(Session::from)
+    .collect();
+
+    Ok(sessions)
+}
+
+/// Revokes a session of the given user. Returns `false` if the user has no
+/// active session with that id.
+pub async fn revoke_session(
+    pool: &PgPool,
+    user_id: i32,
+    session_id: Uuid,
+) -> Result<bool, AuthError> {
+    let result = sqlx::query!(
+        "UPDATE session SET revoked_at = now()
+        WHERE id = $1 AND user_id = $2 AND revoked_at IS NULL",
+        session_id,
+        user_id
+    )
+    .execute(pool)
+    .await
+    .map_err(internal)?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+async fn issue_refresh_token(
+    tx: &mut Transaction<'_, Postgres>,
+    session_id: Uuid,
+) -> Result<RefreshToken, AuthError> {
+    let token = generate_token();
+    let expires_at = Utc::now() + REFRESH_TOKEN_TTL;
+
+    sqlx::query!(
+        "INSERT INTO refresh_token (session_id, token_hash, expires_at) VALUES ($1, $2, $3)",
+        session_id,
+        hash_token(&token),
+        expires_at,
+    )
+    .execute(&mut **tx)
+    .await
+    .map_err(internal)?;
+
+    Ok(RefreshToken { token, expires_at })
+}
+
+fn internal(e: sqlx::Error) -> AuthError {
+    AuthError::InternalError(anyhow!(e))
+}
+
+#[cfg(test)]
+mod tests {
+    use jsonwebtoken::Algorithm;
+
+    use super::*;
+    use crate::services::{
+        auth::{self, AuthTokens},
+        jwt::{JwtKey, JwtKeys},
+    };
+
+    fn keys() -> JwtKeys {
+        let key = JwtKey::hmac(
+            "test",
+            Algorithm::HS256,
+            b"test-secret-test-secret-test-secret",
+        )
+        .expect("key");
+        JwtKeys::new("test", vec![key]).expect("keys")
+    }
+
+    fn session_of(keys: &JwtKeys, tokens: &AuthTokens) -> Uuid {
+        keys.verify(&tokens.access_token.token).expect("claims").sid
+    }
+
+    #[sqlx::test]
+    async fn refresh_tokens_rotate_once(pool: PgPool) {
+        let keys = keys();
+        let tokens = auth::register(&pool, &keys, "a@example.com", "password123", None)
+            .await
+            .expect("register");
+        let session_id = session_of(&keys, &tokens);
+
+        let rotated = rotate_refresh_token(&pool, &tokens.refresh_token.token)
+            .await
+            .expect("rotate");
+        assert_eq!(rotated.session_id, session_id);
+        assert_ne!(rotated.refresh_token.token, tokens.refresh_token.token);
+        assert!(is_active(&pool, session_id).await.expect("active"));
+
+        // The new token works once as well.
+        rotate_refresh_token(&pool, &rotated.refresh_token.token)
+            .await
+            .expect("rotate");
+    }
+
+    #[sqlx::test]
+    async fn reused_refresh_token_revokes_the_session(pool: PgPool) {
+        let keys = keys();
+        let tokens = auth::register(&pool, &keys, "a@example.com", "password123", None)
+            .await
+            .expect("register");
+        let session_id = session_of(&keys, &tokens);
+        let rotated = rotate_refresh_token(&pool, &tokens.refresh_token.token)
+            .await
+            .expect("rotate");
+
+        assert!(matches!(
+            rotate_refresh_token(&pool, &tokens.refresh_token.token).await,
+            Err(AuthError::TokenReused)
+        ));
+
+        // Neither the stolen nor the legitimate client gets in any more.
+        assert!(!is_active(&pool, session_id).await.expect("active"));
+        assert!(matches!(
+            rotate_refresh_token(&pool, &rotated.refresh_token.token).await,
+            Err(AuthError::InvalidToken)
+        ));
+    }
+
+    #[sqlx::test]
+    async fn logout_and_revocation_end_sessions(pool: PgPool) {
+        let keys = keys();
+        let laptop = auth::register(&pool, &keys, "a@example.com", "password123", None)
+            .await
+            .expect("register");
+        let phone = auth::login(&pool, &keys, "a@example.com", "password123", None)
+            .await
+            .expect("login");
+        let intruder = sqlx::query_scalar!(
+            r#"INSERT INTO "user" (email, password_hash) VALUES ('b@example.com', 'unused') RETURNING id"#
+        )
+        .fetch_one(&pool)
+        .await
+        .expect("user");
+        let user_id = sqlx::query_scalar!(r#"SELECT id FROM "user" WHERE email = 'a@example.com'"#)
+            .fetch_one(&pool)
+            .await
+            .expect("user");
+        let laptop_session = session_of(&keys, &laptop);
+        let phone_session = session_of(&keys, &phone);
+
+        // Logging out revokes the caller's own session.
+        assert!(
+            revoke_session(&pool, user_id, laptop_session)
+                .await
+                .expect("logout")
+        );
+        assert!(!is_active(&pool, laptop_session).await.expect("active"));
+        assert!(is_active(&pool, phone_session).await.expect("active"));
+        assert!(matches!(
+            rotate_refresh_token(&pool, &laptop.refresh_token.token).await,
+            Err(AuthError::InvalidToken)
+        ));
+
+        // Sessions of other users cannot be revoked.
+        assert!(
+            !revoke_session(&pool, intruder, phone_session)
+                .await
+                .expect("revoke")
+        );
+        assert!(is_active(&pool, phone_session).await.expect("active"));
+
+        // Revoking another session of the user from the laptop.
+        assert!(
+            revoke_session(&pool, user_id, phone_session)
+                .await
+                .expect("revoke")
+        );
+        assert!(!is_active(&pool, phone_session).await.expect("active"));
+        assert!(matches!(
+            rotate_refresh_token(&pool, &phone.refresh_token.token).await,
+            Err(AuthError::InvalidToken)
+        ));
+        assert_eq!(
+            get_sessions(&pool, user_id).await.expect("sessions").len(),
+            0
+        );
+        assert!(!is_active(&pool, Uuid::new_v4()).await.expect("active"));
+    }
+}
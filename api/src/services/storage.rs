@@ -0,0 +1,203 @@
+//! Blob storage for uploaded files. The backend is picked on startup by
+//! `STORAGE_BACKEND`:
+//!
+//! - `local` (default) keeps files below `STORAGE_DIR` (default `uploads`).
+//! - `s3` talks to S3 or any compatible server such as MinIO. It is configured
+//!   through the usual `AWS_*` variables, e.g. `AWS_BUCKET`, `AWS_ENDPOINT`,
+//!   `AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY` and, for a plain-http
+//!   endpoint, `AWS_ALLOW_HTTP=true`.
+
+use std::{io::ErrorKind, path::PathBuf, sync::Arc};
+
+use anyhow::{Context, bail};
+use async_trait::async_trait;
+use bytes::Bytes;
+use object_store::{
+    ObjectStoreExt,
+    aws::{AmazonS3, AmazonS3Builder},
+    path::Path as ObjectPath,
+};
+
+/// A flat key-value store for blobs. Keys are `/`-separated relative paths
+/// chosen by the services, never by clients.
+#[async_trait]
+pub trait Storage: Send + Sync {
+    async fn put(&self, key: &str, data: Bytes) -> anyhow::Result<()>;
+
+    async fn get(&self, key: &str) -> anyhow::Result<Bytes>;
+
+    /// Removes the blob. Removing a missing key is not an error.
+    async fn delete(&self, key: &str) -> anyhow::Result<()>;
+}
+
+pub fn from_env() -> anyhow::Result<Arc<dyn Storage>> {
+    let backend = std::env::var("STORAGE_BACKEND").unwrap_or_else(|_| "local".to_string());
+
+    match backend.as_str() {
+        "local" => {
+            let root = std::env::var("STORAGE_DIR").unwrap_or_else(|_| "uploads".to_string());
+            Ok(Arc::new(LocalStorage::new(root)))
+        }
+        "s3" => Ok(Arc::new(S3Storage::from_env()?)),
+        other => bail!("Unknown STORAGE_BACKEND {other}, expected local or s3"),
+    }
+}
+
+/// Stores blobs as files below a root directory.
+pub struct LocalStorage {
+    root: PathBuf,
+}
+
+impl LocalStorage {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        LocalStorage { root: root.into() }
+    }
+
+    fn path(&self, key: &str) -> anyhow::Result<PathBuf> {
+        if key.is_empty() || key.split('/').any(|part| part.is_empty() || part == "..") {
+            bail!("Invalid storage key {key}");
+        }
+        Ok(self.root.join(key))
+    }
+}
+
+#[async_trait]
+impl Storage for LocalStorage {
+    async fn put(&self, key: &str, data: Bytes) -> anyhow::Result<()> {
+        let path = self.path(key)?;
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .with_context(|| format!("Could not create {}", parent.display()))?;
+        }
+
+        // Write next to the target and rename, so readers never see a
+        // partially written file.
+        let partial = path.with_extension("partial");
+        tokio::fs::write(&partial, &data)
+            .await
+            .with_context(|| format!("Could not write {}", partial.display()))?;
+        tokio::fs::rename(&partial, &path)
+            .await
+            .with_context(|| format!("Could not write {}", path.display()))?;
+
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> anyhow::Result<Bytes> {
+        let path = self.path(key)?;
+        let data = tokio::fs::read(&path)
+            .await
+            .with_context(|| format!("Could not read {}", path.display()))?;
+
+        Ok(Bytes::from(data))
+    }
+
+    async fn delete(&self, key: &str) -> anyhow::Result<()> {
+        let path = self.path(key)?;
+        match tokio::fs::remove_file(&path).await {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e).with_context(|| format!("Could not delete {}", path.display())),
+        }
+    }
+}
+
+/// Stores blobs in an S3 bucket.
+pub struct S3Storage {
+    store: AmazonS3,
+}
+
+impl S3Storage {
+    pub fn from_env() -> anyhow::Result<Self> {
+        let store = AmazonS3Builder::from_env()
+            .build()
+            .context("Invalid S3 storage configuration")?;
+
+        Ok(S3Storage { store })
+    }
+}
+
+#[async_trait]
+impl Storage for S3Storage {
+    async fn put(&self, key: &str, data: Bytes) -> anyhow::Result<()> {
+        self.store
+            .put(&ObjectPath::from(key), data.into())
+            .await
+            .with_context(|| format!("Could not upload {key}"))?;
+
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> anyhow::Result<Bytes> {
+        let data = self
+            .store
+            .get(&ObjectPath::from(key))
+            .await
+            .with_context(|| format!("Could not download {key}"))?
+            .bytes()
+            .await
+            .with_context(|| format!("Could not download {key}"))?;
+
+        Ok(data)
+    }
+
+    async fn delete(&self, key: &str) -> anyhow::Result<()> {
+        match self.store.delete(&ObjectPath::from(key)).await {
+            Ok(()) | Err(object_store::Error::NotFound { .. }) => Ok(()),
+            Err(e) => Err(e).with_context(|| format!("Could not delete {key}")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_storage() -> LocalStorage {
+        LocalStorage::new(std::env::temp_dir().join(format!("storage-{}", uuid::Uuid::new_v4())))
+    }
+
+    async fn round_trip(storage: &dyn Storage) {
+        let key = format!("tests/{}.txt", uuid::Uuid::new_v4());
+
+        storage
+            .put(&key, Bytes::from_static(b"first"))
+            .await
+            .expect("put");
+        storage
+            .put(&key, Bytes::from_static(b"second"))
+            .await
+            .expect("overwrite");
+        assert_eq!(storage.get(&key).await.expect("get"), "second");
+
+        storage.delete(&key).await.expect("delete");
+        assert!(storage.get(&key).await.is_err());
+        storage.delete(&key).await.expect("delete is idempotent");
+    }
+
+    #[tokio::test]
+    async fn local_storage_round_trip() {
+        round_trip(&temp_storage()).await;
+    }
+
+    #[tokio::test]
+    async fn local_storage_rejects_escaping_keys() {
+        let storage = temp_storage();
+
+        for key in ["", "../secret", "a/../../b", "/etc/passwd", "a//b"] {
+            assert!(
+                storage.put(key, Bytes::from_static(b"x")).await.is_err(),
+                "{key}"
+            );
+        }
+    }
+
+    /// Runs against the bucket configured in the `AWS_*` variables, e.g. the
+    /// MinIO service from `docker-compose.yml`.
+    #[tokio::test]
+    #[ignore = "needs an S3-compatible server"]
+    async fn s3_storage_round_trip() {
+        round_trip(&S3Storage::from_env().expect("S3 configuration")).await;
+    }
+}
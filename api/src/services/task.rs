@@ -0,0 +1,464 @@
+use std::time::Duration;
+
+use sqlx::{
+    PgConnection, Pool, Postgres,
+    types::chrono::{DateTime, Utc},
+};
+
+use crate::{
+    entities::{Task, TaskCompletion, TaskCompletionDb, TaskDb, TaskType},
+    services::error::ServiceError,
+};
+
+// Like plants, tasks are scoped by `owner_id` and foreign tasks are reported
+// as missing.
+
+pub const MAX_SNOOZE_DAYS: i64 = 30;
+/// Also enforced by the database.
+pub const MAX_INTERVAL_DAYS: i32 = 3650;
+
+pub struct TaskInput {
+    pub name: String,
+    pub task_type: TaskType,
+    pub plant_id: Option<i32>,
+    pub due_at: DateTime<Utc>,
+    pub interval_days: Option<i32>,
+}
+
+#[derive(Default)]
+pub struct TaskFilter {
+    pub plant_id: Option<i32>,
+    pub done: Option<bool>,
+    pub due_before: Option<DateTime<Utc>>,
+}
+
+pub fn days(days: i64) -> Duration {
+    Duration::from_secs(days.max(0) as u64 * 24 * 60 * 60)
+}
+
+pub async fn create_task(
+    pool: &Pool<Postgres>,
+    user_id: i32,
+    input: &TaskInput,
+) -> Result<Task, ServiceError> {
+    validate(pool, user_id, input).await?;
+
+    let task = sqlx::query_as!(
+        TaskDb,
+        "INSERT INTO task (owner_id, name, task_type, plant_id, due_at, interval_days)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING *",
+        user_id,
+        input.name.trim(),
+        input.task_type.as_str(),
+        input.plant_id,
+        input.due_at,
+        input.interval_days,
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(Task::from(task))
+}
+
+pub async fn get_task(
+    pool: &Pool<Postgres>,
+    user_id: i32,
+    task_id: i32,
+) -> Result<Task, ServiceError> {
+    sqlx::query_as!(
+        TaskDb,
+        "SELECT * FROM task WHERE id = $1 AND owner_id = $2",
+        task_id,
+        user_id
+    )
+    .fetch_optional(pool)
+    .await?
+    .map(Task::from)
+    .ok_or(ServiceError::NotFound("Task"))
+}
+
+/// Returns the user's tasks, soonest due first.
+pub async fn get_tasks(
+    pool: &Pool<Postgres>,
+    user_id: i32,
+    filter: &TaskFilter,
+) -> Result<Vec<Task>, ServiceError> {
+    let tasks = sqlx::query_as!(
+        TaskDb,
+        "SELECT * FROM task
+        WHERE owner_id = $1
+        AND ($2::int IS NULL OR plant_id = $2)
+        AND ($3::boolean IS NULL OR done = $3)
+        AND ($4::timestamptz IS NULL OR due_at < $4)
+        ORDER BY due_at, id",
+        user_id,
+        filter.plant_id,
+        filter.done,
+        filter.due_before,
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(Task::from)
+    .collect();
+
+    Ok(tasks)
+}
+
+pub async fn update_task(
+    pool: &Pool<Postgres>,
+    user_id: i32,
+    task_id: i32,
+    input: &TaskInput,
+    done: bool,
+) -> Result<Task, ServiceError> {
+    validate(pool, user_id, input).await?;
+
+    sqlx::query_as!(
+        TaskDb,
+        "UPDATE task SET name = $1, task_type = $2, plant_id = $3, due_at = $4,
+            interval_days = $5, done = $6, updated_at = now()
+        WHERE id = $7 AND owner_id = $8
+        RETURNING *",
+        input.name.trim(),
+        input.task_type.as_str(),
+        input.plant_id,
+        input.due_at,
+        input.interval_days,
+        done,
+        task_id,
+        user_id,
+    )
+    .fetch_optional(pool)
+    .await?
+    .map(Task::from)
+    .ok_or(ServiceError::NotFound("Task"))
+}
+
+pub async fn delete_task(
+    pool: &Pool<Postgres>,
+    user_id: i32,
+    task_id: i32,
+) -> Result<(), ServiceError> {
+    let result = sqlx::query!(
+        "DELETE FROM task WHERE id = $1 AND owner_id = $2",
+        task_id,
+        user_id
+    )
+    .execute(pool)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(ServiceError::NotFound("Task"));
+    }
+
+    Ok(())
+}
+
+/// Records a completion. Recurring tasks are rescheduled `interval_days`
+/// after the completion, one-off tasks are marked as done.
+pub async fn complete_task(
+    pool: &Pool<Postgres>,
+    user_id: i32,
+    task_id: i32,
+    completed_at: DateTime<Utc>,
+) -> Result<Task, ServiceError> {
+    if completed_at > Utc::now() {
+        return Err(ServiceError::validation(
+            "completedAt",
+            "must not be in the future",
+        ));
+    }
+
+    let mut tx = pool.begin().await?;
+
+    let task = lock_pending_task(&mut tx, user_id, task_id).await?;
+    let (task, _) = record_completion(&mut tx, &task, completed_at).await?;
+
+    tx.commit().await?;
+
+    Ok(task)
+}
+
+/// Records a completion of a task locked by the caller and moves the task on.
+/// Returns the updated task and the id of the completion.
+pub async fn record_completion(
+    conn: &mut PgConnection,
+    task: &Task,
+    completed_at: DateTime<Utc>,
+) -> Result<(Task, i32), ServiceError> {
+    let (due_at, done) = match task.interval_days {
+        Some(interval) => {
+            let due_at = completed_at
+                .timestamp()
+                .checked_add(i64::from(interval) * 24 * 60 * 60)
+                .and_then(|due_at| {
+                    DateTime::from_timestamp(due_at, completed_at.timestamp_subsec_nanos())
+                })
+                .ok_or_else(|| ServiceError::validation("completedAt", "is out of range"))?;
+            (due_at, false)
+        }
+        None => (task.due_at, true),
+    };
+
+    let completion_id = sqlx::query_scalar!(
+        "INSERT INTO task_completion (task_id, due_at, completed_at) VALUES ($1, $2, $3)
+        RETURNING id",
+        task.id,
+        task.due_at,
+        completed_at,
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    let task = sqlx::query_as!(
+        TaskDb,
+        "UPDATE task SET due_at = $1, done = $2, updated_at = now() WHERE id = $3 RETURNING *",
+        due_at,
+        done,
+        task.id,
+    )
+    .fetch_one(conn)
+    .await?;
+
+    Ok((Task::from(task), completion_id))
+}
+
+/// Postpones a pending task by `snooze_days`, counted from now for overdue
+/// tasks and from the due date otherwise.
+pub async fn snooze_task(
+    pool: &Pool<Postgres>,
+    user_id: i32,
+    task_id: i32,
+    snooze_days: i64,
+) -> Result<Task, ServiceError> {
+    if !(1..=MAX_SNOOZE_DAYS).contains(&snooze_days) {
+        return Err(ServiceError::validation(
+            "days",
+            format!("must be between 1 and {}", MAX_SNOOZE_DAYS),
+        ));
+    }
+
+    let mut tx = pool.begin().await?;
+
+    let task = lock_pending_task(&mut tx, user_id, task_id).await?;
+    let due_at = task.due_at.max(Utc::now()) + days(snooze_days);
+
+    sqlx::query!(
+        "INSERT INTO task_snooze (task_id, from_due_at, to_due_at) VALUES ($1, $2, $3)",
+        task.id,
+        task.due_at,
+        due_at,
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    let task = sqlx::query_as!(
+        TaskDb,
+        "UPDATE task SET due_at = $1, updated_at = now() WHERE id = $2 RETURNING *",
+        due_at,
+        task.id,
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(Task::from(task))
+}
+
+/// Returns the completion history of a task, most recent first.
+pub async fn get_completions(
+    pool: &Pool<Postgres>,
+    user_id: i32,
+    task_id: i32,
+) -> Result<Vec<TaskCompletion>, ServiceError> {
+    get_task(pool, user_id, task_id).await?;
+
+    let completions = sqlx::query_as!(
+        TaskCompletionDb,
+        "SELECT * FROM task_completion WHERE task_id = $1 ORDER BY completed_at DESC, id DESC",
+        task_id
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(TaskCompletion::from)
+    .collect();
+
+    Ok(completions)
+}
+
+async fn lock_pending_task(
+    conn: &mut PgConnection,
+    user_id: i32,
+    task_id: i32,
+) -> Result<Task, ServiceError> {
+    let task = sqlx::query_as!(
+        TaskDb,
+        "SELECT * FROM task WHERE id = $1 AND owner_id = $2 FOR UPDATE",
+        task_id,
+        user_id
+    )
+    .fetch_optional(conn)
+    .await?
+    .map(Task::from)
+    .ok_or(ServiceError::NotFound("Task"))?;
+
+    if task.done {
+        return Err(ServiceError::conflict(
+            "TASK_ALREADY_DONE",
+            "Task is already done",
+        ));
+    }
+
+    Ok(task)
+}
+
+async fn validate(
+    pool: &Pool<Postgres>,
+    user_id: i32,
+    input: &TaskInput,
+) -> Result<(), ServiceError> {
+    if input.name.trim().is_empty() {
+        return Err(ServiceError::validation("name", "must not be empty"));
+    }
+    if input
+        .interval_days
+        .is_some_and(|interval| !(1..=MAX_INTERVAL_DAYS).contains(&interval))
+    {
+        return Err(ServiceError::validation(
+            "intervalDays",
+            format!("must be between 1 and {}", MAX_INTERVAL_DAYS),
+        ));
+    }
+    if let Some(plant_id) = input.plant_id {
+        sqlx::query_scalar!(
+            "SELECT id FROM plant WHERE id = $1 AND owner_id = $2",
+            plant_id,
+            user_id
+        )
+        .fetch_optional(pool)
+        .await?
+        .ok_or(ServiceError::NotFound("Plant"))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::PgPool;
+
+    use super::*;
+
+    async fn create_user(pool: &PgPool) -> i32 {
+        sqlx::query_scalar!(
+            r#"INSERT INTO "user" (email, password_hash) VALUES ('a@example.com', 'unused') RETURNING id"#
+        )
+        .fetch_one(pool)
+        .await
+        .expect("user")
+    }
+
+    fn input(interval_days: Option<i32>) -> TaskInput {
+        TaskInput {
+            name: "Water".to_string(),
+            task_type: TaskType::Watering,
+            plant_id: None,
+            due_at: Utc::now(),
+            interval_days,
+        }
+    }
+
+    #[sqlx::test]
+    async fn completing_a_recurring_task_reschedules_it(pool: PgPool) {
+        let user_id = create_user(&pool).await;
+        let mut overdue = input(Some(3));
+        overdue.due_at = Utc::now() - days(2);
+        let task = create_task(&pool, user_id, &overdue).await.expect("task");
+        let completed_at = task.due_at + days(1);
+
+        let task = complete_task(&pool, user_id, task.id, completed_at)
+            .await
+            .expect("complete");
+
+        assert!(!task.done);
+        assert_eq!(task.due_at, completed_at + days(3));
+        let completions = get_completions(&pool, user_id, task.id)
+            .await
+            .expect("completions");
+        assert_eq!(completions.len(), 1);
+        assert_eq!(completions[0].completed_at, completed_at);
+    }
+
+    #[sqlx::test]
+    async fn completing_a_one_off_task_marks_it_done(pool: PgPool) {
+        let user_id = create_user(&pool).await;
+        let task = create_task(&pool, user_id, &input(None))
+            .await
+            .expect("task");
+
+        let task = complete_task(&pool, user_id, task.id, Utc::now())
+            .await
+            .expect("complete");
+
+        assert!(task.done);
+        assert!(matches!(
+            complete_task(&pool, user_id, task.id, Utc::now()).await,
+            Err(ServiceError::Conflict { .. })
+        ));
+        assert!(matches!(
+            snooze_task(&pool, user_id, task.id, 1).await,
+            Err(ServiceError::Conflict { .. })
+        ));
+    }
+
+    #[sqlx::test]
+    async fn snoozing_moves_the_due_date(pool: PgPool) {
+        let user_id = create_user(&pool).await;
+        let mut upcoming = input(None);
+        upcoming.due_at = Utc::now() + days(2);
+        let task = create_task(&pool, user_id, &upcoming).await.expect("task");
+
+        let snoozed = snooze_task(&pool, user_id, task.id, 3)
+            .await
+            .expect("snooze");
+
+        assert_eq!(snoozed.due_at, task.due_at + days(3));
+        assert!(matches!(
+            snooze_task(&pool, user_id, task.id, MAX_SNOOZE_DAYS + 1).await,
+            Err(ServiceError::Validation(_))
+        ));
+    }
+
+    #[sqlx::test]
+    async fn intervals_and_completion_times_are_bounded(pool: PgPool) {
+        let user_id = create_user(&pool).await;
+
+        assert!(matches!(
+            create_task(&pool, user_id, &input(Some(i32::MAX))).await,
+            Err(ServiceError::Validation(_))
+        ));
+        let task = create_task(&pool, user_id, &input(Some(MAX_INTERVAL_DAYS)))
+            .await
+            .expect("task");
+        assert!(matches!(
+            complete_task(&pool, user_id, task.id, Utc::now() + days(1)).await,
+            Err(ServiceError::Validation(_))
+        ));
+
+        // The database enforces the same bound.
+        assert!(
+            sqlx::query!(
+                "UPDATE task SET interval_days = $1 WHERE id = $2",
+                MAX_INTERVAL_DAYS + 1,
+                task.id
+            )
+            .execute(&pool)
+            .await
+            .is_err()
+        );
+    }
+}
@@ -0,0 +1,477 @@
+//! Cross-user isolation: user B must never be able to see or modify anything
+//! owned by user A.
+
+use sqlx::{
+    PgPool,
+    types::chrono::{NaiveDateTime, Utc},
+};
+
+use crate::{
+    entities::TaskType,
+    services::{
+        automation, device, error::ServiceError, link, measurement, measurement::MeasurementFilter,
+        photo, photo::PhotoUpload, plant, plant::PlantInput, plant::PlantPatch,
+        plausibility::PlausibilityLimits, pot, pot::PotRegistration, session,
+        storage::LocalStorage, task, task::TaskFilter, task::TaskInput, timelapse,
+    },
+};
+
+async fn create_user(pool: &PgPool, email: &str) -> i32 {
+    sqlx::query_scalar!(
+        r#"INSERT INTO "user" (email, password_hash) VALUES ($1, 'unused') RETURNING id"#,
+        email
+    )
+    .fetch_one(pool)
+    .await
+    .expect("user")
+}
+
+async fn create_users(pool: &PgPool) -> (i32, i32) {
+    (
+        create_user(pool, "a@example.com").await,
+        create_user(pool, "b@example.com").await,
+    )
+}
+
+fn timestamp() -> NaiveDateTime {
+    NaiveDateTime::parse_from_str("2026-01-01 12:00:00", "%Y-%m-%d %H:%M:%S").expect("timestamp")
+}
+
+#[sqlx::test]
+async fn plants_are_invisible_to_other_users(pool: PgPool) {
+    let (user_a, user_b) = create_users(&pool).await;
+    let plant = plant::create_plant(&pool, &PlantInput::new("Monstera"), user_a)
+        .await
+        .expect("plant");
+
+    assert!(matches!(
+        plant::get_plant(&pool, user_b, plant.id).await,
+        Err(ServiceError::NotFound(_))
+    ));
+    assert!(
+        plant::get_plants(&pool, user_b, None)
+            .await
+            .expect("query")
+            .is_empty()
+    );
+    assert_eq!(
+        plant::get_plants(&pool, user_a, None)
+            .await
+            .expect("query")
+            .len(),
+        1
+    );
+}
+
+#[sqlx::test]
+async fn plants_cannot_be_updated_or_deleted_by_other_users(pool: PgPool) {
+    let (user_a, user_b) = create_users(&pool).await;
+    let plant = plant::create_plant(&pool, &PlantInput::new("Monstera"), user_a)
+        .await
+        .expect("plant");
+
+    assert!(matches!(
+        plant::update_plant(&pool, user_b, plant.id, &PlantInput::new("Stolen")).await,
+        Err(ServiceError::NotFound(_))
+    ));
+    assert!(matches!(
+        plant::patch_plant(
+            &pool,
+            user_b,
+            plant.id,
+            PlantPatch {
+                archived: Some(true),
+                ..Default::default()
+            }
+        )
+        .await,
+        Err(ServiceError::NotFound(_))
+    ));
+    assert!(matches!(
+        plant::delete_plant(&pool, user_b, plant.id).await,
+        Err(ServiceError::NotFound(_))
+    ));
+
+    let unchanged = plant::get_plant(&pool, user_a, plant.id)
+        .await
+        .expect("plant still exists");
+    assert_eq!(unchanged.name, "Monstera");
+    assert!(!unchanged.archived);
+
+    plant::delete_plant(&pool, user_a, plant.id)
+        .await
+        .expect("delete");
+}
+
+#[sqlx::test]
+async fn pots_are_invisible_to_other_users(pool: PgPool) {
+    let (user_a, user_b) = create_users(&pool).await;
+    let (pot, _) = pot::create_pot(&pool, user_a, &PotRegistration::default())
+        .await
+        .expect("pot");
+
+    assert!(matches!(
+        pot::get_pot(&pool, user_b, pot.id).await,
+        Err(ServiceError::NotFound(_))
+    ));
+    assert!(
+        pot::get_all_pots(&pool, user_b, false)
+            .await
+            .expect("query")
+            .is_empty()
+    );
+}
+
+#[sqlx::test]
+async fn foreign_plants_and_pots_cannot_be_linked(pool: PgPool) {
+    let (user_a, user_b) = create_users(&pool).await;
+    let plant_a = plant::create_plant(&pool, &PlantInput::new("Monstera"), user_a)
+        .await
+        .expect("plant");
+    let (pot_a, _) = pot::create_pot(&pool, user_a, &PotRegistration::default())
+        .await
+        .expect("pot");
+    let plant_b = plant::create_plant(&pool, &PlantInput::new("Ficus"), user_b)
+        .await
+        .expect("plant");
+    let (pot_b, _) = pot::create_pot(&pool, user_b, &PotRegistration::default())
+        .await
+        .expect("pot");
+
+    assert!(matches!(
+        link::link_plant_to_pot(&pool, user_b, plant_a.id, pot_b.id).await,
+        Err(ServiceError::NotFound("Plant"))
+    ));
+    assert!(matches!(
+        link::link_plant_to_pot(&pool, user_b, plant_b.id, pot_a.id).await,
+        Err(ServiceError::NotFound("Pot"))
+    ));
+
+    let assignments =
+        sqlx::query_scalar!(r#"SELECT COUNT(*) AS "count!" FROM plant_pot_assignment"#)
+            .fetch_one(&pool)
+            .await
+            .expect("count");
+    assert_eq!(assignments, 0);
+}
+
+#[sqlx::test]
+async fn links_cannot_be_removed_by_other_users(pool: PgPool) {
+    let (user_a, user_b) = create_users(&pool).await;
+    let plant = plant::create_plant(&pool, &PlantInput::new("Monstera"), user_a)
+        .await
+        .expect("plant");
+    let (pot, _) = pot::create_pot(&pool, user_a, &PotRegistration::default())
+        .await
+        .expect("pot");
+    link::link_plant_to_pot(&pool, user_a, plant.id, pot.id)
+        .await
+        .expect("link");
+
+    assert!(matches!(
+        link::unlink_plant_from_pot(&pool, user_b, plant.id, pot.id).await,
+        Err(ServiceError::NotFound(_))
+    ));
+    link::unlink_plant_from_pot(&pool, user_a, plant.id, pot.id)
+        .await
+        .expect("unlink");
+}
+
+#[sqlx::test]
+async fn measurements_are_invisible_to_other_users(pool: PgPool) {
+    let (user_a, user_b) = create_users(&pool).await;
+    let (pot, _) = pot::create_pot(&pool, user_a, &PotRegistration::default())
+        .await
+        .expect("pot");
+    measurement::create_measurement(
+        &pool,
+        &PlausibilityLimits::without_age_limit(),
+        pot.id,
+        40.0,
+        21.0,
+        300.0,
+        55.0,
+        90,
+        timestamp(),
+    )
+    .await
+    .expect("measurement");
+
+    let filter = MeasurementFilter {
+        pot_id: Some(pot.id),
+        ..Default::default()
+    };
+    assert!(matches!(
+        measurement::get_measurements(&pool, user_b, &filter).await,
+        Err(ServiceError::NotFound(_))
+    ));
+    assert!(
+        measurement::get_measurements(&pool, user_b, &MeasurementFilter::default())
+            .await
+            .expect("query")
+            .measurements
+            .is_empty()
+    );
+    assert_eq!(
+        measurement::get_measurements(&pool, user_a, &filter)
+            .await
+            .expect("own pot")
+            .total,
+        1
+    );
+}
+
+#[sqlx::test]
+async fn device_credentials_cannot_be_managed_by_other_users(pool: PgPool) {
+    let (user_a, user_b) = create_users(&pool).await;
+    let (pot, credential) = pot::create_pot(&pool, user_a, &PotRegistration::default())
+        .await
+        .expect("pot");
+
+    assert!(matches!(
+        device::get_credentials(&pool, user_b, pot.id).await,
+        Err(ServiceError::NotFound(_))
+    ));
+    assert!(matches!(
+        device::rotate_credential(&pool, user_b, pot.id, true).await,
+        Err(ServiceError::NotFound(_))
+    ));
+    assert!(matches!(
+        device::revoke_credential(&pool, user_b, pot.id, credential.id).await,
+        Err(ServiceError::NotFound(_))
+    ));
+    assert_eq!(
+        device::verify_credential(&pool, &credential.credential())
+            .await
+            .expect("query"),
+        Some(pot.id)
+    );
+}
+
+#[sqlx::test]
+async fn sessions_cannot_be_revoked_by_other_users(pool: PgPool) {
+    let (user_a, user_b) = create_users(&pool).await;
+    let (session_id, _) = session::create_session(&pool, user_a, None)
+        .await
+        .expect("session");
+
+    assert!(
+        !session::revoke_session(&pool, user_b, session_id)
+            .await
+            .expect("query")
+    );
+    assert_eq!(
+        session::get_sessions(&pool, user_a)
+            .await
+            .expect("query")
+            .len(),
+        1
+    );
+}
+
+#[sqlx::test]
+async fn tasks_are_invisible_to_other_users(pool: PgPool) {
+    let (user_a, user_b) = create_users(&pool).await;
+    let plant = plant::create_plant(&pool, &PlantInput::new("Monstera"), user_a)
+        .await
+        .expect("plant");
+    let input = TaskInput {
+        name: "Water".to_string(),
+        task_type: TaskType::Watering,
+        plant_id: Some(plant.id),
+        due_at: Utc::now(),
+        interval_days: Some(7),
+    };
+    let task = task::create_task(&pool, user_a, &input)
+        .await
+        .expect("task");
+
+    assert!(matches!(
+        task::create_task(&pool, user_b, &input).await,
+        Err(ServiceError::NotFound("Plant"))
+    ));
+    assert!(matches!(
+        task::get_task(&pool, user_b, task.id).await,
+        Err(ServiceError::NotFound(_))
+    ));
+    assert!(
+        task::get_tasks(&pool, user_b, &TaskFilter::default())
+            .await
+            .expect("query")
+            .is_empty()
+    );
+    assert!(matches!(
+        task::complete_task(&pool, user_b, task.id, Utc::now()).await,
+        Err(ServiceError::NotFound(_))
+    ));
+    assert!(matches!(
+        task::snooze_task(&pool, user_b, task.id, 1).await,
+        Err(ServiceError::NotFound(_))
+    ));
+    assert!(matches!(
+        task::get_completions(&pool, user_b, task.id).await,
+        Err(ServiceError::NotFound(_))
+    ));
+    assert!(matches!(
+        task::delete_task(&pool, user_b, task.id).await,
+        Err(ServiceError::NotFound(_))
+    ));
+
+    task::delete_task(&pool, user_a, task.id)
+        .await
+        .expect("delete");
+}
+
+#[sqlx::test]
+async fn task_automations_cannot_be_reverted_by_other_users(pool: PgPool) {
+    let (user_a, user_b) = create_users(&pool).await;
+    let plant = plant::create_plant(&pool, &PlantInput::new("Monstera"), user_a)
+        .await
+        .expect("plant");
+    let (pot, _) = pot::create_pot(&pool, user_a, &PotRegistration::default())
+        .await
+        .expect("pot");
+    link::link_plant_to_pot(&pool, user_a, plant.id, pot.id)
+        .await
+        .expect("link");
+    // The readings are dated in the past, the link has to cover them.
+    sqlx::query!(
+        "UPDATE plant_pot_assignment SET linked_at = '2025-01-01Z' WHERE pot_id = $1",
+        pot.id
+    )
+    .execute(&pool)
+    .await
+    .expect("backdate");
+    let input = TaskInput {
+        name: "Water".to_string(),
+        task_type: TaskType::Watering,
+        plant_id: Some(plant.id),
+        due_at: timestamp().and_utc(),
+        interval_days: Some(7),
+    };
+    task::create_task(&pool, user_a, &input)
+        .await
+        .expect("task");
+    for (minutes, moisture) in [(0, 20.0), (30, 45.0)] {
+        let at = timestamp() + std::time::Duration::from_secs(minutes * 60);
+        measurement::create_measurement(
+            &pool,
+            &PlausibilityLimits::without_age_limit(),
+            pot.id,
+            moisture,
+            21.0,
+            300.0,
+            55.0,
+            90,
+            at,
+        )
+        .await
+        .expect("measurement");
+    }
+
+    let automations = automation::get_automations(&pool, user_a, None)
+        .await
+        .expect("automations");
+    assert_eq!(automations.len(), 1);
+    assert!(
+        automation::get_automations(&pool, user_b, None)
+            .await
+            .expect("query")
+            .is_empty()
+    );
+    assert!(matches!(
+        automation::revert_automation(&pool, user_b, automations[0].id).await,
+        Err(ServiceError::NotFound(_))
+    ));
+}
+
+#[sqlx::test]
+async fn photos_are_invisible_to_other_users(pool: PgPool) {
+    let (user_a, user_b) = create_users(&pool).await;
+    let plant = plant::create_plant(&pool, &PlantInput::new("Monstera"), user_a)
+        .await
+        .expect("plant");
+    let storage =
+        LocalStorage::new(std::env::temp_dir().join(format!("photos-{}", uuid::Uuid::new_v4())));
+    let mut png = std::io::Cursor::new(Vec::new());
+    image::RgbImage::new(16, 16)
+        .write_to(&mut png, image::ImageFormat::Png)
+        .expect("png");
+    let upload = || PhotoUpload {
+        data: png.get_ref().clone().into(),
+        captured_at: None,
+    };
+
+    let photo = photo::upload_photo(&pool, &storage, user_a, plant.id, upload())
+        .await
+        .expect("photo");
+
+    assert!(matches!(
+        photo::upload_photo(&pool, &storage, user_b, plant.id, upload()).await,
+        Err(ServiceError::NotFound(_))
+    ));
+    assert!(matches!(
+        photo::get_photos(&pool, user_b, plant.id, None, None).await,
+        Err(ServiceError::NotFound(_))
+    ));
+    assert!(matches!(
+        photo::get_photo(&pool, user_b, plant.id, photo.id).await,
+        Err(ServiceError::NotFound(_))
+    ));
+    assert!(matches!(
+        photo::delete_photo(&pool, &storage, user_b, plant.id, photo.id).await,
+        Err(ServiceError::NotFound(_))
+    ));
+    photo::delete_plant_photos(&pool, &storage, user_b, plant.id)
+        .await
+        .expect("no-op");
+
+    let page = photo::get_photos(&pool, user_a, plant.id, None, None)
+        .await
+        .expect("photos");
+    assert_eq!(page.total, 1);
+}
+
+#[sqlx::test]
+async fn timelapses_are_invisible_to_other_users(pool: PgPool) {
+    let (user_a, user_b) = create_users(&pool).await;
+    let plant = plant::create_plant(&pool, &PlantInput::new("Monstera"), user_a)
+        .await
+        .expect("plant");
+    let storage =
+        LocalStorage::new(std::env::temp_dir().join(format!("timelapse-{}", uuid::Uuid::new_v4())));
+    let mut png = std::io::Cursor::new(Vec::new());
+    image::RgbImage::new(16, 16)
+        .write_to(&mut png, image::ImageFormat::Png)
+        .expect("png");
+    for date in ["2026-03-01T08:00:00Z", "2026-03-02T08:00:00Z"] {
+        let captured_at = NaiveDateTime::parse_from_str(date, "%Y-%m-%dT%H:%M:%SZ")
+            .expect("date")
+            .and_utc();
+        let upload = PhotoUpload {
+            data: png.get_ref().clone().into(),
+            captured_at: Some(captured_at),
+        };
+        photo::upload_photo(&pool, &storage, user_a, plant.id, upload)
+            .await
+            .expect("photo");
+    }
+
+    let request = timelapse::TimelapseRequest::default();
+    assert!(matches!(
+        timelapse::enqueue_timelapse(&pool, user_b, plant.id, &request).await,
+        Err(ServiceError::NotFound(_))
+    ));
+    let queued = timelapse::enqueue_timelapse(&pool, user_a, plant.id, &request)
+        .await
+        .expect("timelapse");
+
+    assert!(matches!(
+        timelapse::get_timelapses(&pool, user_b, plant.id).await,
+        Err(ServiceError::NotFound(_))
+    ));
+    assert!(matches!(
+        timelapse::get_timelapse(&pool, user_b, plant.id, queued.id).await,
+        Err(ServiceError::NotFound(_))
+    ));
+}
@@ -0,0 +1,750 @@
+use std::{io::Cursor, sync::Arc, time::Duration};
+
+use bytes::Bytes;
+use image::{
+    Delay, Frame, GrayImage, RgbaImage,
+    codecs::gif::{GifEncoder, Repeat},
+    imageops::{self, FilterType},
+};
+use log::{error, info};
+use sqlx::{
+    Pool, Postgres,
+    types::chrono::{DateTime, Utc},
+};
+
+use crate::{
+    entities::{PlantPhoto, PlantPhotoDb, Timelapse, TimelapseDb},
+    services::{error::ServiceError, photo, storage::Storage},
+};
+
+pub const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Upper bound on the frames of one time-lapse; longer series are sampled.
+pub const MAX_FRAMES: usize = 120;
+
+/// Frames are scaled so that their longer side has this many pixels.
+pub const FRAME_SIZE: u32 = 480;
+
+pub const DEFAULT_FRAME_DELAY_MS: i32 = 500;
+pub const MIN_FRAME_DELAY_MS: i32 = 20;
+pub const MAX_FRAME_DELAY_MS: i32 = 10_000;
+
+#[derive(Default)]
+pub struct TimelapseRequest {
+    /// Defaults to the first photo of the plant.
+    pub start: Option<DateTime<Utc>>,
+    /// Defaults to the last photo of the plant.
+    pub end: Option<DateTime<Utc>>,
+    pub frame_delay_ms: Option<i32>,
+}
+
+/// Picks the frames of a time-lapse from photos sorted by capture time. Only
+/// the first photo of each day is used, and of those at most `max_frames`,
+/// chosen to be as evenly spaced in time as possible.
+pub fn select_frames(photos: &[PlantPhoto], max_frames: usize) -> Vec<&PlantPhoto> {
+    let mut daily: Vec<&PlantPhoto> = Vec::new();
+    for photo in photos {
+        let same_day = daily
+            .last()
+            .is_some_and(|last| last.captured_at.date_naive() == photo.captured_at.date_naive());
+        if !same_day {
+            daily.push(photo);
+        }
+    }
+
+    let count = daily.len().min(max_frames);
+    if count < 2 {
+        return daily;
+    }
+
+    let first = daily[0].captured_at;
+    let span = daily[daily.len() - 1].captured_at - first;
+
+    // For each evenly spaced target, take the closest photo after the
+    // previous pick that still leaves one photo for every later target.
+    let mut frames = Vec::with_capacity(count);
+    let mut next = 0;
+    for k in 0..count {
+        let target = first + span * k as i32 / (count - 1) as i32;
+        let last_allowed = daily.len() - (count - k);
+        let best = (next..=last_allowed)
+            .min_by_key(|&i| (daily[i].captured_at - target).abs())
+            .unwrap_or(next);
+        frames.push(daily[best]);
+        next = best + 1;
+    }
+
+    frames
+}
+
+/// Scales `width` x `height` down so the longer side is at most
+/// [`FRAME_SIZE`].
+pub fn frame_size(width: u32, height: u32) -> (u32, u32) {
+    let longest = width.max(height);
+    if longest <= FRAME_SIZE {
+        return (width, height);
+    }
+
+    let scale =
+        |side: u32| ((u64::from(side) * u64::from(FRAME_SIZE)) / u64::from(longest)).max(1) as u32;
+    (scale(width), scale(height))
+}
+
+/// Frames can be shifted by up to this share of their size, in either
+/// direction, to line up with the previous frame.
+const ALIGN_MARGIN: f32 = 0.1;
+
+/// Longer side of the grayscale copies the shift is searched on.
+const ALIGN_SIZE: u32 = 64;
+
+struct PreparedFrame {
+    image: RgbaImage,
+    /// Small grayscale copy of the frame, the next frame is aligned with it.
+    reference: GrayImage,
+}
+
+/// Decodes a photo and brings it to the frame size, cropping around the
+/// center when its aspect ratio differs from the first frame. The crop is
+/// shifted to best match `previous`, so a plant that was photographed from
+/// a slightly different spot does not jump around.
+fn prepare_frame(
+    data: &[u8],
+    (width, height): (u32, u32),
+    previous: Option<&GrayImage>,
+) -> Result<PreparedFrame, ServiceError> {
+    let (_, image) = photo::decode(data)?;
+    let margin = |side: u32| (side as f32 * ALIGN_MARGIN) as u32;
+    let padded = image.resize_to_fill(
+        width + 2 * margin(width),
+        height + 2 * margin(height),
+        FilterType::Triangle,
+    );
+
+    // The shift is searched on small copies and scaled back up, which is
+    // precise enough for photos taken by hand.
+    let step = width.max(height).div_ceil(ALIGN_SIZE);
+    let small = padded
+        .resize_exact(
+            padded.width() / step,
+            padded.height() / step,
+            FilterType::Triangle,
+        )
+        .to_luma8();
+    let (small_width, small_height) = (width / step, height / step);
+    let (x, y) = match previous {
+        Some(previous) => best_offset(&small, previous),
+        None => (
+            (small.width() - small_width) / 2,
+            (small.height() - small_height) / 2,
+        ),
+    };
+
+    let image = imageops::crop_imm(
+        &padded.to_rgba8(),
+        (x * step).min(padded.width() - width),
+        (y * step).min(padded.height() - height),
+        width,
+        height,
+    )
+    .to_image();
+    let reference = imageops::crop_imm(&small, x, y, small_width, small_height).to_image();
+
+    Ok(PreparedFrame { image, reference })
+}
+
+/// Returns where in `image` the window of the size of `reference` differs
+/// least from it. Ties go to the most central window.
+fn best_offset(image: &GrayImage, reference: &GrayImage) -> (u32, u32) {
+    let max_x = image.width().saturating_sub(reference.width());
+    let max_y = image.height().saturating_sub(reference.height());
+    let difference = |x: u32, y: u32| -> u64 {
+        reference
+            .enumerate_pixels()
+            .filter_map(|(rx, ry, pixel)| {
+                let other = image.get_pixel_checked(x + rx, y + ry)?;
+                Some(u64::from(pixel[0].abs_diff(other[0])))
+            })
+            .sum()
+    };
+
+    (0..=max_y)
+        .flat_map(|y| (0..=max_x).map(move |x| (x, y)))
+        .min_by_key(|&(x, y)| {
+            let off_center = (2 * x).abs_diff(max_x) + (2 * y).abs_diff(max_y);
+            (difference(x, y), off_center)
+        })
+        .unwrap_or((0, 0))
+}
+
+fn encode_gif(frames: Vec<RgbaImage>, frame_delay_ms: i32) -> Result<Vec<u8>, ServiceError> {
+    let delay = Delay::from_numer_denom_ms(frame_delay_ms as u32, 1);
+    let mut data = Cursor::new(Vec::new());
+
+    {
+        // Speed 10 trades some palette quality for a much faster encode.
+        let mut encoder = GifEncoder::new_with_speed(&mut data, 10);
+        encoder
+            .set_repeat(Repeat::Infinite)
+            .map_err(|e| ServiceError::Internal(e.into()))?;
+        encoder
+            .encode_frames(
+                frames
+                    .into_iter()
+                    .map(|frame| Frame::from_parts(frame, 0, 0, delay)),
+            )
+            .map_err(|e| ServiceError::Internal(e.into()))?;
+    }
+
+    Ok(data.into_inner())
+}
+
+async fn get_photos_between(
+    pool: &Pool<Postgres>,
+    plant_id: i32,
+    start: Option<DateTime<Utc>>,
+    end: Option<DateTime<Utc>>,
+) -> Result<Vec<PlantPhoto>, ServiceError> {
+    let photos = sqlx::query_as!(
+        PlantPhotoDb,
+        "SELECT * FROM plant_photo
+        WHERE plant_id = $1
+        AND ($2::timestamptz IS NULL OR captured_at >= $2)
+        AND ($3::timestamptz IS NULL OR captured_at <= $3)
+        ORDER BY captured_at, id",
+        plant_id,
+        start,
+        end
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(PlantPhoto::from)
+    .collect();
+
+    Ok(photos)
+}
+
+fn not_enough_photos() -> ServiceError {
+    ServiceError::conflict(
+        "NOT_ENOUGH_PHOTOS",
+        "A time-lapse needs photos from at least two different days",
+    )
+}
+
+/// Queues a time-lapse of the plant's photos between the given dates. The
+/// job is picked up by the worker started with [`spawn_worker`].
+pub async fn enqueue_timelapse(
+    pool: &Pool<Postgres>,
+    user_id: i32,
+    plant_id: i32,
+    request: &TimelapseRequest,
+) -> Result<Timelapse, ServiceError> {
+    let frame_delay_ms = request.frame_delay_ms.unwrap_or(DEFAULT_FRAME_DELAY_MS);
+    if !(MIN_FRAME_DELAY_MS..=MAX_FRAME_DELAY_MS).contains(&frame_delay_ms) {
+        return Err(ServiceError::validation(
+            "frameDelayMs",
+            format!(
+                "must be between {} and {}",
+                MIN_FRAME_DELAY_MS, MAX_FRAME_DELAY_MS
+            ),
+        ));
+    }
+    if let (Some(start), Some(end)) = (request.start, request.end)
+        && start > end
+    {
+        return Err(ServiceError::validation("from", "must not be after to"));
+    }
+
+    sqlx::query_scalar!(
+        "SELECT id FROM plant WHERE id = $1 AND owner_id = $2",
+        plant_id,
+        user_id
+    )
+    .fetch_optional(pool)
+    .await?
+    .ok_or(ServiceError::NotFound("Plant"))?;
+
+    let photos = get_photos_between(pool, plant_id, request.start, request.end).await?;
+    let frames = select_frames(&photos, MAX_FRAMES);
+    let [first, .., last] = frames.as_slice() else {
+        return Err(not_enough_photos());
+    };
+
+    let timelapse = sqlx::query_as!(
+        TimelapseDb,
+        "INSERT INTO timelapse (plant_id, range_start, range_end, frame_delay_ms)
+        VALUES ($1, $2, $3, $4)
+        RETURNING *",
+        plant_id,
+        request.start.unwrap_or(first.captured_at),
+        request.end.unwrap_or(last.captured_at),
+        frame_delay_ms
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(Timelapse::from(timelapse))
+}
+
+/// Renders the job's GIF and stores it, returning its key and frame count.
+async fn render(
+    pool: &Pool<Postgres>,
+    storage: &dyn Storage,
+    timelapse: &Timelapse,
+) -> Result<(String, i32), ServiceError> {
+    let photos = get_photos_between(
+        pool,
+        timelapse.plant_id,
+        Some(timelapse.range_start),
+        Some(timelapse.range_end),
+    )
+    .await?;
+    let selected = select_frames(&photos, MAX_FRAMES);
+    let [first, _, ..] = selected.as_slice() else {
+        return Err(not_enough_photos());
+    };
+    let size = frame_size(first.width as u32, first.height as u32);
+
+    // Originals are loaded one at a time and shrunk right away, so only the
+    // small frames are kept in memory.
+    let mut frames = Vec::with_capacity(selected.len());
+    let mut previous = None;
+    for photo in &selected {
+        let data = storage.get(&photo.image_key).await?;
+        let frame =
+            tokio::task::spawn_blocking(move || prepare_frame(&data, size, previous.as_ref()))
+                .await
+                .map_err(|e| ServiceError::Internal(e.into()))??;
+        frames.push(frame.image);
+        previous = Some(frame.reference);
+    }
+
+    let frame_count = frames.len() as i32;
+    let frame_delay_ms = timelapse.frame_delay_ms;
+    let gif = tokio::task::spawn_blocking(move || encode_gif(frames, frame_delay_ms))
+        .await
+        .map_err(|e| ServiceError::Internal(e.into()))??;
+
+    let key = format!(
+        "plants/{}/timelapses/{}.gif",
+        timelapse.plant_id, timelapse.id
+    );
+    storage.put(&key, Bytes::from(gif)).await?;
+
+    Ok((key, frame_count))
+}
+
+/// Claims the oldest pending job and runs it. Returns whether there was one.
+pub async fn run_next_job(
+    pool: &Pool<Postgres>,
+    storage: &dyn Storage,
+) -> Result<bool, ServiceError> {
+    // A job that has been running for this long belonged to a replica that
+    // went away.
+    sqlx::query!(
+        "UPDATE timelapse SET status = 'pending', started_at = NULL
+        WHERE status = 'running' AND started_at < now() - interval '30 minutes'"
+    )
+    .execute(pool)
+    .await?;
+
+    let Some(timelapse) = sqlx::query_as!(
+        TimelapseDb,
+        "UPDATE timelapse SET status = 'running', started_at = now()
+        WHERE id = (
+            SELECT id FROM timelapse
+            WHERE status = 'pending'
+            ORDER BY id
+            FOR UPDATE SKIP LOCKED
+            LIMIT 1
+        )
+        RETURNING *"
+    )
+    .fetch_optional(pool)
+    .await?
+    .map(Timelapse::from) else {
+        return Ok(false);
+    };
+
+    match render(pool, storage, &timelapse).await {
+        Ok((key, frame_count)) => {
+            let result = sqlx::query!(
+                "UPDATE timelapse
+                SET status = 'completed', output_key = $2, frame_count = $3, finished_at = now()
+                WHERE id = $1",
+                timelapse.id,
+                key,
+                frame_count
+            )
+            .execute(pool)
+            .await?;
+            // The plant's time-lapses were deleted while this one rendered,
+            // nothing refers to the file anymore.
+            if result.rows_affected() == 0 {
+                if let Err(e) = storage.delete(&key).await {
+                    error!("Could not delete time-lapse {key}: {e:#}");
+                }
+                return Ok(true);
+            }
+            info!(
+                "Rendered time-lapse {} with {} frames",
+                timelapse.id, frame_count
+            );
+        }
+        Err(e) => {
+            sqlx::query!(
+                "UPDATE timelapse SET status = 'failed', error = $2, finished_at = now()
+                WHERE id = $1",
+                timelapse.id,
+                e.to_string()
+            )
+            .execute(pool)
+            .await?;
+            error!("Failed to render time-lapse {}: {}", timelapse.id, e);
+        }
+    }
+
+    Ok(true)
+}
+
+/// Runs queued time-lapse jobs, checking for new ones every `interval`.
+pub fn spawn_worker(pool: Pool<Postgres>, storage: Arc<dyn Storage>, interval: Duration) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            loop {
+                match run_next_job(&pool, storage.as_ref()).await {
+                    Ok(true) => {}
+                    Ok(false) => break,
+                    Err(e) => {
+                        error!("Failed to run time-lapse jobs: {}", e);
+                        break;
+                    }
+                }
+            }
+        }
+    });
+}
+
+/// Returns the plant's time-lapses, newest first.
+pub async fn get_timelapses(
+    pool: &Pool<Postgres>,
+    user_id: i32,
+    plant_id: i32,
+) -> Result<Vec<Timelapse>, ServiceError> {
+    sqlx::query_scalar!(
+        "SELECT id FROM plant WHERE id = $1 AND owner_id = $2",
+        plant_id,
+        user_id
+    )
+    .fetch_optional(pool)
+    .await?
+    .ok_or(ServiceError::NotFound("Plant"))?;
+
+    let timelapses = sqlx::query_as!(
+        TimelapseDb,
+        "SELECT * FROM timelapse WHERE plant_id = $1 ORDER BY created_at DESC, id DESC",
+        plant_id
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(Timelapse::from)
+    .collect();
+
+    Ok(timelapses)
+}
+
+pub async fn get_timelapse(
+    pool: &Pool<Postgres>,
+    user_id: i32,
+    plant_id: i32,
+    timelapse_id: i32,
+) -> Result<Timelapse, ServiceError> {
+    sqlx::query_as!(
+        TimelapseDb,
+        "SELECT timelapse.* FROM timelapse
+        JOIN plant ON plant.id = timelapse.plant_id
+        WHERE timelapse.id = $1 AND timelapse.plant_id = $2 AND plant.owner_id = $3",
+        timelapse_id,
+        plant_id,
+        user_id
+    )
+    .fetch_optional(pool)
+    .await?
+    .map(Timelapse::from)
+    .ok_or(ServiceError::NotFound("Time-lapse"))
+}
+
+/// Loads the rendered GIF of a completed time-lapse.
+pub async fn read_timelapse(
+    storage: &dyn Storage,
+    timelapse: &Timelapse,
+) -> Result<Bytes, ServiceError> {
+    let key = timelapse.output_key.as_deref().ok_or_else(|| {
+        ServiceError::conflict(
+            "TIMELAPSE_NOT_READY",
+            format!("Time-lapse is {}", timelapse.status.as_str()),
+        )
+    })?;
+
+    Ok(storage.get(key).await?)
+}
+
+/// Deletes the plant's time-lapses, ahead of deleting the plant itself.
+pub async fn delete_plant_timelapses(
+    pool: &Pool<Postgres>,
+    storage: &dyn Storage,
+    user_id: i32,
+    plant_id: i32,
+) -> Result<(), ServiceError> {
+    let keys = sqlx::query_scalar!(
+        "DELETE FROM timelapse
+        USING plant
+        WHERE timelapse.plant_id = plant.id
+        AND plant.id = $1
+        AND plant.owner_id = $2
+        RETURNING timelapse.output_key",
+        plant_id,
+        user_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    // Best effort, as for photos: a leftover file is only wasted space.
+    for key in keys.into_iter().flatten() {
+        if let Err(e) = storage.delete(&key).await {
+            error!("Could not delete time-lapse {key}: {e:#}");
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{AnimationDecoder, ImageFormat, codecs::gif::GifDecoder};
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::{
+        entities::{CaptureSource, TimelapseStatus},
+        services::{
+            photo::PhotoUpload,
+            plant::{self, PlantInput},
+            storage::LocalStorage,
+        },
+    };
+
+    fn at(rfc3339: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(rfc3339)
+            .expect("date")
+            .with_timezone(&Utc)
+    }
+
+    fn photo(id: i32, captured_at: &str) -> PlantPhoto {
+        PlantPhoto {
+            id,
+            plant_id: 1,
+            image_key: format!("{id}.png"),
+            thumbnail_key: format!("{id}-thumbnail.jpg"),
+            content_type: "image/png".to_string(),
+            width: 640,
+            height: 480,
+            captured_at: at(captured_at),
+            capture_source: CaptureSource::Explicit,
+            created_at: at(captured_at),
+        }
+    }
+
+    fn ids(frames: &[&PlantPhoto]) -> Vec<i32> {
+        frames.iter().map(|photo| photo.id).collect()
+    }
+
+    #[test]
+    fn keeps_the_first_photo_of_each_day() {
+        let photos = [
+            photo(1, "2026-03-01T08:00:00Z"),
+            photo(2, "2026-03-01T18:00:00Z"),
+            photo(3, "2026-03-02T08:00:00Z"),
+            photo(4, "2026-03-04T08:00:00Z"),
+            photo(5, "2026-03-04T09:00:00Z"),
+        ];
+
+        assert_eq!(ids(&select_frames(&photos, MAX_FRAMES)), vec![1, 3, 4]);
+    }
+
+    #[test]
+    fn samples_evenly_spaced_frames() {
+        // Daily photos for ten days, then one three weeks later.
+        let mut photos: Vec<PlantPhoto> = (1..=10)
+            .map(|day| photo(day, &format!("2026-03-{day:02}T08:00:00Z")))
+            .collect();
+        photos.push(photo(11, "2026-03-31T08:00:00Z"));
+
+        // Targets are March 1st, 11th, 21st and 31st. March 10th would be
+        // closest to the second one, but is needed for the third.
+        assert_eq!(ids(&select_frames(&photos, 4)), vec![1, 9, 10, 11]);
+    }
+
+    #[test]
+    fn frames_are_scaled_down_only() {
+        assert_eq!(frame_size(4032, 3024), (FRAME_SIZE, 360));
+        assert_eq!(frame_size(3024, 4032), (360, FRAME_SIZE));
+        assert_eq!(frame_size(320, 200), (320, 200));
+    }
+
+    #[test]
+    fn frames_follow_the_previous_one() {
+        // A bright square that moved between the photos.
+        let photo = |left: u32, top: u32| {
+            let image = RgbaImage::from_fn(200, 150, |x, y| {
+                let inside = (left..left + 40).contains(&x) && (top..top + 40).contains(&y);
+                image::Rgba(if inside { [250; 4] } else { [20, 20, 20, 255] })
+            });
+            let mut png = Cursor::new(Vec::new());
+            image.write_to(&mut png, ImageFormat::Png).expect("png");
+            png.into_inner()
+        };
+        let center = |frame: &RgbaImage| {
+            let bright: Vec<_> = frame
+                .enumerate_pixels()
+                .filter(|(_, _, pixel)| pixel[0] > 128)
+                .map(|(x, y, _)| (x, y))
+                .collect();
+            let count = bright.len() as u32;
+            (
+                bright.iter().map(|(x, _)| x).sum::<u32>() / count,
+                bright.iter().map(|(_, y)| y).sum::<u32>() / count,
+            )
+        };
+
+        let first = prepare_frame(&photo(80, 55), (200, 150), None).expect("frame");
+        let second =
+            prepare_frame(&photo(90, 61), (200, 150), Some(&first.reference)).expect("frame");
+
+        assert_eq!(second.image.dimensions(), (200, 150));
+        let (x1, y1) = center(&first.image);
+        let (x2, y2) = center(&second.image);
+        assert!(x1.abs_diff(x2) <= 4 && y1.abs_diff(y2) <= 4);
+    }
+
+    async fn plant_with_photos(pool: &PgPool, storage: &dyn Storage, dates: &[&str]) -> (i32, i32) {
+        let user_id = sqlx::query_scalar!(
+            r#"INSERT INTO "user" (email, password_hash) VALUES ('a@example.com', 'unused') RETURNING id"#
+        )
+        .fetch_one(pool)
+        .await
+        .expect("user");
+        let plant = plant::create_plant(pool, &PlantInput::new("Monstera"), user_id)
+            .await
+            .expect("plant");
+
+        for (i, date) in dates.iter().enumerate() {
+            let shade = (i * 60) as u8;
+            let mut png = Cursor::new(Vec::new());
+            RgbaImage::from_pixel(64, 48, image::Rgba([shade, 160, 60, 255]))
+                .write_to(&mut png, ImageFormat::Png)
+                .expect("png");
+            photo::upload_photo(
+                pool,
+                storage,
+                user_id,
+                plant.id,
+                PhotoUpload {
+                    data: Bytes::from(png.into_inner()),
+                    captured_at: Some(at(date)),
+                },
+            )
+            .await
+            .expect("photo");
+        }
+
+        (user_id, plant.id)
+    }
+
+    #[sqlx::test]
+    async fn renders_a_queued_timelapse(pool: PgPool) {
+        let storage = LocalStorage::new(
+            std::env::temp_dir().join(format!("timelapse-{}", uuid::Uuid::new_v4())),
+        );
+        let (user_id, plant_id) = plant_with_photos(
+            &pool,
+            &storage,
+            &[
+                "2026-03-01T08:00:00Z",
+                "2026-03-01T09:00:00Z",
+                "2026-03-05T08:00:00Z",
+                "2026-03-09T08:00:00Z",
+            ],
+        )
+        .await;
+
+        let queued = enqueue_timelapse(&pool, user_id, plant_id, &TimelapseRequest::default())
+            .await
+            .expect("enqueue");
+        assert_eq!(queued.status, TimelapseStatus::Pending);
+        assert_eq!(queued.range_start, at("2026-03-01T08:00:00Z"));
+        assert_eq!(queued.range_end, at("2026-03-09T08:00:00Z"));
+        assert!(matches!(
+            read_timelapse(&storage, &queued).await,
+            Err(ServiceError::Conflict {
+                code: "TIMELAPSE_NOT_READY",
+                ..
+            })
+        ));
+
+        assert!(run_next_job(&pool, &storage).await.expect("run"));
+        assert!(!run_next_job(&pool, &storage).await.expect("run"));
+
+        let done = get_timelapse(&pool, user_id, plant_id, queued.id)
+            .await
+            .expect("timelapse");
+        assert_eq!(done.status, TimelapseStatus::Completed);
+        assert_eq!(done.frame_count, Some(3));
+
+        let gif = read_timelapse(&storage, &done).await.expect("gif");
+        let frames = GifDecoder::new(Cursor::new(gif))
+            .expect("decoder")
+            .into_frames()
+            .collect_frames()
+            .expect("frames");
+        assert_eq!(frames.len(), 3);
+        assert_eq!(frames[0].buffer().dimensions(), (64, 48));
+    }
+
+    #[sqlx::test]
+    async fn needs_photos_from_two_days(pool: PgPool) {
+        let storage = LocalStorage::new(
+            std::env::temp_dir().join(format!("timelapse-{}", uuid::Uuid::new_v4())),
+        );
+        let (user_id, plant_id) = plant_with_photos(
+            &pool,
+            &storage,
+            &["2026-03-01T08:00:00Z", "2026-03-01T18:00:00Z"],
+        )
+        .await;
+
+        assert!(matches!(
+            enqueue_timelapse(&pool, user_id, plant_id, &TimelapseRequest::default()).await,
+            Err(ServiceError::Conflict {
+                code: "NOT_ENOUGH_PHOTOS",
+                ..
+            })
+        ));
+        assert!(matches!(
+            enqueue_timelapse(
+                &pool,
+                user_id,
+                plant_id,
+                &TimelapseRequest {
+                    frame_delay_ms: Some(5),
+                    ..Default::default()
+                }
+            )
+            .await,
+            Err(ServiceError::Validation(_))
+        ));
+    }
+}
@@ -0,0 +1,14 @@
+use rand::Rng;
+use sha2::{Digest, Sha256};
+
+/// Generates a random 256-bit secret, hex encoded.
+pub fn generate_token() -> String {
+    let bytes: [u8; 32] = rand::rng().random();
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Tokens from [`generate_token`] are random and high-entropy, so a plain
+/// SHA-256 is enough to keep them unusable if the table storing them leaks.
+pub fn hash_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
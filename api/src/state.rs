@@ -0,0 +1,23 @@
+use std::sync::Arc;
+
+use axum::extract::FromRef;
+use sqlx::PgPool;
+
+use crate::{
+    controllers::TrustProxy,
+    services::{
+        events::EventBus, identify::PlantIdentifier, jwt::JwtKeys,
+        plausibility::PlausibilityLimits, storage::Storage,
+    },
+};
+
+#[derive(Clone, FromRef)]
+pub struct AppState {
+    pub pool: PgPool,
+    pub jwt_keys: Arc<JwtKeys>,
+    pub events: EventBus,
+    pub storage: Arc<dyn Storage>,
+    pub identifier: Arc<dyn PlantIdentifier>,
+    pub measurement_limits: Arc<PlausibilityLimits>,
+    pub trust_proxy: TrustProxy,
+}